@@ -0,0 +1,61 @@
+//! Deserialization of request bodies of different sizes.
+//!
+//! `Xml::from_bytes` takes the same path as the extractor once the body is buffered. With the
+//! quick-xml backend, the `from_str` and `from_reader` cases show what that path costs on its
+//! own for small bodies, read straight from the slice or through quick-xml's buffered reader.
+//! Compare runs across changes with `cargo bench -- --save-baseline <name>` and
+//! `--baseline <name>`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rustcms_axum_xml::Xml;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Item {
+    name: String,
+    quantity: u32,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Order {
+    id: u64,
+    #[serde(default, rename = "Item")]
+    items: Vec<Item>,
+}
+
+fn order(items: usize) -> String {
+    let items = "<Item><name>tea</name><quantity>2</quantity></Item>".repeat(items);
+    format!("<Order><id>7</id>{items}</Order>")
+}
+
+fn from_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_bytes");
+    for items in [0, 4, 64, 1024] {
+        let body = order(items);
+        group.bench_with_input(BenchmarkId::from_parameter(body.len()), &body, |b, body| {
+            b.iter(|| Xml::<Order>::from_bytes(black_box(body.as_bytes())).unwrap())
+        });
+    }
+    group.finish();
+}
+
+#[cfg(feature = "backend-quick-xml")]
+fn small_body_paths(c: &mut Criterion) {
+    let body = order(4);
+    let mut group = c.benchmark_group("small_body");
+    group.bench_function("from_str", |b| {
+        b.iter(|| quick_xml::de::from_str::<Order>(black_box(&body)).unwrap())
+    });
+    group.bench_function("from_reader", |b| {
+        b.iter(|| quick_xml::de::from_reader::<_, Order>(black_box(body.as_bytes())).unwrap())
+    });
+    group.finish();
+}
+
+#[cfg(not(feature = "backend-quick-xml"))]
+fn small_body_paths(_: &mut Criterion) {}
+
+criterion_group!(benches, from_bytes, small_body_paths);
+criterion_main!(benches);
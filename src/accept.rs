@@ -0,0 +1,132 @@
+use axum_core::response::{IntoResponse, Response};
+use http::{header, HeaderMap, StatusCode};
+use mime::Mime;
+use serde::Serialize;
+
+use crate::{Xml, XmlResponse};
+
+/// Response that is XML, or `406 Not Acceptable` when the request's `Accept` header rules XML
+/// out.
+///
+/// A response can't see the request it answers, so take the request's headers with the
+/// [`HeaderMap`] extractor and pass them in, as with [`Xml::if_accepted`]. XML is acceptable
+/// when `application/xml` or `text/xml` gets a non-zero quality value, directly or through
+/// `application/*` or `*/*`, and when there is no `Accept` header at all.
+///
+/// ```rust
+/// use axum::{http::HeaderMap, routing::get, Router};
+/// use serde::Serialize;
+/// use rustcms_axum_xml::{AcceptedXml, Xml};
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// async fn get_user(headers: HeaderMap) -> AcceptedXml<User> {
+///     Xml::if_accepted(User { name: "ferris".to_owned() }, &headers)
+/// }
+///
+/// let app: Router = Router::new().route("/user", get(get_user));
+/// ```
+#[derive(Debug, Clone)]
+pub struct AcceptedXml<T> {
+    value: T,
+    accepted: bool,
+}
+
+impl<T> AcceptedXml<T> {
+    /// Respond with `value` if the `Accept` header in `headers` allows XML.
+    pub fn new(value: T, headers: &HeaderMap) -> Self {
+        Self {
+            value,
+            accepted: xml_quality(headers) > 0.0,
+        }
+    }
+
+    /// Whether the response will be XML rather than `406 Not Acceptable`.
+    pub fn is_accepted(&self) -> bool {
+        self.accepted
+    }
+}
+
+impl<T> Xml<T> {
+    /// Respond with `value` as XML, or with `406 Not Acceptable` when the client doesn't
+    /// accept XML.
+    ///
+    /// See [`AcceptedXml`].
+    pub fn if_accepted(value: T, headers: &HeaderMap) -> AcceptedXml<T> {
+        AcceptedXml::new(value, headers)
+    }
+}
+
+impl<T> IntoResponse for AcceptedXml<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        if self.accepted {
+            XmlResponse::new(self.value).into_response()
+        } else {
+            (
+                StatusCode::NOT_ACCEPTABLE,
+                "Response is only available as `application/xml`",
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Best quality value the `Accept` header gives to `application/xml` or `text/xml`.
+pub(crate) fn xml_quality(headers: &HeaderMap) -> f32 {
+    quality(headers, &mime::TEXT_XML).max(quality(
+        headers,
+        &"application/xml".parse().expect("valid MIME type"),
+    ))
+}
+
+/// Quality value the `Accept` header gives to `candidate`.
+///
+/// The most specific matching media range decides, so `*/*, application/xml;q=0` refuses XML.
+/// A missing header accepts anything. Media ranges that fail to parse are ignored.
+pub(crate) fn quality(headers: &HeaderMap, candidate: &Mime) -> f32 {
+    let mut accept = headers.get_all(header::ACCEPT).iter().peekable();
+    if accept.peek().is_none() {
+        return 1.0;
+    }
+
+    accept
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|range| range.trim().parse::<Mime>().ok())
+        .filter_map(|range| {
+            let specificity = match (range.type_(), range.subtype()) {
+                (mime::STAR, mime::STAR) => 0,
+                (type_, mime::STAR) if type_ == candidate.type_() => 1,
+                (type_, subtype)
+                    if type_ == candidate.type_() && subtype == candidate.subtype() =>
+                {
+                    2
+                }
+                _ => return None,
+            };
+            let q = range
+                .get_param("q")
+                .and_then(|q| q.as_str().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((specificity, q))
+        })
+        .fold(
+            None,
+            |best: Option<(u8, f32)>, (specificity, q)| match best {
+                Some((best_specificity, best_q)) if best_specificity > specificity => {
+                    Some((best_specificity, best_q))
+                }
+                Some((best_specificity, best_q)) if best_specificity == specificity => {
+                    Some((specificity, best_q.max(q)))
+                }
+                _ => Some((specificity, q)),
+            },
+        )
+        .map_or(0.0, |(_, q)| q)
+}
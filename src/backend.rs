@@ -0,0 +1,245 @@
+//! The serde implementation behind [`Xml`](crate::Xml), chosen with the `backend-quick-xml`
+//! (default) or `backend-serde-xml-rs` feature.
+//!
+//! Everything else in the crate — limits, scanning, transcoding and re-indenting — works on raw
+//! events from quick-xml's reader and writer, so only the serde step changes with the backend.
+
+use serde::{Deserialize, Serialize};
+use std::{fmt, io::BufRead};
+
+#[cfg(all(feature = "backend-quick-xml", feature = "backend-serde-xml-rs"))]
+compile_error!("features `backend-quick-xml` and `backend-serde-xml-rs` are mutually exclusive");
+
+#[cfg(not(any(feature = "backend-quick-xml", feature = "backend-serde-xml-rs")))]
+compile_error!("one of the features `backend-quick-xml` or `backend-serde-xml-rs` must be enabled");
+
+/// Error returned by the selected backend.
+#[cfg(feature = "backend-quick-xml")]
+pub(crate) type Error = quick_xml::DeError;
+
+/// Error returned by the selected backend.
+#[cfg(feature = "backend-serde-xml-rs")]
+pub(crate) type Error = serde_xml_rs::Error;
+
+/// Build a backend error carrying `msg`.
+pub(crate) fn custom(msg: impl fmt::Display) -> Error {
+    <Error as serde::de::Error>::custom(msg)
+}
+
+/// Build a backend error carrying `msg` that [`is_syntax_error`] counts as the document not
+/// being acceptable XML, for checks the crate makes on the document itself.
+#[cfg(feature = "backend-quick-xml")]
+pub(crate) fn syntax_error(msg: impl fmt::Display) -> Error {
+    Error::InvalidXml(quick_xml::Error::UnexpectedToken(msg.to_string()))
+}
+
+/// Build a backend error carrying `msg` that [`is_syntax_error`] counts as the document not
+/// being acceptable XML, for checks the crate makes on the document itself.
+#[cfg(feature = "backend-serde-xml-rs")]
+pub(crate) fn syntax_error(msg: impl fmt::Display) -> Error {
+    let position = xml::common::TextPosition::new();
+    Error::Syntax {
+        source: (&position, msg.to_string()).into(),
+    }
+}
+
+/// Whether `err` means the document isn't well-formed XML, as opposed to well-formed XML that
+/// doesn't fit the type it is deserialized into.
+#[cfg(feature = "backend-quick-xml")]
+pub(crate) fn is_syntax_error(err: &Error) -> bool {
+    matches!(err, Error::InvalidXml(_) | Error::UnexpectedEof)
+}
+
+/// Whether `err` means the document isn't well-formed XML, as opposed to well-formed XML that
+/// doesn't fit the type it is deserialized into.
+#[cfg(feature = "backend-serde-xml-rs")]
+pub(crate) fn is_syntax_error(err: &Error) -> bool {
+    matches!(err, Error::Syntax { .. })
+}
+
+/// Convert a failure of quick-xml's event writer into a backend error.
+#[cfg(feature = "backend-quick-xml")]
+pub(crate) fn writer_error(err: quick_xml::Error) -> Error {
+    err.into()
+}
+
+/// Convert a failure of quick-xml's event writer into a backend error.
+#[cfg(feature = "backend-serde-xml-rs")]
+pub(crate) fn writer_error(err: quick_xml::Error) -> Error {
+    custom(err)
+}
+
+/// Deserialize from `reader`.
+///
+/// The reader hands out owned strings only, so a value borrowing `&str` fields fails to
+/// deserialize.
+#[cfg(feature = "backend-quick-xml")]
+pub(crate) fn from_reader<'de, R, T>(reader: R) -> Result<T, Error>
+where
+    R: BufRead,
+    T: Deserialize<'de>,
+{
+    T::deserialize(&mut quick_xml::de::Deserializer::from_reader(reader))
+}
+
+/// Deserialize from `reader`.
+#[cfg(feature = "backend-serde-xml-rs")]
+pub(crate) fn from_reader<'de, R, T>(reader: R) -> Result<T, Error>
+where
+    R: BufRead,
+    T: Deserialize<'de>,
+{
+    serde_xml_rs::from_reader(reader)
+}
+
+/// Deserialize from `reader` like [`from_reader`], calling `ignored` with the path of every
+/// element or attribute the value had no field for.
+#[cfg(feature = "backend-quick-xml")]
+pub(crate) fn from_reader_with_ignored<'de, R, T, F>(reader: R, ignored: F) -> Result<T, Error>
+where
+    R: BufRead,
+    T: Deserialize<'de>,
+    F: FnMut(serde_ignored::Path<'_>),
+{
+    serde_ignored::deserialize(
+        &mut quick_xml::de::Deserializer::from_reader(reader),
+        ignored,
+    )
+}
+
+/// Deserialize from `reader` like [`from_reader`], calling `ignored` with the path of every
+/// element or attribute the value had no field for.
+#[cfg(feature = "backend-serde-xml-rs")]
+pub(crate) fn from_reader_with_ignored<'de, R, T, F>(reader: R, ignored: F) -> Result<T, Error>
+where
+    R: BufRead,
+    T: Deserialize<'de>,
+    F: FnMut(serde_ignored::Path<'_>),
+{
+    serde_ignored::deserialize(
+        &mut serde_xml_rs::Deserializer::new_from_reader(reader),
+        ignored,
+    )
+}
+
+/// Deserialize from a string, letting the value borrow from `xml` where the backend can.
+#[cfg(feature = "backend-quick-xml")]
+pub(crate) fn from_str<'de, T>(xml: &'de str) -> Result<T, Error>
+where
+    T: Deserialize<'de>,
+{
+    quick_xml::de::from_str(xml)
+}
+
+/// Deserialize from a string, letting the value borrow from `xml` where the backend can.
+///
+/// serde-xml-rs hands out owned strings only, so borrowed `&str` fields fail to deserialize.
+#[cfg(feature = "backend-serde-xml-rs")]
+pub(crate) fn from_str<'de, T>(xml: &'de str) -> Result<T, Error>
+where
+    T: Deserialize<'de>,
+{
+    serde_xml_rs::from_str(xml)
+}
+
+/// Deserialize from a string like [`from_str`], calling `ignored` with the path of every
+/// element or attribute the value had no field for.
+#[cfg(feature = "backend-quick-xml")]
+pub(crate) fn from_str_with_ignored<'de, T, F>(xml: &'de str, ignored: F) -> Result<T, Error>
+where
+    T: Deserialize<'de>,
+    F: FnMut(serde_ignored::Path<'_>),
+{
+    serde_ignored::deserialize(&mut quick_xml::de::Deserializer::from_str(xml), ignored)
+}
+
+/// Deserialize from a string like [`from_str`], calling `ignored` with the path of every
+/// element or attribute the value had no field for.
+#[cfg(feature = "backend-serde-xml-rs")]
+pub(crate) fn from_str_with_ignored<'de, T, F>(xml: &'de str, ignored: F) -> Result<T, Error>
+where
+    T: Deserialize<'de>,
+    F: FnMut(serde_ignored::Path<'_>),
+{
+    from_reader_with_ignored(xml.as_bytes(), ignored)
+}
+
+/// Serialize `value` as a compact document without an XML declaration, into a buffer which
+/// starts out with room for `capacity` bytes.
+#[cfg(feature = "backend-quick-xml")]
+pub(crate) fn to_vec<T>(
+    value: &T,
+    root_name: Option<&str>,
+    capacity: usize,
+) -> Result<Vec<u8>, Error>
+where
+    T: Serialize + ?Sized,
+{
+    let mut body = Vec::with_capacity(capacity);
+    value.serialize(&mut quick_xml::se::Serializer::with_root(
+        quick_xml::Writer::new(&mut body),
+        root_name,
+    ))?;
+    Ok(body)
+}
+
+/// Serialize `value` as a compact document without an XML declaration, into a buffer which
+/// starts out with room for `capacity` bytes.
+///
+/// serde-xml-rs always writes a declaration and names the root after the type, so the output
+/// is passed through quick-xml once more to drop the one and rename the other.
+#[cfg(feature = "backend-serde-xml-rs")]
+pub(crate) fn to_vec<T>(
+    value: &T,
+    root_name: Option<&str>,
+    capacity: usize,
+) -> Result<Vec<u8>, Error>
+where
+    T: Serialize + ?Sized,
+{
+    use quick_xml::events::{BytesEnd, BytesStart, Event};
+
+    let mut body = Vec::with_capacity(capacity);
+    value.serialize(&mut serde_xml_rs::Serializer::new(&mut body))?;
+
+    let mut reader = quick_xml::Reader::from_reader(&body[..]);
+    let mut writer = quick_xml::Writer::new(Vec::with_capacity(body.len()));
+    let mut depth = 0usize;
+
+    loop {
+        let event = match reader.read_event().map_err(writer_error)? {
+            Event::Eof => return Ok(writer.into_inner()),
+            Event::Decl(_) => continue,
+            Event::Start(mut start) => {
+                if depth == 0 {
+                    if let Some(name) = root_name {
+                        start.set_name(name.as_bytes());
+                    }
+                }
+                depth += 1;
+                Event::Start(start)
+            }
+            Event::Empty(start) => {
+                // xml-rs writes `<name />`, with a space before the `/>`.
+                let name_len = start.name().as_ref().len();
+                let content = std::str::from_utf8(&start).map_err(custom)?;
+                let mut start = BytesStart::from_content(content.trim_end().to_owned(), name_len);
+                if depth == 0 {
+                    if let Some(name) = root_name {
+                        start.set_name(name.as_bytes());
+                    }
+                }
+                Event::Empty(start)
+            }
+            Event::End(end) => {
+                depth -= 1;
+                match root_name {
+                    Some(name) if depth == 0 => Event::End(BytesEnd::new(name)),
+                    _ => Event::End(end),
+                }
+            }
+            event => event,
+        };
+        writer.write_event(event).map_err(writer_error)?;
+    }
+}
@@ -0,0 +1,100 @@
+use axum_core::{
+    extract::{
+        rejection::{BytesRejection, FailedToBufferBody},
+        FromRequest,
+    },
+    BoxError,
+};
+use bytes::Bytes;
+use http::{header, HeaderMap, Method, Request};
+use http_body::{Body as HttpBody, Limited};
+
+use crate::{config::XmlConfig, rejection::XmlRejection};
+
+/// Buffer a request body, enforcing the size limit from `config` as well as any
+/// `DefaultBodyLimit` layer.
+///
+/// A `Content-Length` above the limit is rejected before anything is read. Bodies of unknown
+/// length are counted as they arrive and rejected as soon as they grow past the limit.
+///
+/// An empty body where the `Content-Length` promises one means something else took the body
+/// out of the request first, which is reported as
+/// [`XmlRejection::BodyAlreadyExtracted`] rather than as an empty document.
+pub(crate) async fn read_body<S, B>(
+    req: Request<B>,
+    state: &S,
+    config: &XmlConfig,
+) -> Result<Bytes, XmlRejection>
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    let length = content_length(req.headers());
+    let bytes = read_limited(req, state, config).await?;
+
+    if bytes.is_empty() && length.is_some_and(|length| length > 0) {
+        return Err(XmlRejection::BodyAlreadyExtracted);
+    }
+    Ok(bytes)
+}
+
+async fn read_limited<S, B>(
+    req: Request<B>,
+    state: &S,
+    config: &XmlConfig,
+) -> Result<Bytes, XmlRejection>
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    let limit = match config.limit() {
+        Some(limit) => limit,
+        None => return buffer(req, state).await,
+    };
+
+    if content_length(req.headers()).is_some_and(|length| length > limit) {
+        return Err(XmlRejection::PayloadTooLarge);
+    }
+
+    buffer(req.map(|body| Limited::new(body, limit)), state).await
+}
+
+/// Buffer the body with axum's extractor, which also enforces a `DefaultBodyLimit` layer, and
+/// report going over either limit as [`XmlRejection::PayloadTooLarge`].
+async fn buffer<S, B>(req: Request<B>, state: &S) -> Result<Bytes, XmlRejection>
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    Bytes::from_request(req, state)
+        .await
+        .map_err(|rejection| match rejection {
+            BytesRejection::FailedToBufferBody(FailedToBufferBody::LengthLimitError(_)) => {
+                XmlRejection::PayloadTooLarge
+            }
+            rejection => rejection.into(),
+        })
+}
+
+pub(crate) fn content_length(headers: &HeaderMap) -> Option<usize> {
+    headers
+        .get(header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Whether `req` is a GET or HEAD request without a body: no `Transfer-Encoding` and no
+/// `Content-Length` other than zero.
+pub(crate) fn is_bodiless<B>(req: &Request<B>) -> bool {
+    matches!(*req.method(), Method::GET | Method::HEAD)
+        && !req.headers().contains_key(header::TRANSFER_ENCODING)
+        && content_length(req.headers()).unwrap_or(0) == 0
+}
@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use axum_core::{extract::FromRequest, BoxError};
+use bytes::Bytes;
+use http::Request;
+use http_body::Body as HttpBody;
+use serde::Deserialize;
+
+use crate::{
+    config::XmlConfig,
+    de::{self, Document},
+    rejection::XmlRejection,
+    xml_content_type,
+};
+
+/// XML Extractor for deserializing into types that borrow from the request body.
+///
+/// [`Xml`](crate::Xml) needs a [`DeserializeOwned`](serde::de::DeserializeOwned) type, so every
+/// string in the document is copied into its own allocation. `BorrowedXml` instead keeps the
+/// buffered body and deserializes from it on demand, so `&str` and `Cow<str>` fields can point
+/// into the body. The request goes through the same checks as with `Xml` when it is extracted.
+///
+/// The deserialized value borrows from the extractor, so it can't outlive the `BorrowedXml`
+/// it came from or be moved into a `'static` task; convert it into an owned type for that.
+/// A `&str` field only deserializes if its text contains no escapes such as `&amp;`, since
+/// those have to be unescaped into a new string: declare such fields as `Cow<'a, str>` with
+/// `#[serde(borrow)]` to borrow where possible and allocate otherwise; without the attribute,
+/// serde always allocates a `Cow`. Bodies that had to be transcoded to UTF-8 are borrowed from
+/// the transcoded copy.
+///
+/// With the `backend-serde-xml-rs` feature all strings are owned, so only `Cow` fields work.
+///
+/// ```rust,no_run
+/// use axum::{routing::post, Router};
+/// use serde::Deserialize;
+/// use rustcms_axum_xml::{BorrowedXml, XmlRejection};
+///
+/// #[derive(Deserialize)]
+/// struct Article<'a> {
+///     title: &'a str,
+/// }
+///
+/// async fn create_article(body: BorrowedXml) -> Result<String, XmlRejection> {
+///     let article: Article<'_> = body.deserialize()?;
+///     Ok(article.title.to_owned())
+/// }
+///
+/// let app: Router = Router::new().route("/articles", post(create_article));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BorrowedXml {
+    bytes: Bytes,
+    as_sent: bool,
+    config: XmlConfig,
+}
+
+impl BorrowedXml {
+    /// Deserialize the body into a `T` which may borrow from it.
+    ///
+    /// The [`XmlConfig`] of the request applies here as it does with [`Xml`](crate::Xml).
+    pub fn deserialize<'de, T>(&'de self) -> Result<T, XmlRejection>
+    where
+        T: Deserialize<'de>,
+    {
+        let result = de::from_str(&self.bytes, &self.config);
+        if self.as_sent {
+            return result;
+        }
+        result.map_err(XmlRejection::without_position)
+    }
+
+    /// The body, as UTF-8.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+#[async_trait]
+impl<S, B> FromRequest<S, B> for BorrowedXml
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = XmlRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let config = XmlConfig::from_extensions(req.extensions());
+        let request_id = config.request_id(req.headers());
+
+        let extracted: Result<Self, XmlRejection> = async move {
+            if xml_content_type(req.headers(), &config) {
+                let document = Document::from_request(req, state, &config).await?;
+                let checked = document.into_checked(&config)?;
+
+                Ok(Self {
+                    bytes: checked.bytes,
+                    as_sent: checked.as_sent,
+                    config,
+                })
+            } else {
+                Err(config.missing_content_type(&req))
+            }
+        }
+        .await;
+        extracted.map_err(|rejection| rejection.with_request_id(request_id))
+    }
+}
@@ -0,0 +1,136 @@
+use quick_xml::{
+    events::{BytesCData, BytesText, Event},
+    Writer,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ops::{Deref, DerefMut};
+
+use crate::backend::{self, Error};
+
+/// Marks the start of a CDATA section in the serializer's output.
+const START: char = '\u{FDD0}';
+/// Marks the end of a CDATA section in the serializer's output.
+const END: char = '\u{FDD1}';
+
+/// String serialized as a CDATA section instead of escaped text.
+///
+/// [`Xml`](crate::Xml) escapes `<`, `&` and friends in strings as entities. Consumers that
+/// expect large blocks of markup or code verbatim often prefer `<![CDATA[...]]>`, which a
+/// `Cdata` field is written as. A `]]>` in the content ends one section after its `]]` and
+/// starts another with the `>`. Deserializing accepts both text and CDATA.
+///
+/// ```rust
+/// use rustcms_axum_xml::{Cdata, Xml};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Snippet {
+///     code: Cdata,
+/// }
+///
+/// async fn snippet() -> Xml<Snippet> {
+///     Xml(Snippet {
+///         code: Cdata::from("if a < b && b > c {}"),
+///     })
+/// }
+/// ```
+///
+/// The section is marked with the Unicode noncharacters U+FDD0 and U+FDD1 on its way through
+/// the serializer, so strings that contain either of them are not supported.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cdata(pub String);
+
+impl Serialize for Cdata {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut marked = String::with_capacity(self.0.len() + 2 * START.len_utf8());
+        marked.push(START);
+        marked.push_str(&self.0);
+        marked.push(END);
+
+        serializer.serialize_newtype_struct("Cdata", &marked)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cdata {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+impl Deref for Cdata {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Cdata {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<String> for Cdata {
+    fn from(text: String) -> Self {
+        Self(text)
+    }
+}
+
+impl From<&str> for Cdata {
+    fn from(text: &str) -> Self {
+        Self(text.to_owned())
+    }
+}
+
+/// Whether serialized XML contains a [`Cdata`] value still to be turned into CDATA.
+pub(crate) fn contains_marker(xml: &[u8]) -> bool {
+    let mut start = [0; 3];
+    START.encode_utf8(&mut start);
+    xml.windows(start.len()).any(|window| window == start)
+}
+
+/// Write `text`, turning the marked parts into CDATA sections.
+pub(crate) fn write_text(writer: &mut Writer<Vec<u8>>, text: BytesText<'_>) -> Result<(), Error> {
+    let unescaped = text.unescape().map_err(backend::writer_error)?;
+    if !unescaped.contains(START) {
+        return writer
+            .write_event(Event::Text(text))
+            .map_err(backend::writer_error);
+    }
+
+    let mut rest = &*unescaped;
+    while let Some((before, after)) = rest.split_once(START) {
+        let (content, after) = after.split_once(END).unwrap_or((after, ""));
+        if !before.is_empty() {
+            write(writer, Event::Text(BytesText::new(before)))?;
+        }
+        write_sections(writer, content)?;
+        rest = after;
+    }
+    if !rest.is_empty() {
+        write(writer, Event::Text(BytesText::new(rest)))?;
+    }
+    Ok(())
+}
+
+/// Write `content` as CDATA, splitting it into two sections at every `]]>`.
+fn write_sections(writer: &mut Writer<Vec<u8>>, content: &str) -> Result<(), Error> {
+    let mut rest = content;
+    while let Some(split) = rest.find("]]>") {
+        // `]]` ends this section, `>` starts the next one.
+        write(writer, Event::CData(BytesCData::new(&rest[..split + 2])))?;
+        rest = &rest[split + 2..];
+    }
+    write(writer, Event::CData(BytesCData::new(rest)))
+}
+
+fn write(writer: &mut Writer<Vec<u8>>, event: Event<'_>) -> Result<(), Error> {
+    writer.write_event(event).map_err(backend::writer_error)
+}
@@ -0,0 +1,157 @@
+use std::fmt::Write;
+
+use crate::backend::{self, Error};
+
+/// What to do with characters XML 1.0 doesn't allow, such as `\u{0}`, in a serialized body.
+///
+/// The serializer escapes markup but writes control characters as they are, which makes the
+/// document unreadable for conforming parsers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum InvalidCharPolicy {
+    /// Fail to serialize, naming the first such character.
+    #[default]
+    Reject,
+    /// Leave the characters out.
+    Strip,
+    /// Write the characters as character references, such as `&#x0;`.
+    ///
+    /// XML 1.1 readers accept references to control characters other than `\u{0}`, but strict
+    /// XML 1.0 parsers still reject the document.
+    Escape,
+}
+
+impl InvalidCharPolicy {
+    /// Check `body`, a UTF-8 document, for forbidden characters and handle them.
+    pub(crate) fn apply(self, body: Vec<u8>) -> Result<Vec<u8>, Error> {
+        if !may_contain_forbidden(&body) {
+            return Ok(body);
+        }
+        let text = match std::str::from_utf8(&body) {
+            Ok(text) => text,
+            Err(_) => return Ok(body),
+        };
+
+        let mut cleaned = String::with_capacity(text.len());
+        for c in text.chars() {
+            if !is_forbidden(c) {
+                cleaned.push(c);
+                continue;
+            }
+            match self {
+                Self::Reject => {
+                    return Err(backend::custom(format_args!(
+                        "character U+{:04X} is not allowed in XML",
+                        u32::from(c)
+                    )))
+                }
+                Self::Strip => {}
+                Self::Escape => {
+                    let _ = write!(cleaned, "&#x{:X};", u32::from(c));
+                }
+            }
+        }
+        Ok(cleaned.into_bytes())
+    }
+}
+
+/// Whether `body` has a control character other than tab, line feed and carriage return, or
+/// the start of U+FFFE or U+FFFF, without decoding it.
+fn may_contain_forbidden(body: &[u8]) -> bool {
+    body.iter()
+        .any(|&byte| (byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r')) || byte == 0xEF)
+}
+
+/// Whether XML 1.0 forbids `c` anywhere in a document.
+fn is_forbidden(c: char) -> bool {
+    !matches!(
+        c,
+        '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..
+    )
+}
+
+/// Write the non-ASCII characters of `body`, a UTF-8 document, as decimal character
+/// references such as `&#233;`.
+///
+/// Only text and attribute values are rewritten: references aren't allowed in element and
+/// attribute names, nor recognized in comments, CDATA sections and processing instructions,
+/// so characters there are left as they are.
+pub(crate) fn escape_non_ascii(body: Vec<u8>) -> Vec<u8> {
+    if body.is_ascii() {
+        return body;
+    }
+    let text = match String::from_utf8(body) {
+        Ok(text) => text,
+        Err(err) => return err.into_bytes(),
+    };
+
+    let mut escaped = String::with_capacity(text.len() + text.len() / 2);
+    let mut rest = text.as_str();
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = markup_end(rest);
+            escape_quoted(&rest[..end], &mut escaped);
+            rest = &rest[end..];
+            continue;
+        }
+        push_escaped(c, &mut escaped);
+        rest = &rest[c.len_utf8()..];
+    }
+    escaped.into_bytes()
+}
+
+/// Length of the markup at the start of `rest`, which starts with `<`.
+pub(crate) fn markup_end(rest: &str) -> usize {
+    let terminator = if rest.starts_with("<!--") {
+        "-->"
+    } else if rest.starts_with("<![CDATA[") {
+        "]]>"
+    } else if rest.starts_with("<?") {
+        "?>"
+    } else {
+        let mut quote = None;
+        // The declarations in a DOCTYPE's internal subset end with `>` too.
+        let mut subset = 0_usize;
+        for (i, c) in rest.char_indices() {
+            match (quote, c) {
+                (None, '"' | '\'') => quote = Some(c),
+                (Some(open), _) if open == c => quote = None,
+                (None, '[') => subset += 1,
+                (None, ']') => subset = subset.saturating_sub(1),
+                (None, '>') if subset == 0 => return i + 1,
+                _ => {}
+            }
+        }
+        return rest.len();
+    };
+    rest.find(terminator)
+        .map_or(rest.len(), |i| i + terminator.len())
+}
+
+/// Copy the markup `tag` to `out`, escaping characters in quoted attribute values of tags.
+fn escape_quoted(tag: &str, out: &mut String) {
+    if tag.starts_with("<!") || tag.starts_with("<?") {
+        out.push_str(tag);
+        return;
+    }
+    let mut quote = None;
+    for c in tag.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if open == c => quote = None,
+            (Some(_), _) => {
+                push_escaped(c, out);
+                continue;
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+}
+
+fn push_escaped(c: char, out: &mut String) {
+    if c.is_ascii() {
+        out.push(c);
+    } else {
+        let _ = write!(out, "&#{};", u32::from(c));
+    }
+}
@@ -0,0 +1,63 @@
+use bytes::Bytes;
+use http::HeaderValue;
+
+use crate::{config::XmlConfig, rejection::XmlRejection};
+
+/// Undo the `Content-Encoding` of a request body.
+///
+/// Encodings are listed in the order they were applied, so they are removed from last to
+/// first. The decompressed body is capped at the size limit from `config`, or at
+/// [`XmlConfig::DEFAULT_DECOMPRESSED_LIMIT`] when none is set, so that a small compressed body
+/// can't expand into an arbitrarily large one.
+pub(crate) fn decompress(
+    bytes: Bytes,
+    content_encoding: Option<&HeaderValue>,
+    config: &XmlConfig,
+) -> Result<Bytes, XmlRejection> {
+    let content_encoding = match content_encoding {
+        Some(content_encoding) => content_encoding
+            .to_str()
+            .map_err(|_| XmlRejection::UnsupportedContentEncoding)?,
+        None => return Ok(bytes),
+    };
+
+    content_encoding
+        .rsplit(',')
+        .map(str::trim)
+        .filter(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity"))
+        .try_fold(bytes, |bytes, coding| decode(bytes, coding, config))
+}
+
+#[cfg(not(feature = "compression"))]
+fn decode(_bytes: Bytes, _coding: &str, _config: &XmlConfig) -> Result<Bytes, XmlRejection> {
+    Err(XmlRejection::UnsupportedContentEncoding)
+}
+
+#[cfg(feature = "compression")]
+fn decode(bytes: Bytes, coding: &str, config: &XmlConfig) -> Result<Bytes, XmlRejection> {
+    use flate2::read::{GzDecoder, ZlibDecoder};
+    use std::io::Read;
+
+    let decoder: Box<dyn Read + '_> =
+        if coding.eq_ignore_ascii_case("gzip") || coding.eq_ignore_ascii_case("x-gzip") {
+            Box::new(GzDecoder::new(&*bytes))
+        } else if coding.eq_ignore_ascii_case("deflate") {
+            Box::new(ZlibDecoder::new(&*bytes))
+        } else {
+            return Err(XmlRejection::UnsupportedContentEncoding);
+        };
+
+    let limit = config
+        .limit()
+        .unwrap_or(XmlConfig::DEFAULT_DECOMPRESSED_LIMIT);
+    let mut decoded = Vec::new();
+    decoder
+        .take(limit as u64 + 1)
+        .read_to_end(&mut decoded)
+        .map_err(|_| XmlRejection::InvalidCompressedBody)?;
+    if decoded.len() > limit {
+        return Err(XmlRejection::PayloadTooLarge);
+    }
+
+    Ok(decoded.into())
+}
@@ -0,0 +1,566 @@
+use http::{header::HeaderName, Extensions, HeaderMap, Request, StatusCode};
+use mime::Mime;
+use std::{borrow::Cow, collections::BTreeMap, fmt, sync::Arc};
+
+use crate::{body::is_bodiless, entity, malformed_content_type, rejection::XmlRejection};
+
+type ContentTypeMatcher = Arc<dyn Fn(&Mime) -> bool + Send + Sync>;
+
+/// Configuration for the XML extractors.
+///
+/// Install it on a router or a single route as an [`Extension`] layer and every extractor in
+/// this crate picks it up from the request. Requests without an `XmlConfig` use the defaults.
+///
+/// A configuration on a route replaces the one on its router as a whole, rather than being
+/// merged with it, so derive route configurations from a shared base. Responses are tuned per
+/// response instead, with [`XmlResponse`](crate::XmlResponse), as they don't see the request.
+///
+/// ```rust
+/// use axum::{routing::post, Extension, Router};
+/// use rustcms_axum_xml::{Xml, XmlConfig};
+///
+/// async fn feed(Xml(_): Xml<()>) {}
+///
+/// let config = XmlConfig::new().with_content_type_matcher(|mime| {
+///     mime.type_() == "application" && mime.subtype() == "rss"
+/// });
+///
+/// let app: Router = Router::new()
+///     .route("/feed", post(feed))
+///     .route(
+///         "/import",
+///         post(feed).layer(Extension(config.clone().with_limit(64 * 1024 * 1024))),
+///     )
+///     .layer(Extension(config));
+/// ```
+///
+/// [`Extension`]: https://docs.rs/axum/0.6/axum/struct.Extension.html
+#[derive(Clone)]
+pub struct XmlConfig {
+    content_type_matcher: Option<ContentTypeMatcher>,
+    content_type_status: StatusCode,
+    forbid_doctype: bool,
+    allow_doctype: bool,
+    forbid_comments: bool,
+    max_depth: usize,
+    max_elements: usize,
+    max_attributes: usize,
+    max_attribute_len: usize,
+    entity_max_bytes: usize,
+    entity_max_depth: usize,
+    entities: BTreeMap<String, String>,
+    trim_text: bool,
+    attribute_prefix: bool,
+    xsi_nil: bool,
+    strip_prefixes: bool,
+    deny_unknown_elements: bool,
+    expected_root: Option<Cow<'static, str>>,
+    request_id_header: Option<HeaderName>,
+    limit: Option<usize>,
+    #[cfg(feature = "streaming")]
+    stream_buffer_size: usize,
+}
+
+impl XmlConfig {
+    /// Default for [`XmlConfig::with_max_depth`].
+    pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+    /// Default for [`XmlConfig::with_max_elements`].
+    pub const DEFAULT_MAX_ELEMENTS: usize = 1_000_000;
+
+    /// Default for [`XmlConfig::with_max_attributes`].
+    pub const DEFAULT_MAX_ATTRIBUTES: usize = 256;
+
+    /// Default for [`XmlConfig::with_max_attribute_len`].
+    pub const DEFAULT_MAX_ATTRIBUTE_LEN: usize = 64 * 1024;
+
+    /// Default `max_bytes` for [`XmlConfig::with_entity_limit`].
+    pub const DEFAULT_ENTITY_MAX_BYTES: usize = 64 * 1024;
+
+    /// Default `max_depth` for [`XmlConfig::with_entity_limit`].
+    pub const DEFAULT_ENTITY_MAX_DEPTH: usize = 8;
+
+    /// Largest body a compressed request may expand to when no [`XmlConfig::with_limit`] is
+    /// set. Matches the 2 MB default of axum's `DefaultBodyLimit`.
+    pub const DEFAULT_DECOMPRESSED_LIMIT: usize = 2 * 1024 * 1024;
+
+    /// Default for [`XmlConfig::with_stream_buffer_size`].
+    #[cfg(feature = "streaming")]
+    pub const DEFAULT_STREAM_BUFFER_SIZE: usize = 8 * 1024;
+
+    /// Create a configuration with the default settings.
+    pub fn new() -> Self {
+        Self {
+            content_type_matcher: None,
+            content_type_status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            forbid_doctype: true,
+            allow_doctype: false,
+            forbid_comments: false,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            max_elements: Self::DEFAULT_MAX_ELEMENTS,
+            max_attributes: Self::DEFAULT_MAX_ATTRIBUTES,
+            max_attribute_len: Self::DEFAULT_MAX_ATTRIBUTE_LEN,
+            entity_max_bytes: Self::DEFAULT_ENTITY_MAX_BYTES,
+            entity_max_depth: Self::DEFAULT_ENTITY_MAX_DEPTH,
+            entities: BTreeMap::new(),
+            trim_text: true,
+            attribute_prefix: false,
+            xsi_nil: false,
+            strip_prefixes: false,
+            deny_unknown_elements: false,
+            expected_root: None,
+            request_id_header: None,
+            limit: None,
+            #[cfg(feature = "streaming")]
+            stream_buffer_size: Self::DEFAULT_STREAM_BUFFER_SIZE,
+        }
+    }
+
+    /// Decide which `Content-Type`s are accepted as XML with `matcher`.
+    ///
+    /// This replaces the built-in check for `application/xml`, `text/xml` and `+xml` types.
+    pub fn with_content_type_matcher<F>(mut self, matcher: F) -> Self
+    where
+        F: Fn(&Mime) -> bool + Send + Sync + 'static,
+    {
+        self.content_type_matcher = Some(Arc::new(matcher));
+        self
+    }
+
+    /// Accept only the `Content-Type`s in `types`, such as `application/xml`, rather than
+    /// every XML type.
+    ///
+    /// This is a shorthand for [`XmlConfig::with_content_type_matcher`]: a type such as
+    /// `application/evil+xml` is rejected unless it is listed, for services that only
+    /// expect a few document types. Types are compared without their parameters and ignoring
+    /// case.
+    ///
+    /// ```rust
+    /// use rustcms_axum_xml::XmlConfig;
+    ///
+    /// let config =
+    ///     XmlConfig::new().with_allowed_types(["application/xml", "application/atom+xml"]);
+    /// ```
+    pub fn with_allowed_types<I>(self, types: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let types: Vec<String> = types
+            .into_iter()
+            .map(|allowed| allowed.as_ref().trim().to_ascii_lowercase())
+            .collect();
+        self.with_content_type_matcher(move |mime| {
+            types.iter().any(|allowed| mime.essence_str() == allowed)
+        })
+    }
+
+    /// Respond with `status` when the `Content-Type` of a request is missing or not XML.
+    ///
+    /// Such requests are rejected with
+    /// [`XmlRejection::MissingXMLContentType`](crate::XmlRejection::MissingXMLContentType),
+    /// which is `415 Unsupported Media Type` by default. APIs that answer every client error
+    /// with `400 Bad Request` can set that here.
+    pub fn with_content_type_status(mut self, status: StatusCode) -> Self {
+        self.content_type_status = status;
+        self
+    }
+
+    /// Reject every document with a DOCTYPE.
+    ///
+    /// Few services expect a DOCTYPE in a request, and it is where entity declarations live, so
+    /// any document containing one is rejected with
+    /// [`XmlRejection::ForbiddenDoctype`](crate::XmlRejection::ForbiddenDoctype) by default.
+    /// Pass `false` to accept DOCTYPEs that only use internal declarations; external ones are
+    /// still subject to [`XmlConfig::allow_doctype`]. References to the internal entities they
+    /// declare are expanded before the document is parsed, within the limits of
+    /// [`XmlConfig::with_entity_limit`]. [`StreamingXml`](crate::StreamingXml) doesn't
+    /// expand them.
+    pub fn forbid_doctype(mut self, forbid: bool) -> Self {
+        self.forbid_doctype = forbid;
+        self
+    }
+
+    /// Accept documents whose DOCTYPE declares external entities or an external DTD.
+    ///
+    /// `SYSTEM` and `PUBLIC` identifiers in a DOCTYPE are how XML external entity (XXE) attacks
+    /// reach files and URLs on the server, so such documents are rejected with
+    /// [`XmlRejection::ForbiddenDoctype`](crate::XmlRejection::ForbiddenDoctype) by default.
+    /// Allowing them accepts any DOCTYPE, overriding [`XmlConfig::forbid_doctype`].
+    pub fn allow_doctype(mut self, allow: bool) -> Self {
+        self.allow_doctype = allow;
+        self
+    }
+
+    /// Reject documents containing comments or processing instructions.
+    ///
+    /// Comments and processing instructions such as `<?xml-stylesheet ...?>` are removed
+    /// before deserializing by default, wherever they appear, so they never affect the value.
+    /// Pass `true` to reject such documents with
+    /// [`XmlRejection::InvalidXMLBody`](crate::XmlRejection::InvalidXMLBody) instead. The XML
+    /// declaration is not a processing instruction and is always accepted.
+    pub fn forbid_comments(mut self, forbid: bool) -> Self {
+        self.forbid_comments = forbid;
+        self
+    }
+
+    /// Reject documents with elements nested deeper than `max_depth`.
+    ///
+    /// Deeply nested documents can exhaust the stack while deserializing, so the nesting is
+    /// checked before any value is built. Defaults to [`XmlConfig::DEFAULT_MAX_DEPTH`].
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Reject documents with more than `max_elements` elements in total.
+    ///
+    /// Millions of tiny elements fit in a modest body and keep the deserializer busy, so they
+    /// are counted before any value is built and the document is rejected with
+    /// [`XmlRejection::TooManyElements`](crate::XmlRejection::TooManyElements) past the cap.
+    /// Defaults to [`XmlConfig::DEFAULT_MAX_ELEMENTS`].
+    pub fn with_max_elements(mut self, max_elements: usize) -> Self {
+        self.max_elements = max_elements;
+        self
+    }
+
+    /// Reject documents with an element carrying more than `max_attributes` attributes.
+    ///
+    /// Such documents are rejected with
+    /// [`XmlRejection::TooManyAttributes`](crate::XmlRejection::TooManyAttributes). Defaults to
+    /// [`XmlConfig::DEFAULT_MAX_ATTRIBUTES`].
+    pub fn with_max_attributes(mut self, max_attributes: usize) -> Self {
+        self.max_attributes = max_attributes;
+        self
+    }
+
+    /// Reject documents with an attribute value longer than `max_len` bytes, as written in the
+    /// document.
+    ///
+    /// A body within [`XmlConfig::with_limit`] can still hold a single multi-megabyte attribute,
+    /// which the deserializer copies out in one go. Such documents are rejected with
+    /// [`XmlRejection::AttributeTooLarge`](crate::XmlRejection::AttributeTooLarge). Defaults to
+    /// [`XmlConfig::DEFAULT_MAX_ATTRIBUTE_LEN`].
+    pub fn with_max_attribute_len(mut self, max_len: usize) -> Self {
+        self.max_attribute_len = max_len;
+        self
+    }
+
+    /// Cap how far the entities declared in a document's DOCTYPE may expand.
+    ///
+    /// Entities defined in terms of other entities grow exponentially, which is how "billion
+    /// laughs" payloads turn a few hundred bytes into gigabytes. Documents are rejected with
+    /// [`XmlRejection::EntityExpansionLimit`](crate::XmlRejection::EntityExpansionLimit) when
+    /// their entity references would expand to more than `max_bytes` in total, or when entity
+    /// definitions refer to each other more than `max_depth` levels deep. The entities are
+    /// resolved within these limits before the body is expanded. Defaults to
+    /// [`XmlConfig::DEFAULT_ENTITY_MAX_BYTES`] and [`XmlConfig::DEFAULT_ENTITY_MAX_DEPTH`].
+    pub fn with_entity_limit(mut self, max_bytes: usize, max_depth: usize) -> Self {
+        self.entity_max_bytes = max_bytes;
+        self.entity_max_depth = max_depth;
+        self
+    }
+
+    /// Read references to the named entities in `entities` as their replacement text, such as
+    /// `&copy;` as `"©"` for a map of `"copy"` to `"©"`. Calls add to the entities registered
+    /// so far.
+    ///
+    /// XML itself only knows `&amp;`, `&lt;`, `&gt;`, `&quot;` and `&apos;`, and the parser
+    /// rejects references to any other entity that the document's DOCTYPE doesn't declare.
+    /// Registered entities are expanded in text and attribute values before the
+    /// document is parsed, while references to entities that aren't registered are still
+    /// rejected. The replacement text is taken literally, not as markup, and counts toward the
+    /// `max_bytes` of [`XmlConfig::with_entity_limit`]. [`StreamingXml`](crate::StreamingXml)
+    /// doesn't expand them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a name isn't a valid XML name or is one of the five predefined entities.
+    pub fn with_entities<I, K, V>(mut self, entities: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        for (name, replacement) in entities {
+            let name = name.into();
+            entity::check_name(&name);
+            self.entities.insert(name, replacement.into());
+        }
+        self
+    }
+
+    /// Trim the whitespace around text content, so that `<name>\n  Ann\n</name>` reads as
+    /// `"Ann"`.
+    ///
+    /// Text is trimmed by default. Pass `false` to keep it exactly as sent, for content where
+    /// the whitespace matters. While trimming, the text of elements marked
+    /// `xml:space="preserve"`, and of their children, is still kept as sent. Only the
+    /// `backend-quick-xml` backend can keep the whitespace; serde-xml-rs always trims it.
+    pub fn with_trim_text(mut self, trim: bool) -> Self {
+        self.trim_text = trim;
+        self
+    }
+
+    /// Read attributes into fields named with an `@` prefix, as in
+    /// `#[serde(rename = "@id")]`.
+    ///
+    /// By default an attribute fills the field of the same name: quick-xml serializes plain
+    /// string and number fields as attributes and reads them back the same way. Types that
+    /// mark their attribute fields with `@` instead, the convention of newer serde XML
+    /// libraries, need this turned on, and then every attribute other than an `xmlns`
+    /// declaration is only read into an `@` field. Responses always write these fields as
+    /// attributes without the `@`.
+    ///
+    /// Only the `backend-quick-xml` backend reads `@` fields; serde-xml-rs always reads an
+    /// attribute into the field of the same name.
+    pub fn with_attribute_prefix(mut self, prefix: bool) -> Self {
+        self.attribute_prefix = prefix;
+        self
+    }
+
+    /// Read elements marked `xsi:nil="true"` as missing, so that `Option` fields they fill are
+    /// `None`.
+    ///
+    /// Without this a nil element is read like any other, which usually fails for anything but
+    /// strings. The `nil` attribute counts whatever its prefix, as long as the prefix is bound
+    /// to the XML Schema instance namespace. The root element is never dropped. See
+    /// [`xsi_nil`](crate::xsi_nil) for writing such elements.
+    pub fn with_xsi_nil(mut self, xsi_nil: bool) -> Self {
+        self.xsi_nil = xsi_nil;
+        self
+    }
+
+    /// Read elements and attributes as if they had no namespace prefix, so that
+    /// `<ns:User ns:id="7">` fills a `User` struct with an `id` field.
+    ///
+    /// By default names are matched with their prefix. `xmlns` declarations and `xml:`
+    /// attributes such as `xml:lang` keep their names. Only the `backend-quick-xml` backend
+    /// matches prefixed names; serde-xml-rs always ignores the prefix.
+    pub fn with_strip_prefixes(mut self, strip: bool) -> Self {
+        self.strip_prefixes = strip;
+        self
+    }
+
+    /// Reject documents with elements or attributes that the extracted type has no field for,
+    /// with [`XmlRejection::UnknownElement`](crate::XmlRejection::UnknownElement).
+    ///
+    /// By default they are skipped, so that clients can send newer versions of a document
+    /// than the server knows about, unless the type itself is marked
+    /// `#[serde(deny_unknown_fields)]`. This applies that strictness to every type, including
+    /// ones from other crates. Namespace declarations and `xml:` attributes are not taken
+    /// as unknown with the quick-xml backend; serde-xml-rs reads `xml:lang` as `lang`.
+    pub fn with_deny_unknown_elements(mut self, deny: bool) -> Self {
+        self.deny_unknown_elements = deny;
+        self
+    }
+
+    /// Reject documents whose root element isn't called `name`.
+    ///
+    /// A document that is well-formed but the wrong kind of message is rejected with
+    /// [`XmlRejection::UnexpectedRoot`](crate::XmlRejection::UnexpectedRoot) before it is
+    /// deserialized. A `name` without a prefix matches the local name of the root, whatever
+    /// its prefix; one with a prefix, such as `soap:Envelope`, must match exactly.
+    pub fn with_expected_root(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.expected_root = Some(name.into());
+        self
+    }
+
+    /// Add the id a request carries in the `header` header, such as `x-request-id`, to the
+    /// body of its rejection.
+    ///
+    /// Rejections of requests with the header are wrapped in
+    /// [`XmlRejection::WithRequestId`](crate::XmlRejection::WithRequestId), which appends the id
+    /// to the plain text message, or adds a `<requestId>` element with the `xml-errors`
+    /// feature. Requests without it are rejected as usual.
+    pub fn with_request_id_header(mut self, header: HeaderName) -> Self {
+        self.request_id_header = Some(header);
+        self
+    }
+
+    /// Reject request bodies larger than `limit` bytes.
+    ///
+    /// Oversized bodies are rejected with
+    /// [`XmlRejection::PayloadTooLarge`](crate::XmlRejection::PayloadTooLarge), based on the
+    /// `Content-Length` header when there is one and while reading the body otherwise.
+    ///
+    /// axum's `DefaultBodyLimit` applies either way, so a limit set once with that layer covers
+    /// XML bodies too, and bodies going over it are rejected with `PayloadTooLarge` as well.
+    /// This limit only needs setting to be stricter for XML than for other bodies.
+    ///
+    /// Compressed bodies are also checked after decompression. Without this limit they may
+    /// expand to at most [`XmlConfig::DEFAULT_DECOMPRESSED_LIMIT`] bytes, whatever
+    /// `DefaultBodyLimit` says, so set it to accept larger decompressed documents.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Hand the body to the parser of a [`StreamingXml`](crate::StreamingXml) extractor in
+    /// pieces of at least `size` bytes.
+    ///
+    /// Clients and proxies often send bodies in small chunks, and passing each one to the
+    /// parser's thread on its own costs a wakeup per chunk. Smaller chunks are collected until
+    /// there are `size` bytes, so a larger size means fewer handoffs, for up to about four
+    /// times `size` bytes held in memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    #[cfg(feature = "streaming")]
+    pub fn with_stream_buffer_size(mut self, size: usize) -> Self {
+        assert!(size > 0, "the stream buffer size must not be zero");
+        self.stream_buffer_size = size;
+        self
+    }
+
+    /// Rejection for a request that isn't labelled as XML.
+    ///
+    /// GET and HEAD requests normally have neither a body nor a `Content-Type`, so one that
+    /// declares no body is reported as [`XmlRejection::EmptyBody`] rather than as a wrong type.
+    /// A `Content-Type` that doesn't parse is reported as
+    /// [`XmlRejection::InvalidContentType`], to tell it apart from a valid type other than XML.
+    pub(crate) fn missing_content_type<B>(&self, req: &Request<B>) -> XmlRejection {
+        if is_bodiless(req) {
+            return XmlRejection::EmptyBody;
+        }
+        if malformed_content_type(req.headers()) {
+            return XmlRejection::InvalidContentType;
+        }
+        XmlRejection::MissingXMLContentType {
+            status: self.content_type_status,
+        }
+    }
+
+    pub(crate) fn doctype_forbidden(&self) -> bool {
+        self.forbid_doctype && !self.allow_doctype
+    }
+
+    pub(crate) fn doctype_allowed(&self) -> bool {
+        self.allow_doctype
+    }
+
+    pub(crate) fn comments_forbidden(&self) -> bool {
+        self.forbid_comments
+    }
+
+    pub(crate) fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub(crate) fn max_elements(&self) -> usize {
+        self.max_elements
+    }
+
+    pub(crate) fn max_attributes(&self) -> usize {
+        self.max_attributes
+    }
+
+    pub(crate) fn max_attribute_len(&self) -> usize {
+        self.max_attribute_len
+    }
+
+    pub(crate) fn entity_max_bytes(&self) -> usize {
+        self.entity_max_bytes
+    }
+
+    pub(crate) fn entity_max_depth(&self) -> usize {
+        self.entity_max_depth
+    }
+
+    pub(crate) fn entities(&self) -> &BTreeMap<String, String> {
+        &self.entities
+    }
+
+    pub(crate) fn trim_text(&self) -> bool {
+        self.trim_text
+    }
+
+    pub(crate) fn attribute_prefix(&self) -> bool {
+        self.attribute_prefix
+    }
+
+    pub(crate) fn xsi_nil(&self) -> bool {
+        self.xsi_nil
+    }
+
+    pub(crate) fn strip_prefixes(&self) -> bool {
+        self.strip_prefixes
+    }
+
+    pub(crate) fn deny_unknown_elements(&self) -> bool {
+        self.deny_unknown_elements
+    }
+
+    pub(crate) fn expected_root(&self) -> Option<&str> {
+        self.expected_root.as_deref()
+    }
+
+    /// The request id in `headers`, if one is asked for and present.
+    pub(crate) fn request_id(&self, headers: &HeaderMap) -> Option<String> {
+        let value = headers
+            .get(self.request_id_header.as_ref()?)?
+            .to_str()
+            .ok()?;
+        (!value.is_empty()).then(|| value.to_owned())
+    }
+
+    pub(crate) fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    #[cfg(feature = "streaming")]
+    pub(crate) fn stream_buffer_size(&self) -> usize {
+        self.stream_buffer_size
+    }
+
+    pub(crate) fn from_extensions(extensions: &Extensions) -> Self {
+        extensions.get::<Self>().cloned().unwrap_or_default()
+    }
+
+    pub(crate) fn is_xml(&self, mime: &Mime) -> bool {
+        match &self.content_type_matcher {
+            Some(matcher) => matcher(mime),
+            None => crate::is_xml_mime(mime),
+        }
+    }
+}
+
+impl Default for XmlConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for XmlConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("XmlConfig");
+        debug
+            .field(
+                "content_type_matcher",
+                &self.content_type_matcher.as_ref().map(|_| ".."),
+            )
+            .field("content_type_status", &self.content_type_status)
+            .field("forbid_doctype", &self.forbid_doctype)
+            .field("allow_doctype", &self.allow_doctype)
+            .field("forbid_comments", &self.forbid_comments)
+            .field("max_depth", &self.max_depth)
+            .field("max_elements", &self.max_elements)
+            .field("max_attributes", &self.max_attributes)
+            .field("max_attribute_len", &self.max_attribute_len)
+            .field("entity_max_bytes", &self.entity_max_bytes)
+            .field("entity_max_depth", &self.entity_max_depth)
+            .field("entities", &self.entities)
+            .field("trim_text", &self.trim_text)
+            .field("attribute_prefix", &self.attribute_prefix)
+            .field("xsi_nil", &self.xsi_nil)
+            .field("strip_prefixes", &self.strip_prefixes)
+            .field("deny_unknown_elements", &self.deny_unknown_elements)
+            .field("expected_root", &self.expected_root)
+            .field("request_id_header", &self.request_id_header)
+            .field("limit", &self.limit);
+        #[cfg(feature = "streaming")]
+        debug.field("stream_buffer_size", &self.stream_buffer_size);
+        debug.finish()
+    }
+}
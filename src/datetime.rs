@@ -0,0 +1,108 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+/// [chrono](https://crates.io/crates/chrono) date and time written as an `xs:dateTime`, such
+/// as `2024-05-30T09:30:10.5Z`.
+///
+/// chrono's own serde support writes RFC 3339 with nanoseconds and `+00:00`, which not every
+/// schema-driven consumer accepts. `XmlDateTime` writes only the fractional digits the value
+/// needs, and `Z` for UTC. `XmlDateTime<Utc>` converts to UTC when reading, while
+/// `XmlDateTime<FixedOffset>` keeps the offset a value was sent with, and writes it back.
+///
+/// Values without a timezone, which `xs:dateTime` allows, are read as UTC.
+///
+/// ```rust
+/// use chrono::{TimeZone, Utc};
+/// use rustcms_axum_xml::{Xml, XmlDateTime};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Event {
+///     at: XmlDateTime,
+/// }
+///
+/// let event = Event {
+///     at: Utc.with_ymd_and_hms(2024, 5, 30, 9, 30, 10).unwrap().into(),
+/// };
+/// let bytes = Xml::to_bytes(&event).unwrap();
+/// # #[cfg(feature = "backend-quick-xml")]
+/// assert_eq!(bytes, br#"<Event at="2024-05-30T09:30:10Z"/>"#);
+///
+/// let Xml(read) = Xml::<Event>::from_bytes(&bytes).unwrap();
+/// assert_eq!(read.at, event.at);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct XmlDateTime<Tz: TimeZone = Utc>(pub DateTime<Tz>);
+
+impl<Tz: TimeZone> XmlDateTime<Tz> {
+    /// Consume the `XmlDateTime` and return the date and time.
+    pub fn into_inner(self) -> DateTime<Tz> {
+        self.0
+    }
+}
+
+impl<Tz: TimeZone> From<DateTime<Tz>> for XmlDateTime<Tz> {
+    fn from(datetime: DateTime<Tz>) -> Self {
+        Self(datetime)
+    }
+}
+
+impl<Tz: TimeZone> Deref for XmlDateTime<Tz> {
+    type Target = DateTime<Tz>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<Tz: TimeZone> DerefMut for XmlDateTime<Tz> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<Tz: TimeZone> Serialize for XmlDateTime<Tz> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.0.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+}
+
+impl<'de, Tz> Deserialize<'de> for XmlDateTime<Tz>
+where
+    Tz: TimeZone,
+    DateTime<Tz>: From<DateTime<FixedOffset>>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        parse(&text)
+            .map(|datetime| Self(datetime.into()))
+            .ok_or_else(|| de::Error::custom(InvalidDateTime(&text)))
+    }
+}
+
+/// Parse an `xs:dateTime`, in which the timezone is optional.
+fn parse(text: &str) -> Option<DateTime<FixedOffset>> {
+    let text = text.trim();
+    DateTime::parse_from_rfc3339(text).ok().or_else(|| {
+        let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+        Some(Utc.from_utc_datetime(&naive).fixed_offset())
+    })
+}
+
+struct InvalidDateTime<'a>(&'a str);
+
+impl fmt::Display for InvalidDateTime<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` is not a valid xs:dateTime", self.0)
+    }
+}
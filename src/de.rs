@@ -0,0 +1,406 @@
+use axum_core::BoxError;
+use bytes::Bytes;
+use http::{header, Request};
+use http_body::Body as HttpBody;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{
+    borrow::Cow,
+    io::{self, BufRead, Read},
+};
+
+use crate::{
+    backend, body::read_body, compression::decompress, config::XmlConfig, encoding, entity,
+    rejection::XmlRejection, scan, text, xsi_nil,
+};
+
+/// Request body buffered and decompressed, ready to be deserialized.
+pub(crate) struct Document {
+    bytes: Bytes,
+    charset: Option<String>,
+}
+
+impl Document {
+    pub(crate) async fn from_request<S, B>(
+        req: Request<B>,
+        state: &S,
+        config: &XmlConfig,
+    ) -> Result<Self, XmlRejection>
+    where
+        B: HttpBody + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<BoxError>,
+        S: Send + Sync,
+    {
+        Self::with_raw_body(req, state, config)
+            .await
+            .map(|(document, _)| document)
+    }
+
+    /// Buffer the body like [`Document::from_request`], also returning it as it was received,
+    /// before decompression.
+    pub(crate) async fn with_raw_body<S, B>(
+        req: Request<B>,
+        state: &S,
+        config: &XmlConfig,
+    ) -> Result<(Self, Bytes), XmlRejection>
+    where
+        B: HttpBody + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<BoxError>,
+        S: Send + Sync,
+    {
+        let charset = encoding::charset(req.headers());
+        let content_encoding = req.headers().get(header::CONTENT_ENCODING).cloned();
+
+        let raw = read_body(req, state, config).await?;
+        let bytes = decompress(raw.clone(), content_encoding.as_ref(), config)?;
+
+        Ok((Self { bytes, charset }, raw))
+    }
+
+    /// Whether the body is empty or holds nothing but whitespace.
+    pub(crate) fn is_empty(&self) -> bool {
+        is_blank(&self.bytes)
+    }
+
+    pub(crate) fn deserialize<T>(&self, config: &XmlConfig) -> Result<T, XmlRejection>
+    where
+        T: DeserializeOwned,
+    {
+        deserialize_body(&self.bytes, self.charset.as_deref(), config)
+    }
+
+    /// Transcode the body to UTF-8 and run the checks of [`deserialize`] on it, returning it
+    /// as it was sent, without the rewriting `config` asks for.
+    pub(crate) fn into_utf8(self, config: &XmlConfig) -> Result<Bytes, XmlRejection> {
+        let bytes = self.utf8()?;
+        scan::check(&bytes, config)?;
+        Ok(bytes)
+    }
+
+    /// Transcode the body to UTF-8 and run the checks of [`deserialize`] on it, returning it
+    /// as it was sent and with its entities expanded, but without the other rewriting `config`
+    /// asks for.
+    #[cfg(feature = "xsd")]
+    pub(crate) fn into_expanded(self, config: &XmlConfig) -> Result<(Bytes, Bytes), XmlRejection> {
+        let bytes = self.utf8()?;
+        let scanned = scan::check(&bytes, config)?;
+        let expanded = match entity::expand(&bytes, &scanned.entities, config)? {
+            Cow::Borrowed(_) => bytes.clone(),
+            Cow::Owned(expanded) => Bytes::from(expanded),
+        };
+        Ok((bytes, expanded))
+    }
+
+    /// Transcode the body to UTF-8 and run the checks of [`deserialize`] on it, returning the
+    /// bytes to deserialize from later.
+    pub(crate) fn into_checked(self, config: &XmlConfig) -> Result<Checked, XmlRejection> {
+        let bytes = self.utf8()?;
+        match prepare(&bytes, config)? {
+            Cow::Borrowed(_) => Ok(Checked {
+                as_sent: bytes == self.bytes,
+                bytes,
+            }),
+            Cow::Owned(prepared) => Ok(Checked {
+                bytes: Bytes::from(prepared),
+                as_sent: false,
+            }),
+        }
+    }
+
+    /// The body transcoded to UTF-8, sharing its buffer if it is UTF-8 already.
+    fn utf8(&self) -> Result<Bytes, XmlRejection> {
+        if self.is_empty() {
+            return Err(XmlRejection::EmptyBody);
+        }
+
+        Ok(
+            match encoding::to_utf8(&self.bytes, self.charset.as_deref())? {
+                Cow::Borrowed(utf8) => self.bytes.slice_ref(utf8),
+                Cow::Owned(utf8) => Bytes::from(utf8),
+            },
+        )
+    }
+}
+
+/// Body checked and rewritten by [`Document::into_checked`].
+pub(crate) struct Checked {
+    pub(crate) bytes: Bytes,
+    /// Whether `bytes` is still the body as it was sent, so that positions in it point at what
+    /// the client sent.
+    pub(crate) as_sent: bool,
+}
+
+fn is_blank(bytes: &[u8]) -> bool {
+    bytes.iter().all(u8::is_ascii_whitespace)
+}
+
+/// Deserialize a buffered request body, rejecting empty ones.
+pub(crate) fn deserialize_body<T>(
+    bytes: &[u8],
+    charset: Option<&str>,
+    config: &XmlConfig,
+) -> Result<T, XmlRejection>
+where
+    T: DeserializeOwned,
+{
+    #[cfg(feature = "tracing")]
+    let span = tracing::debug_span!(
+        "xml.deserialize",
+        body_len = bytes.len(),
+        outcome = tracing::field::Empty,
+    )
+    .entered();
+
+    let result = if is_blank(bytes) {
+        Err(XmlRejection::EmptyBody)
+    } else {
+        deserialize(bytes, charset, config)
+    };
+
+    #[cfg(feature = "tracing")]
+    span.record("outcome", &if result.is_ok() { "ok" } else { "rejected" });
+    #[cfg(feature = "metrics")]
+    crate::telemetry::record_request(bytes.len(), result.is_ok());
+
+    result
+}
+
+/// Bodies up to this many bytes are first deserialized straight from the slice.
+///
+/// Below it, setting up the buffered reader costs about as much as the parse itself.
+pub(crate) const SMALL_BODY: usize = 16 * 1024;
+
+/// Deserialize a buffered request body.
+///
+/// `charset` is the parameter of the same name from the request's `Content-Type`. Parse
+/// failures are reported together with the position the parser had reached, as long as it
+/// read the body as it was sent. Bodies that were transcoded or rewritten first, to remove
+/// comments or expand entities for instance, are reported without one.
+pub(crate) fn deserialize<T>(
+    bytes: &[u8],
+    charset: Option<&str>,
+    config: &XmlConfig,
+) -> Result<T, XmlRejection>
+where
+    T: DeserializeOwned,
+{
+    let utf8 = encoding::to_utf8(bytes, charset)?;
+    let result = deserialize_utf8(&utf8, config);
+    match utf8 {
+        // A byte order mark is all that can have been skipped.
+        Cow::Borrowed(utf8) => result.map_err(|err| err.skipped(bytes, bytes.len() - utf8.len())),
+        Cow::Owned(_) => result.map_err(XmlRejection::without_position),
+    }
+}
+
+/// Deserialize a request body that is already UTF-8.
+pub(crate) fn deserialize_utf8<T>(bytes: &[u8], config: &XmlConfig) -> Result<T, XmlRejection>
+where
+    T: DeserializeOwned,
+{
+    let prepared = prepare(bytes, config)?;
+    let result = deserialize_prepared(&prepared, config);
+    match prepared {
+        Cow::Borrowed(_) => result,
+        Cow::Owned(_) => result.map_err(XmlRejection::without_position),
+    }
+}
+
+fn deserialize_prepared<T>(bytes: &[u8], config: &XmlConfig) -> Result<T, XmlRejection>
+where
+    T: DeserializeOwned,
+{
+    if config.deny_unknown_elements() {
+        return from_reader_denying_unknown(bytes);
+    }
+    if bytes.len() <= SMALL_BODY {
+        if let Some(value) = from_slice(bytes) {
+            return Ok(value);
+        }
+    }
+    from_reader(bytes)
+}
+
+/// Deserialize a small document in a single pass over `bytes`, without the reader's buffer.
+///
+/// Returns `None` if `bytes` fails to deserialize, leaving it to [`from_reader`] to find the
+/// error again together with its position, so both paths reject a document the same way.
+pub(crate) fn from_slice<T>(bytes: &[u8]) -> Option<T>
+where
+    T: DeserializeOwned,
+{
+    backend::from_str(std::str::from_utf8(bytes).ok()?).ok()
+}
+
+/// Deserialize `bytes` through a reader that tracks how far the parser got.
+pub(crate) fn from_reader<'de, T>(bytes: &[u8]) -> Result<T, XmlRejection>
+where
+    T: Deserialize<'de>,
+{
+    let mut reader = PositionReader::new(bytes);
+
+    backend::from_reader(&mut reader)
+        .map_err(|err| XmlRejection::invalid_body(err, bytes, reader.position))
+}
+
+/// Deserialize `bytes` like [`from_reader`], rejecting the document if it has an element or
+/// attribute the value has no field for.
+fn from_reader_denying_unknown<'de, T>(bytes: &[u8]) -> Result<T, XmlRejection>
+where
+    T: Deserialize<'de>,
+{
+    let mut reader = PositionReader::new(bytes);
+    let mut unknown = None;
+
+    let value =
+        backend::from_reader_with_ignored(&mut reader, |path| record_unknown(&mut unknown, path))
+            .map_err(|err| XmlRejection::invalid_body(err, bytes, reader.position))?;
+
+    match unknown {
+        Some(name) => Err(XmlRejection::UnknownElement { name }),
+        None => Ok(value),
+    }
+}
+
+/// Deserialize a checked UTF-8 document into a value that may borrow from it.
+///
+/// The parser can't report how far it got here, so a document that fails to deserialize is
+/// read once more with [`from_reader`] to find the position of the error. Values that fail
+/// differently there, on a field that has to borrow for instance, are rejected without one.
+pub(crate) fn from_str<'de, T>(bytes: &'de [u8], config: &XmlConfig) -> Result<T, XmlRejection>
+where
+    T: Deserialize<'de>,
+{
+    let xml = std::str::from_utf8(bytes)
+        .map_err(|err| XmlRejection::invalid_utf8(bytes, err.valid_up_to()))?;
+
+    let deny_unknown = config.deny_unknown_elements();
+    let result = if deny_unknown {
+        from_str_denying_unknown(xml)
+    } else {
+        backend::from_str(xml).map_err(XmlRejection::from)
+    };
+    let source = match result {
+        Err(XmlRejection::InvalidXMLBody { source, .. }) => source,
+        result => return result,
+    };
+
+    let located = if deny_unknown {
+        from_reader_denying_unknown::<T>(bytes)
+    } else {
+        from_reader::<T>(bytes)
+    };
+    match located {
+        Err(XmlRejection::InvalidXMLBody {
+            source: found,
+            position,
+        }) if found.to_string() == source.to_string() => {
+            Err(XmlRejection::InvalidXMLBody { source, position })
+        }
+        _ => Err(XmlRejection::from(source)),
+    }
+}
+
+/// Deserialize `xml` like [`from_str`], rejecting the document if it has an element or
+/// attribute the value has no field for.
+fn from_str_denying_unknown<'de, T>(xml: &'de str) -> Result<T, XmlRejection>
+where
+    T: Deserialize<'de>,
+{
+    let mut unknown = None;
+    let value = backend::from_str_with_ignored(xml, |path| record_unknown(&mut unknown, path))?;
+
+    match unknown {
+        Some(name) => Err(XmlRejection::UnknownElement { name }),
+        None => Ok(value),
+    }
+}
+
+/// Keep the first element or attribute in `unknown` that has no field, leaving out the
+/// attributes of [`is_reserved_attribute`].
+fn record_unknown(unknown: &mut Option<String>, path: serde_ignored::Path<'_>) {
+    if unknown.is_some() {
+        return;
+    }
+    *unknown = match path {
+        serde_ignored::Path::Map { key, .. } if is_reserved_attribute(&key) => None,
+        serde_ignored::Path::Map { key, .. } => Some(key),
+        path => Some(path.to_string()),
+    };
+}
+
+/// Whether `name` is a namespace declaration or an `xml:` attribute such as `xml:lang`, which
+/// quick-xml passes on like any other attribute but which aren't fields of the document.
+///
+/// serde-xml-rs leaves out namespace declarations and drops the `xml` prefix, so there such
+/// attributes can't be told apart from fields.
+fn is_reserved_attribute(name: &str) -> bool {
+    let name = name.strip_prefix('@').unwrap_or(name);
+    name == "xmlns" || name.starts_with("xmlns:") || name.starts_with("xml:")
+}
+
+/// Check a UTF-8 document against `config` and rewrite it the way `config` asks for, returning
+/// what the deserializer is to read.
+fn prepare<'a>(bytes: &'a [u8], config: &XmlConfig) -> Result<Cow<'a, [u8]>, XmlRejection> {
+    let scanned = scan::check(bytes, config)?;
+
+    // Before anything that reads the text, which would reject the references.
+    let mut bytes = entity::expand(bytes, &scanned.entities, config)?;
+    if scanned.has_comments {
+        bytes = rewritten(bytes, text::strip_comments);
+    }
+    if config.xsi_nil() {
+        bytes = rewritten(bytes, xsi_nil::drop_nil_elements);
+    }
+    if !config.trim_text() {
+        bytes = rewritten(bytes, text::preserve_whitespace);
+    } else if scanned.has_xml_space {
+        bytes = rewritten(bytes, text::preserve_marked_whitespace);
+    }
+    if cfg!(feature = "backend-quick-xml") && config.strip_prefixes() {
+        bytes = rewritten(bytes, text::strip_prefixes);
+    }
+    if cfg!(feature = "backend-quick-xml") && config.attribute_prefix() {
+        bytes = rewritten(bytes, text::prefix_attributes);
+    }
+    Ok(bytes)
+}
+
+fn rewritten<'a>(bytes: Cow<'a, [u8]>, rewrite: fn(&[u8]) -> Cow<'_, [u8]>) -> Cow<'a, [u8]> {
+    let owned = match rewrite(&bytes) {
+        Cow::Owned(owned) => owned,
+        Cow::Borrowed(_) => return bytes,
+    };
+    Cow::Owned(owned)
+}
+
+/// Byte reader which remembers how far the parser has consumed its input.
+struct PositionReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PositionReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+}
+
+impl Read for PositionReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.fill_buf()?.read(buf)?;
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for PositionReader<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(&self.bytes[self.position..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position = (self.position + amt).min(self.bytes.len());
+    }
+}
@@ -0,0 +1,188 @@
+use http::{header, HeaderMap};
+use quick_xml::{events::Event, Reader};
+use std::borrow::Cow;
+
+use crate::rejection::XmlRejection;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// Character encoding an XML response is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// UTF-8, without a byte order mark.
+    #[default]
+    Utf8,
+    /// Little-endian UTF-16, after a byte order mark.
+    Utf16Le,
+    /// Big-endian UTF-16, after a byte order mark.
+    Utf16Be,
+}
+
+impl Encoding {
+    /// Name of the encoding in the XML declaration.
+    pub(crate) fn declared_name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le | Encoding::Utf16Be => "UTF-16",
+        }
+    }
+
+    /// `charset` parameter of the `Content-Type`, if the encoding needs one.
+    ///
+    /// The byte order mark tells the byte order of UTF-16, so both orders are labelled `utf-16`.
+    pub(crate) fn charset(self) -> Option<&'static str> {
+        match self {
+            Encoding::Utf8 => None,
+            Encoding::Utf16Le | Encoding::Utf16Be => Some("utf-16"),
+        }
+    }
+
+    /// Transcode a serialized UTF-8 document to this encoding.
+    pub(crate) fn encode(self, utf8: Vec<u8>) -> Vec<u8> {
+        let code_unit_bytes = match self {
+            Encoding::Utf8 => return utf8,
+            Encoding::Utf16Le => u16::to_le_bytes,
+            Encoding::Utf16Be => u16::to_be_bytes,
+        };
+
+        let text = String::from_utf8_lossy(&utf8);
+        let mut bytes = Vec::with_capacity(2 * (text.len() + 1));
+        for unit in std::iter::once(0xFEFF).chain(text.encode_utf16()) {
+            bytes.extend_from_slice(&code_unit_bytes(unit));
+        }
+        bytes
+    }
+}
+
+/// The `charset` parameter of the request's `Content-Type`, if any.
+pub(crate) fn charset(headers: &HeaderMap) -> Option<String> {
+    let mime = headers
+        .get(header::CONTENT_TYPE)?
+        .to_str()
+        .ok()?
+        .parse::<mime::Mime>()
+        .ok()?;
+
+    mime.get_param(mime::CHARSET)
+        .map(|charset| charset.as_str().to_owned())
+}
+
+/// Convert a request body to UTF-8 for the parser.
+///
+/// The encoding is taken from, in order of precedence, a byte order mark, the `charset` of the
+/// `Content-Type` and the encoding in the XML declaration. Bodies without any of these are
+/// UTF-8 by definition and are passed through untouched. A UTF-8 byte order mark is removed,
+/// as the parser doesn't expect one.
+pub(crate) fn to_utf8<'a>(
+    bytes: &'a [u8],
+    charset: Option<&str>,
+) -> Result<Cow<'a, [u8]>, XmlRejection> {
+    if let Some(utf8) = bytes.strip_prefix(UTF8_BOM) {
+        check_utf8(bytes, UTF8_BOM.len())?;
+        return Ok(Cow::Borrowed(utf8));
+    }
+
+    let utf8 = match encoding_label(bytes, charset) {
+        Some(label) => transcode(bytes, &label)?,
+        None => Cow::Borrowed(bytes),
+    };
+    // Transcoded bodies are valid UTF-8, borrowed ones were taken to be UTF-8 already.
+    if let Cow::Borrowed(_) = utf8 {
+        check_utf8(bytes, 0)?;
+    }
+    Ok(utf8)
+}
+
+/// Reject `bytes` unless they are valid UTF-8 from `start` on, pointing at the first byte of
+/// the first invalid sequence.
+fn check_utf8(bytes: &[u8], start: usize) -> Result<(), XmlRejection> {
+    std::str::from_utf8(&bytes[start..])
+        .map(drop)
+        .map_err(|err| XmlRejection::invalid_utf8(bytes, start + err.valid_up_to()))
+}
+
+fn encoding_label<'a>(bytes: &'a [u8], charset: Option<&'a str>) -> Option<Cow<'a, str>> {
+    if bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(b"<\0?\0") {
+        return Some("utf-16le".into());
+    }
+    if bytes.starts_with(&[0xFE, 0xFF]) || bytes.starts_with(b"\0<\0?") {
+        return Some("utf-16be".into());
+    }
+    if let Some(charset) = charset {
+        return Some(charset.into());
+    }
+
+    match Reader::from_reader(bytes).read_event() {
+        Ok(Event::Decl(decl)) => match decl.encoding() {
+            Some(Ok(encoding)) => Some(String::from_utf8_lossy(&encoding).into_owned().into()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "encoding"))]
+fn transcode<'a>(bytes: &'a [u8], label: &str) -> Result<Cow<'a, [u8]>, XmlRejection> {
+    let label = label.trim();
+    if label.eq_ignore_ascii_case("utf-8")
+        || label.eq_ignore_ascii_case("utf8")
+        || label.eq_ignore_ascii_case("us-ascii")
+    {
+        Ok(Cow::Borrowed(bytes))
+    } else {
+        Err(XmlRejection::UnsupportedEncoding)
+    }
+}
+
+#[cfg(feature = "encoding")]
+fn transcode<'a>(bytes: &'a [u8], label: &str) -> Result<Cow<'a, [u8]>, XmlRejection> {
+    use encoding_rs::{Encoding, UTF_8};
+
+    let encoding =
+        Encoding::for_label(label.as_bytes()).ok_or(XmlRejection::UnsupportedEncoding)?;
+    if encoding == UTF_8 {
+        return Ok(Cow::Borrowed(bytes));
+    }
+
+    let bom_length = match Encoding::for_bom(bytes) {
+        Some((bom_encoding, length)) if bom_encoding == encoding => length,
+        _ => 0,
+    };
+    let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+
+    Ok(Cow::Owned(without_declared_encoding(&text)))
+}
+
+/// Drop the `encoding` from the XML declaration of a document that has been converted to
+/// UTF-8, so the parser doesn't try to decode it a second time.
+#[cfg(feature = "encoding")]
+fn without_declared_encoding(text: &str) -> Vec<u8> {
+    use quick_xml::{events::BytesDecl, writer::Writer};
+
+    let mut reader = Reader::from_str(text);
+    let decl = match reader.read_event() {
+        Ok(Event::Decl(decl)) => decl,
+        _ => return text.as_bytes().to_vec(),
+    };
+
+    let version = decl
+        .version()
+        .map(|version| String::from_utf8_lossy(&version).into_owned());
+    let standalone = decl
+        .standalone()
+        .and_then(Result::ok)
+        .map(|standalone| String::from_utf8_lossy(&standalone).into_owned());
+
+    let mut writer = Writer::new(Vec::with_capacity(text.len()));
+    let decl = BytesDecl::new(
+        version.as_deref().unwrap_or("1.0"),
+        None,
+        standalone.as_deref(),
+    );
+    // Writing to a `Vec` can't fail.
+    let _ = writer.write_event(Event::Decl(decl));
+
+    let mut bytes = writer.into_inner();
+    bytes.extend_from_slice(&text.as_bytes()[reader.buffer_position()..]);
+    bytes
+}
@@ -0,0 +1,169 @@
+//! Named entities besides the five XML predefines, registered with
+//! [`XmlConfig::with_entities`] and
+//! [`XmlEncoder::with_entities`](crate::XmlEncoder::with_entities).
+
+use std::{borrow::Cow, collections::BTreeMap, convert::Infallible};
+
+use crate::{chars::markup_end, config::XmlConfig, rejection::XmlRejection};
+
+const PREDEFINED: [&str; 5] = ["amp", "lt", "gt", "quot", "apos"];
+
+/// Panic unless `name` can be registered as an entity.
+pub(crate) fn check_name(name: &str) {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '-' | '.'));
+    assert!(valid, "`{}` is not a valid entity name", name);
+    assert!(
+        !PREDEFINED.contains(&name),
+        "`{}` is predefined and can't be registered",
+        name
+    );
+}
+
+/// Replace references to the entities of `config` and to the internal entities `declared` in
+/// the document's DOCTYPE in text and attribute values with their replacement text.
+///
+/// A declaration in the document wins over an entity of `config` with the same name. The text
+/// of `config`'s entities is escaped and counts toward the `max_bytes` of
+/// [`XmlConfig::with_entity_limit`]; declared entities were already counted when the document
+/// was scanned, and their text is markup. Other references are left for the parser, which
+/// rejects those it doesn't know. Documents that aren't UTF-8 are returned untouched.
+pub(crate) fn expand<'a>(
+    xml: &'a [u8],
+    declared: &BTreeMap<String, String>,
+    config: &XmlConfig,
+) -> Result<Cow<'a, [u8]>, XmlRejection> {
+    let entities = config.entities();
+    if (entities.is_empty() && declared.is_empty()) || !xml.contains(&b'&') {
+        return Ok(Cow::Borrowed(xml));
+    }
+    let text = match std::str::from_utf8(xml) {
+        Ok(text) => text,
+        Err(_) => return Ok(Cow::Borrowed(xml)),
+    };
+
+    let mut expanded = 0;
+    let mut out = String::with_capacity(text.len());
+    rewrite_values(text, &mut out, |mut rest, out| {
+        while let Some(amp) = rest.find('&') {
+            out.push_str(&rest[..amp]);
+            rest = &rest[amp..];
+
+            let end = match rest[1..].find(';') {
+                Some(end) => end,
+                None => {
+                    out.push('&');
+                    rest = &rest[1..];
+                    continue;
+                }
+            };
+            let name = &rest[1..=end];
+            if let Some(replacement) = declared.get(name) {
+                out.push_str(replacement);
+            } else if let Some(replacement) = entities.get(name) {
+                expanded += replacement.len();
+                if expanded > config.entity_max_bytes() {
+                    return Err(XmlRejection::EntityExpansionLimit);
+                }
+                out.push_str(&quick_xml::escape::escape(replacement.as_str()));
+            } else {
+                out.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+            rest = &rest[end + 2..];
+        }
+        out.push_str(rest);
+        Ok(())
+    })?;
+
+    if out == text {
+        Ok(Cow::Borrowed(xml))
+    } else {
+        Ok(Cow::Owned(out.into_bytes()))
+    }
+}
+
+/// Write the text of `entities` in text and attribute values of a serialized document as
+/// references to them, longer ones first.
+///
+/// The replacement text is matched as the serializer escapes it, so `R&D` matches `R&amp;D`.
+pub(crate) fn write_references(body: Vec<u8>, entities: &BTreeMap<String, String>) -> Vec<u8> {
+    let text = match String::from_utf8(body) {
+        Ok(text) => text,
+        Err(err) => return err.into_bytes(),
+    };
+    let mut patterns: Vec<(String, String)> = entities
+        .iter()
+        .filter(|(_, replacement)| !replacement.is_empty())
+        .map(|(name, replacement)| {
+            let written = quick_xml::escape::escape(replacement.as_str()).into_owned();
+            (written, format!("&{};", name))
+        })
+        .collect();
+    patterns.sort_by_key(|(written, _)| std::cmp::Reverse(written.len()));
+
+    let mut out = String::with_capacity(text.len());
+    let written: Result<(), Infallible> = rewrite_values(&text, &mut out, |mut rest, out| {
+        'text: while let Some(c) = rest.chars().next() {
+            for (written, reference) in &patterns {
+                if let Some(after) = rest.strip_prefix(written.as_str()) {
+                    out.push_str(reference);
+                    rest = after;
+                    continue 'text;
+                }
+            }
+            // Copy other references whole, so no replacement text is found inside of them.
+            let len = match c {
+                '&' => rest.find(';').map_or(rest.len(), |end| end + 1),
+                c => c.len_utf8(),
+            };
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+        }
+        Ok(())
+    });
+    written.unwrap_or_else(|never| match never {});
+    out.into_bytes()
+}
+
+/// Copy `xml` to `out`, its text and the quoted attribute values of its tags through
+/// `rewrite` and the rest as it is.
+///
+/// References aren't recognized in comments, CDATA sections, processing instructions and the
+/// DOCTYPE, so those are copied as they are too.
+fn rewrite_values<E>(
+    xml: &str,
+    out: &mut String,
+    mut rewrite: impl FnMut(&str, &mut String) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rewrite(&rest[..start], out)?;
+        let end = start + markup_end(&rest[start..]);
+        let mut tag = &rest[start..end];
+        rest = &rest[end..];
+        if tag.starts_with("<!") || tag.starts_with("<?") {
+            out.push_str(tag);
+            continue;
+        }
+
+        while let Some(open) = tag.find(['"', '\'']) {
+            let quote = &tag[open..=open];
+            out.push_str(&tag[..=open]);
+            tag = &tag[open + 1..];
+            let close = tag.find(quote).unwrap_or(tag.len());
+            rewrite(&tag[..close], out)?;
+            tag = &tag[close..];
+            if let Some(after) = tag.strip_prefix(quote) {
+                out.push_str(quote);
+                tag = after;
+            }
+        }
+        out.push_str(tag);
+    }
+    rewrite(rest, out)
+}
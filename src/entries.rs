@@ -0,0 +1,107 @@
+//! Maps written as `<entry>` elements, for keys that aren't element names.
+//!
+//! A map is normally written with one element per entry, named after its key, which only
+//! works for keys that happen to be valid XML names. Mark a map field with
+//! `#[serde(with = "rustcms_axum_xml::entries")]` to write every entry as
+//! `<entry key="..."><value>...</value></entry>` instead, and read such entries back into the
+//! map. The key is an attribute with the quick-xml backend and a `<key>` element with
+//! serde-xml-rs.
+//!
+//! ```rust
+//! use rustcms_axum_xml::Xml;
+//! use serde::{Deserialize, Serialize};
+//! use std::collections::BTreeMap;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Headers {
+//!     #[serde(with = "rustcms_axum_xml::entries")]
+//!     values: BTreeMap<String, String>,
+//! }
+//!
+//! let headers = Headers {
+//!     values: BTreeMap::from([("Accept Language".to_owned(), "de".to_owned())]),
+//! };
+//! let bytes = Xml::to_bytes(&headers).unwrap();
+//! # #[cfg(feature = "backend-quick-xml")]
+//! assert_eq!(
+//!     bytes,
+//!     b"<Headers><values><entry key=\"Accept Language\"><value>de</value></entry>\
+//!       </values></Headers>",
+//! );
+//!
+//! let Xml(read) = Xml::<Headers>::from_bytes(&bytes).unwrap();
+//! assert_eq!(read.values, headers.values);
+//! ```
+
+use serde::{
+    ser::{Serialize, SerializeStruct, Serializer},
+    Deserialize, Deserializer,
+};
+
+use crate::list::Element;
+
+/// Serialize the entries of `map` as `<entry>` elements.
+pub fn serialize<'a, M, K, V, S>(map: &'a M, serializer: S) -> Result<S::Ok, S::Error>
+where
+    &'a M: IntoIterator<Item = (&'a K, &'a V)>,
+    K: Serialize + 'a,
+    V: Serialize + 'a,
+    S: Serializer,
+{
+    let entries: Vec<_> = map.into_iter().collect();
+    // One field per entry, as with `XmlList`, for serde-xml-rs.
+    let mut state = serializer.serialize_struct("entries", entries.len())?;
+    for (key, value) in entries {
+        state.serialize_field("entry", &Entry { key, value })?;
+    }
+    state.end()
+}
+
+/// Deserialize the `<entry>` elements written by [`serialize`] into a map.
+pub fn deserialize<'de, M, K, V, D>(deserializer: D) -> Result<M, D::Error>
+where
+    M: FromIterator<(K, V)>,
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(bound = "K: Deserialize<'de>, V: Deserialize<'de>")]
+    struct Entries<K, V> {
+        #[serde(rename = "entry", default = "Vec::new")]
+        entries: Vec<OwnedEntry<K, V>>,
+    }
+
+    #[derive(Deserialize)]
+    struct OwnedEntry<K, V> {
+        key: K,
+        value: V,
+    }
+
+    let Entries { entries } = Entries::deserialize(deserializer)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| (entry.key, entry.value))
+        .collect())
+}
+
+struct Entry<'a, K, V> {
+    key: &'a K,
+    value: &'a V,
+}
+
+impl<K, V> Serialize for Entry<'_, K, V>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut entry = serializer.serialize_struct("entry", 2)?;
+        entry.serialize_field("key", self.key)?;
+        entry.serialize_field("value", &Element(self.value))?;
+        entry.end()
+    }
+}
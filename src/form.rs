@@ -0,0 +1,110 @@
+use async_trait::async_trait;
+use axum_core::{extract::FromRequest, BoxError};
+use http::{header, HeaderMap, Request};
+use http_body::Body as HttpBody;
+use serde::de::DeserializeOwned;
+use std::ops::{Deref, DerefMut};
+
+use crate::{
+    body::read_body, config::XmlConfig, de::Document, rejection::XmlRejection, xml_content_type,
+};
+
+/// XML Extractor that also takes `application/x-www-form-urlencoded` bodies.
+///
+/// For senders such as legacy webhooks that post either format to the same endpoint. XML
+/// bodies are extracted the same way as with [`Xml`](crate::Xml), form bodies with
+/// [serde_urlencoded](https://crates.io/crates/serde_urlencoded), into the same `T`. Requests
+/// of any other type are rejected with [`XmlRejection::NotXmlOrForm`].
+///
+/// Forms are flat, so a `T` that is to be read from both can only have fields of strings,
+/// numbers and the like.
+///
+/// ```rust,no_run
+/// use axum::{routing::post, Router};
+/// use serde::Deserialize;
+/// use rustcms_axum_xml::XmlOrForm;
+///
+/// #[derive(Deserialize)]
+/// struct Notification {
+///     event: String,
+///     id: u64,
+/// }
+///
+/// async fn webhook(XmlOrForm(notification): XmlOrForm<Notification>) {
+///     // ...
+/// }
+///
+/// let app: Router = Router::new().route("/webhook", post(webhook));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct XmlOrForm<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for XmlOrForm<T>
+where
+    T: DeserializeOwned,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = XmlRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let config = XmlConfig::from_extensions(req.extensions());
+        let request_id = config.request_id(req.headers());
+
+        let extracted: Result<Self, XmlRejection> = async move {
+            if xml_content_type(req.headers(), &config) {
+                let document = Document::from_request(req, state, &config).await?;
+
+                Ok(Self(document.deserialize(&config)?))
+            } else if form_content_type(req.headers()) {
+                let bytes = read_body(req, state, &config).await?;
+
+                serde_urlencoded::from_bytes(&bytes)
+                    .map(Self)
+                    .map_err(XmlRejection::InvalidFormBody)
+            } else {
+                Err(match config.missing_content_type(&req) {
+                    XmlRejection::MissingXMLContentType { status } => {
+                        XmlRejection::NotXmlOrForm { status }
+                    }
+                    rejection => rejection,
+                })
+            }
+        }
+        .await;
+        extracted.map_err(|rejection| rejection.with_request_id(request_id))
+    }
+}
+
+impl<T> Deref for XmlOrForm<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for XmlOrForm<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Whether every `Content-Type` of the request is `application/x-www-form-urlencoded`.
+fn form_content_type(headers: &HeaderMap) -> bool {
+    let mut content_types = headers.get_all(header::CONTENT_TYPE).iter().peekable();
+    if content_types.peek().is_none() {
+        return false;
+    }
+
+    content_types.all(|content_type| {
+        content_type
+            .to_str()
+            .ok()
+            .and_then(|content_type| content_type.parse::<mime::Mime>().ok())
+            .is_some_and(|mime| mime.essence_str() == mime::APPLICATION_WWW_FORM_URLENCODED)
+    })
+}
@@ -0,0 +1,848 @@
+//! Serializer wrapper which changes how primitives and enum variants are written.
+
+use serde::ser::{
+    self, Impossible, Serialize, SerializeMap, SerializeSeq, SerializeStruct,
+    SerializeStructVariant, SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+    Serializer,
+};
+use std::fmt;
+
+use crate::xsi_nil::{self, NilElement};
+
+/// How `bool` values are written in XML responses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BoolFormat {
+    /// `true` and `false`, as serde writes them.
+    #[default]
+    Literal,
+    /// `1` and `0`.
+    Numeric,
+}
+
+/// `value`, serialized with its `bool`s in `format`, and with its enum variants written as
+/// elements named after the variant.
+///
+/// Serializers name the element of a newtype variant holding a struct after the struct, as in
+/// `<Square><Side len="2"/></Square>`, which the deserializers don't read back, so its fields
+/// are written as those of a struct variant instead: `<Square len="2"/>`. serde-xml-rs also
+/// wraps a struct variant at the root in an element named after the enum, so at the root it is
+/// written as a struct named after the variant.
+pub(crate) struct Formatted<'a, T: ?Sized> {
+    value: &'a T,
+    format: BoolFormat,
+    root: bool,
+}
+
+impl<'a, T: ?Sized> Formatted<'a, T> {
+    pub(crate) fn new(value: &'a T, format: BoolFormat) -> Self {
+        Self {
+            value,
+            format,
+            root: true,
+        }
+    }
+
+    fn nested(value: &'a T, format: BoolFormat) -> Self {
+        Self {
+            value,
+            format,
+            root: false,
+        }
+    }
+}
+
+impl<T> Serialize for Formatted<'_, T>
+where
+    T: Serialize + ?Sized,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.value.serialize(FormatSerializer {
+            inner: serializer,
+            format: self.format,
+            root: self.root,
+        })
+    }
+}
+
+struct FormatSerializer<S> {
+    inner: S,
+    format: BoolFormat,
+    root: bool,
+}
+
+impl<S> FormatSerializer<S> {
+    fn wrap<'a, T: ?Sized>(&self, value: &'a T) -> Formatted<'a, T> {
+        Formatted::nested(value, self.format)
+    }
+
+    /// Wrap `value`, which takes the place of the value being serialized, as with
+    /// `Some(value)`.
+    fn wrap_in_place<'a, T: ?Sized>(&self, value: &'a T) -> Formatted<'a, T> {
+        Formatted {
+            value,
+            format: self.format,
+            root: self.root,
+        }
+    }
+}
+
+macro_rules! forward {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> Result<Self::Ok, Self::Error> {
+                self.inner.$method($($arg),*)
+            }
+        )*
+    };
+}
+
+impl<S> Serializer for FormatSerializer<S>
+where
+    S: Serializer,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Compound<S::SerializeSeq>;
+    type SerializeTuple = Compound<S::SerializeTuple>;
+    type SerializeTupleStruct = Compound<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = Compound<S::SerializeTupleVariant>;
+    type SerializeMap = Compound<S::SerializeMap>;
+    type SerializeStruct = Compound<S::SerializeStruct>;
+    type SerializeStructVariant = Fields<S::SerializeStruct, S::SerializeStructVariant>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        match self.format {
+            BoolFormat::Literal => self.inner.serialize_bool(v),
+            BoolFormat::Numeric => self.inner.serialize_u8(u8::from(v)),
+        }
+    }
+
+    forward! {
+        serialize_i8(v: i8);
+        serialize_i16(v: i16);
+        serialize_i32(v: i32);
+        serialize_i64(v: i64);
+        serialize_i128(v: i128);
+        serialize_u8(v: u8);
+        serialize_u16(v: u16);
+        serialize_u32(v: u32);
+        serialize_u64(v: u64);
+        serialize_u128(v: u128);
+        serialize_f32(v: f32);
+        serialize_f64(v: f64);
+        serialize_char(v: char);
+        serialize_str(v: &str);
+        serialize_bytes(v: &[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(name: &'static str);
+        serialize_unit_variant(name: &'static str, variant_index: u32, variant: &'static str);
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let value = self.wrap_in_place(value);
+        self.inner.serialize_some(&value)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        if name == xsi_nil::NIL {
+            // Only fields can be nil elements; anywhere else it is a plain `None`.
+            return self.inner.serialize_none();
+        }
+        let value = self.wrap_in_place(value);
+        self.inner.serialize_newtype_struct(name, &value)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        if (cfg!(feature = "backend-quick-xml") || self.root) && is_struct(value) {
+            return value.serialize(VariantFields {
+                serializer: self,
+                name,
+                variant_index,
+                variant,
+            });
+        }
+
+        let value = self.wrap(value);
+        self.inner
+            .serialize_newtype_variant(name, variant_index, variant, &value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        let format = self.format;
+        Ok(Compound {
+            inner: self.inner.serialize_seq(len)?,
+            format,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        let format = self.format;
+        Ok(Compound {
+            inner: self.inner.serialize_tuple(len)?,
+            format,
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        let format = self.format;
+        Ok(Compound {
+            inner: self.inner.serialize_tuple_struct(name, len)?,
+            format,
+        })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        let format = self.format;
+        Ok(Compound {
+            inner: self
+                .inner
+                .serialize_tuple_variant(name, variant_index, variant, len)?,
+            format,
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        let format = self.format;
+        Ok(Compound {
+            inner: self.inner.serialize_map(len)?,
+            format,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        let format = self.format;
+        Ok(Compound {
+            inner: self.inner.serialize_struct(name, len)?,
+            format,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        let format = self.format;
+        if cfg!(feature = "backend-serde-xml-rs") && self.root {
+            return Ok(Fields::Struct(Compound {
+                inner: self.inner.serialize_struct(variant, len)?,
+                format,
+            }));
+        }
+
+        Ok(Fields::Variant(Compound {
+            inner: self
+                .inner
+                .serialize_struct_variant(name, variant_index, variant, len)?,
+            format,
+        }))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+/// Compound serializer of the wrapped serializer, wrapping the values passed to it in turn.
+struct Compound<C> {
+    inner: C,
+    format: BoolFormat,
+}
+
+impl<C> SerializeSeq for Compound<C>
+where
+    C: SerializeSeq,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.inner
+            .serialize_element(&Formatted::nested(value, self.format))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C> SerializeTuple for Compound<C>
+where
+    C: SerializeTuple,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.inner
+            .serialize_element(&Formatted::nested(value, self.format))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C> SerializeTupleStruct for Compound<C>
+where
+    C: SerializeTupleStruct,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.inner
+            .serialize_field(&Formatted::nested(value, self.format))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C> SerializeTupleVariant for Compound<C>
+where
+    C: SerializeTupleVariant,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.inner
+            .serialize_field(&Formatted::nested(value, self.format))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C> SerializeMap for Compound<C>
+where
+    C: SerializeMap,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.inner
+            .serialize_key(&Formatted::nested(key, self.format))
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.inner
+            .serialize_value(&Formatted::nested(value, self.format))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C> SerializeStruct for Compound<C>
+where
+    C: SerializeStruct,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        match field_kind(key, value) {
+            FieldKind::Omitted => self.inner.skip_field(key),
+            FieldKind::EmptyElement => self.inner.serialize_field(key, &EmptyElement),
+            FieldKind::Nil => self.inner.serialize_field(key, &NilElement),
+            FieldKind::Value => self
+                .inner
+                .serialize_field(key, &Formatted::nested(value, self.format)),
+        }
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C> SerializeStructVariant for Compound<C>
+where
+    C: SerializeStructVariant,
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        match field_kind(key, value) {
+            FieldKind::Omitted => self.inner.skip_field(key),
+            FieldKind::EmptyElement => self.inner.serialize_field(key, &EmptyElement),
+            FieldKind::Nil => self.inner.serialize_field(key, &NilElement),
+            FieldKind::Value => self
+                .inner
+                .serialize_field(key, &Formatted::nested(value, self.format)),
+        }
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+/// Fields of a struct variant, written either as such or as a struct named after the variant.
+enum Fields<S, V> {
+    Struct(Compound<S>),
+    Variant(Compound<V>),
+}
+
+impl<S, V> Fields<S, V>
+where
+    S: SerializeStruct,
+    V: SerializeStructVariant<Ok = S::Ok, Error = S::Error>,
+{
+    fn field<T>(&mut self, key: &'static str, value: &T) -> Result<(), S::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        match self {
+            Self::Struct(fields) => SerializeStruct::serialize_field(fields, key, value),
+            Self::Variant(fields) => SerializeStructVariant::serialize_field(fields, key, value),
+        }
+    }
+
+    fn skip(&mut self, key: &'static str) -> Result<(), S::Error> {
+        match self {
+            Self::Struct(fields) => SerializeStruct::skip_field(fields, key),
+            Self::Variant(fields) => SerializeStructVariant::skip_field(fields, key),
+        }
+    }
+
+    fn finish(self) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Struct(fields) => SerializeStruct::end(fields),
+            Self::Variant(fields) => SerializeStructVariant::end(fields),
+        }
+    }
+}
+
+impl<S, V> SerializeStruct for Fields<S, V>
+where
+    S: SerializeStruct,
+    V: SerializeStructVariant<Ok = S::Ok, Error = S::Error>,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.field(key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.skip(key)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<S, V> SerializeStructVariant for Fields<S, V>
+where
+    S: SerializeStruct,
+    V: SerializeStructVariant<Ok = S::Ok, Error = S::Error>,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.field(key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.skip(key)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+/// Serializer for the struct in a newtype variant, which writes its fields as those of a
+/// struct variant.
+///
+/// Only used once [`is_struct`] has found the value to be a struct.
+struct VariantFields<S> {
+    serializer: FormatSerializer<S>,
+    name: &'static str,
+    variant_index: u32,
+    variant: &'static str,
+}
+
+macro_rules! not_struct {
+    ($($method:ident($($ty:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> Result<$ok, S::Error> {
+                Err(ser::Error::custom("expected a struct in the newtype variant"))
+            }
+        )*
+    };
+}
+
+impl<S> Serializer for VariantFields<S>
+where
+    S: Serializer,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Impossible<S::Ok, S::Error>;
+    type SerializeTuple = Impossible<S::Ok, S::Error>;
+    type SerializeTupleStruct = Impossible<S::Ok, S::Error>;
+    type SerializeTupleVariant = Impossible<S::Ok, S::Error>;
+    type SerializeMap = Impossible<S::Ok, S::Error>;
+    type SerializeStruct = Fields<S::SerializeStruct, S::SerializeStructVariant>;
+    type SerializeStructVariant = Impossible<S::Ok, S::Error>;
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serializer
+            .serialize_struct_variant(self.name, self.variant_index, self.variant, len)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    not_struct! {
+        serialize_bool(bool) -> S::Ok;
+        serialize_i8(i8) -> S::Ok;
+        serialize_i16(i16) -> S::Ok;
+        serialize_i32(i32) -> S::Ok;
+        serialize_i64(i64) -> S::Ok;
+        serialize_i128(i128) -> S::Ok;
+        serialize_u8(u8) -> S::Ok;
+        serialize_u16(u16) -> S::Ok;
+        serialize_u32(u32) -> S::Ok;
+        serialize_u64(u64) -> S::Ok;
+        serialize_u128(u128) -> S::Ok;
+        serialize_f32(f32) -> S::Ok;
+        serialize_f64(f64) -> S::Ok;
+        serialize_char(char) -> S::Ok;
+        serialize_str(&str) -> S::Ok;
+        serialize_bytes(&[u8]) -> S::Ok;
+        serialize_none() -> S::Ok;
+        serialize_unit() -> S::Ok;
+        serialize_unit_struct(&'static str) -> S::Ok;
+        serialize_unit_variant(&'static str, u32, &'static str) -> S::Ok;
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize)
+            -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct_variant(&'static str, u32, &'static str, usize)
+            -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(ser::Error::custom(
+            "expected a struct in the newtype variant",
+        ))
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(ser::Error::custom(
+            "expected a struct in the newtype variant",
+        ))
+    }
+}
+
+/// Whether `value` serializes as a struct, looking through newtype structs.
+fn is_struct<T>(value: &T) -> bool
+where
+    T: Serialize + ?Sized,
+{
+    matches!(value.serialize(Probe), Err(Probed::Struct))
+}
+
+/// How a struct field is written, so that an empty string and a missing value read back as
+/// what they were.
+///
+/// quick-xml writes fields holding strings as attributes, but drops them when the string is
+/// empty, so they are written as an empty element instead, which it reads back as an empty
+/// string, also into an `Option<String>`. serde-xml-rs writes `None` as an empty element, which
+/// it reads back as `Some("")`, so the field is left out instead. A `None` written by
+/// [`xsi_nil::serialize`] becomes an element with `xsi:nil="true"` for both.
+enum FieldKind {
+    Value,
+    EmptyElement,
+    Nil,
+    Omitted,
+}
+
+fn field_kind<T>(key: &str, value: &T) -> FieldKind
+where
+    T: Serialize + ?Sized,
+{
+    match value.serialize(Probe) {
+        Err(Probed::EmptyStr)
+            if cfg!(feature = "backend-quick-xml") && key != "$value" && !key.starts_with('@') =>
+        {
+            FieldKind::EmptyElement
+        }
+        Err(Probed::None) if cfg!(feature = "backend-serde-xml-rs") => FieldKind::Omitted,
+        Err(Probed::Nil) => FieldKind::Nil,
+        _ => FieldKind::Value,
+    }
+}
+
+/// Element without attributes or content, named after the field it is written for.
+struct EmptyElement;
+
+impl Serialize for EmptyElement {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_struct("", 0)?.end()
+    }
+}
+
+/// Whether `value` is written without any structure of its own: a string, number or other
+/// single value, also inside `Some` or a newtype struct.
+pub(crate) fn is_primitive<T>(value: &T) -> bool
+where
+    T: Serialize + ?Sized,
+{
+    !matches!(
+        value.serialize(Probe),
+        Err(Probed::Struct | Probed::Compound)
+    )
+}
+
+/// Serializer which stops at the first thing it's asked to write, telling what kind of value
+/// it is: a struct, `None`, a nil written by [`xsi_nil::serialize`], an empty string, also
+/// inside `Some`, or any other primitive or compound value.
+struct Probe;
+
+#[derive(Debug)]
+enum Probed {
+    Struct,
+    None,
+    Nil,
+    EmptyStr,
+    Primitive,
+    Compound,
+}
+
+impl fmt::Display for Probed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("probed value")
+    }
+}
+
+impl std::error::Error for Probed {}
+
+impl ser::Error for Probed {
+    fn custom<M: fmt::Display>(_: M) -> Self {
+        Self::Compound
+    }
+}
+
+macro_rules! probed {
+    ($probed:ident: $($method:ident($($ty:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> Result<$ok, Probed> {
+                Err(Probed::$probed)
+            }
+        )*
+    };
+}
+
+impl Serializer for Probe {
+    type Ok = ();
+    type Error = Probed;
+    type SerializeSeq = Impossible<(), Probed>;
+    type SerializeTuple = Impossible<(), Probed>;
+    type SerializeTupleStruct = Impossible<(), Probed>;
+    type SerializeTupleVariant = Impossible<(), Probed>;
+    type SerializeMap = Impossible<(), Probed>;
+    type SerializeStruct = Impossible<(), Probed>;
+    type SerializeStructVariant = Impossible<(), Probed>;
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct, Probed> {
+        Err(Probed::Struct)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<(), Probed>
+    where
+        T: Serialize + ?Sized,
+    {
+        if name == xsi_nil::NIL {
+            return Err(Probed::Nil);
+        }
+        value.serialize(self)
+    }
+
+    probed! { Primitive:
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_i128(i128) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_u128(u128) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+    }
+
+    probed! { Compound:
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize)
+            -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct_variant(&'static str, u32, &'static str, usize)
+            -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Probed> {
+        Err(if v.is_empty() {
+            Probed::EmptyStr
+        } else {
+            Probed::Primitive
+        })
+    }
+
+    fn serialize_none(self) -> Result<(), Probed> {
+        Err(Probed::None)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<(), Probed>
+    where
+        T: Serialize + ?Sized,
+    {
+        match value.serialize(self) {
+            Err(Probed::EmptyStr) => Err(Probed::EmptyStr),
+            Err(Probed::Struct | Probed::Compound) => Err(Probed::Compound),
+            _ => Err(Probed::Primitive),
+        }
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), Probed>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(Probed::Compound)
+    }
+}
@@ -0,0 +1,151 @@
+use axum_core::{
+    body,
+    response::{IntoResponse, Response},
+    BoxError,
+};
+use bytes::Bytes;
+use http::{header, HeaderValue, Request, StatusCode};
+use http_body::Body as HttpBody;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::rejection::xml_error_response;
+
+/// Layer that makes sure the responses of the services it wraps are XML.
+///
+/// For XML-only services, to catch handlers that return something else by accident, such as a
+/// plain-text `String`. A response whose `Content-Type` is an XML type passes through
+/// untouched, and so do responses without a body, such as `204 No Content`. Any other
+/// response is replaced with a `500 Internal Server Error`, or only relabelled as
+/// `application/xml` with [`with_relabel`](Self::with_relabel).
+///
+/// The crate's rejections are plain text, so they are replaced too unless the `xml-errors`
+/// feature is on.
+///
+/// ```rust,no_run
+/// use axum::{routing::get, Router};
+/// use rustcms_axum_xml::{RequireXmlResponseLayer, Xml};
+///
+/// async fn status() -> Xml<&'static str> {
+///     Xml("ok")
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/status", get(status))
+///     .layer(RequireXmlResponseLayer::new());
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequireXmlResponseLayer {
+    relabel: bool,
+}
+
+impl RequireXmlResponseLayer {
+    /// Replace responses that aren't XML with `500 Internal Server Error`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Label responses that aren't XML as `application/xml` instead of replacing them, for
+    /// handlers known to return XML but not always to say so.
+    pub fn with_relabel(mut self, relabel: bool) -> Self {
+        self.relabel = relabel;
+        self
+    }
+}
+
+impl<S> Layer<S> for RequireXmlResponseLayer {
+    type Service = RequireXmlResponse<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequireXmlResponse {
+            inner,
+            relabel: self.relabel,
+        }
+    }
+}
+
+/// Service that makes sure the responses of `S` are XML, created by
+/// [`RequireXmlResponseLayer`].
+#[derive(Debug, Clone, Copy)]
+pub struct RequireXmlResponse<S> {
+    inner: S,
+    relabel: bool,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for RequireXmlResponse<S>
+where
+    S: Service<Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+    ResBody: HttpBody<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let relabel = self.relabel;
+        let response = self.inner.call(req);
+
+        Box::pin(async move {
+            let res = response.await?.map(body::boxed);
+            Ok(require_xml(res, relabel))
+        })
+    }
+}
+
+fn require_xml(mut res: Response, relabel: bool) -> Response {
+    let bodiless = res.status().is_informational()
+        || res.status() == StatusCode::NO_CONTENT
+        || res.status() == StatusCode::NOT_MODIFIED
+        || res.body().size_hint().exact() == Some(0);
+    if bodiless || is_xml_response(&res) {
+        return res;
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        content_type = ?res.headers().get(header::CONTENT_TYPE),
+        status = %res.status(),
+        "response is not XML"
+    );
+
+    if relabel {
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/xml"),
+        );
+        return res;
+    }
+
+    let message = "the response is not XML";
+    if cfg!(feature = "xml-errors") {
+        return xml_error_response(StatusCode::INTERNAL_SERVER_ERROR, message, None);
+    }
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(mime::TEXT_PLAIN_UTF_8.as_ref()),
+        )],
+        message,
+    )
+        .into_response()
+}
+
+fn is_xml_response(res: &Response) -> bool {
+    res.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| content_type.parse::<mime::Mime>().ok())
+        .is_some_and(|mime| crate::is_xml_mime(&mime))
+}
@@ -0,0 +1,78 @@
+use async_trait::async_trait;
+use axum_core::{extract::FromRequest, BoxError};
+use http::{header, Request};
+use http_body::Body as HttpBody;
+use serde::de::DeserializeOwned;
+use std::ops::{Deref, DerefMut};
+
+use crate::{config::XmlConfig, de::Document, rejection::XmlRejection, xml_content_type};
+
+/// XML Extractor that tolerates a missing `Content-Type` header.
+///
+/// Works like [`Xml`](crate::Xml), except that requests without any `Content-Type` are still
+/// parsed as XML. This is meant for clients such as scripts and devices that post XML without
+/// labelling it. A `Content-Type` that is present but isn't XML is rejected with
+/// [`XmlRejection::MissingXMLContentType`], the same as `Xml` does.
+///
+/// ```rust,no_run
+/// use axum::{routing::post, Router};
+/// use serde::Deserialize;
+/// use rustcms_axum_xml::XmlLenient;
+///
+/// #[derive(Deserialize)]
+/// struct Reading {
+///     value: f64,
+/// }
+///
+/// async fn record(XmlLenient(reading): XmlLenient<Reading>) {
+///     // payload is a `Reading`
+/// }
+///
+/// let app: Router = Router::new().route("/readings", post(record));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct XmlLenient<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for XmlLenient<T>
+where
+    T: DeserializeOwned,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = XmlRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let config = XmlConfig::from_extensions(req.extensions());
+        let request_id = config.request_id(req.headers());
+
+        let extracted: Result<Self, XmlRejection> = async move {
+            let headers = req.headers();
+            if headers.contains_key(header::CONTENT_TYPE) && !xml_content_type(headers, &config) {
+                return Err(config.missing_content_type(&req));
+            }
+
+            let document = Document::from_request(req, state, &config).await?;
+
+            Ok(Self(document.deserialize(&config)?))
+        }
+        .await;
+        extracted.map_err(|rejection| rejection.with_request_id(request_id))
+    }
+}
+
+impl<T> Deref for XmlLenient<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for XmlLenient<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
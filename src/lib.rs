@@ -1,23 +1,90 @@
-use axum_core::{
-    extract::{FromRequest},
-    BoxError,
-};
-use bytes::Bytes;
-use http_body::Body as HttpBody;
 use async_trait::async_trait;
 use axum_core::response::{IntoResponse, Response};
+use axum_core::{extract::FromRequest, BoxError};
 use http::{
-    header::{self, HeaderMap, HeaderValue},
-    Request, StatusCode,
+    header::{self, HeaderMap},
+    Request,
 };
+use http_body::Body as HttpBody;
 use serde::{de::DeserializeOwned, Serialize};
 use std::ops::{Deref, DerefMut};
 
-use crate::rejection::XmlRejection;
+use crate::de::Document;
 
+pub use crate::accept::AcceptedXml;
+pub use crate::borrowed::BorrowedXml;
+pub use crate::cdata::Cdata;
+pub use crate::chars::InvalidCharPolicy;
+pub use crate::config::XmlConfig;
+#[cfg(feature = "chrono")]
+pub use crate::datetime::XmlDateTime;
+pub use crate::encoding::Encoding;
+#[cfg(feature = "form")]
+pub use crate::form::XmlOrForm;
+pub use crate::format::BoolFormat;
+pub use crate::layer::{RequireXmlResponse, RequireXmlResponseLayer};
+pub use crate::lenient::XmlLenient;
+pub use crate::list::{XmlCollection, XmlList};
+#[cfg(feature = "json")]
+pub use crate::negotiate::Negotiated;
+pub use crate::optional::OptionalXml;
+pub use crate::raw::{RawXml, XmlString, XmlWithRaw};
+pub use crate::rejection::{ErrorPosition, XmlRejection};
+pub use crate::response::{XmlBody, XmlContentType, XmlEncoder, XmlResponse};
+#[cfg(feature = "xsd")]
+pub use crate::schema::{SchemaError, SchemaValidatedXml, XmlSchema};
+pub use crate::soap::{SoapBody, SoapFaultCode};
+#[cfg(feature = "streaming")]
+pub use crate::streaming::StreamingXml;
+#[cfg(feature = "validator")]
+pub use crate::validated::ValidatedXml;
+pub use crate::value::{DynXml, XmlValue};
+
+mod accept;
+mod backend;
+mod body;
+mod borrowed;
+mod cdata;
+mod chars;
+mod compression;
+mod config;
+#[cfg(feature = "chrono")]
+mod datetime;
+mod de;
+mod encoding;
+mod entity;
+pub mod entries;
+#[cfg(feature = "form")]
+mod form;
+mod format;
+mod layer;
+mod lenient;
+mod list;
+#[cfg(feature = "json")]
+mod negotiate;
+#[cfg(feature = "utoipa")]
+mod openapi;
+mod optional;
+mod raw;
 mod rejection;
+mod response;
+mod scan;
+#[cfg(feature = "xsd")]
+mod schema;
+mod soap;
+#[cfg(feature = "streaming")]
+mod streaming;
+#[cfg(feature = "metrics")]
+mod telemetry;
+#[cfg(feature = "test-util")]
+pub mod test_util;
 #[cfg(test)]
 mod tests;
+mod text;
+#[cfg(feature = "validator")]
+mod validated;
+mod value;
+pub mod xsi_nil;
 
 /// XML Extractor / Response.
 ///
@@ -28,7 +95,7 @@ mod tests;
 /// - The request doesn't have a `Content-Type: application/xml` (or similar) header.
 /// - The body doesn't contain syntactically valid XML.
 /// - The body contains syntactically valid XML but it couldn't be deserialized into the target
-/// type.
+///   type.
 /// - Buffering the request body fails.
 ///
 /// Since parsing XML requires consuming the request body, the `Xml` extractor must be
@@ -87,7 +154,7 @@ mod tests;
 ///     username: String,
 /// }
 ///
-/// async fn get_user(Path(user_id) : Path<Uuid>) -> Json<User> {
+/// async fn get_user(Path(user_id) : Path<Uuid>) -> Xml<User> {
 ///     let user = find_user(user_id).await;
 ///     Xml(user)
 /// }
@@ -102,7 +169,90 @@ mod tests;
 /// # axum::Server::bind(&"".parse().unwrap()).serve(app.into_make_service()).await.unwrap();
 /// # };
 /// ```
-#[derive(Debug, Clone, Copy, Default)]
+///
+/// # Attributes
+///
+/// With the default quick-xml backend, string and number fields are written as attributes
+/// and structs as child elements, and both are read back the same way. Fields renamed with
+/// an `@` prefix, as in `#[serde(rename = "@id")]`, are written as attributes without the
+/// `@`; see [`XmlConfig::with_attribute_prefix`] for reading them.
+///
+/// Reserved attributes such as `xml:lang` fill fields renamed to their full name, as in
+/// `#[serde(rename = "xml:lang")]`, or `"@xml:lang"` with the prefix. serde-xml-rs matches
+/// attributes by local name only, so there the field is the one named `lang`.
+///
+/// # Empty and missing values
+///
+/// An element that is present but empty, `<nickname></nickname>` or `<nickname/>`, is read as
+/// the empty string, also into an `Option<String>`, which is `None` only if the element is
+/// missing. Responses keep the two apart the same way: `Some("")` and `""` are written as an
+/// empty element and `None` is left out. With quick-xml, an empty attribute such as
+/// `nickname=""` is read as `None`.
+///
+/// # Sequences
+///
+/// With the quick-xml backend, a `Vec` field of structs is written as one element per item,
+/// named after the field, and read back the same way, so rename the field to the item name:
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Order {
+///     #[serde(rename = "line")]
+///     lines: Vec<Line>,
+/// }
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Line {
+///     sku: String,
+/// }
+/// ```
+///
+/// Use an [`XmlList`] for sequences of strings or numbers, for a list that has a wrapper
+/// element, for a body that is a list as a whole, and for sequences in general with
+/// serde-xml-rs. Its items are named with [`XmlList::with_item_name`] rather than by renaming
+/// the field, which names the wrapper.
+///
+/// # Enums
+///
+/// Enums are externally tagged, serde's default: the element name selects the variant, both
+/// for the enum as a whole body and for a field renamed to `$value`. Unit variants are written
+/// as `<Circle/>`, and newtype variants holding a struct and struct variants as an element
+/// named after the variant with the fields inside, such as `<Square len="2"/>`.
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// enum Shape {
+///     Circle,
+///     Square(Side),
+///     Rect { w: u32, h: u32 },
+/// }
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Side {
+///     len: u32,
+/// }
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Drawing {
+///     #[serde(rename = "$value")]
+///     shape: Shape,
+/// }
+/// ```
+///
+/// Internally tagged, adjacently tagged and untagged enums are read through serde's
+/// buffering, which only sees text, so they fail for anything but string fields.
+///
+/// # Optional responses
+///
+/// `Xml(Some(user))` is written the same as `Xml(user)`. `Xml(None::<User>)` has no document
+/// to write, so the response is `204 No Content` without a body or `Content-Type`, which
+/// also goes for any other value that serializes to nothing. A status set with
+/// [`XmlResponse::status`] is kept, with the body left empty.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Xml<T>(pub T);
 
 #[async_trait]
@@ -117,45 +267,208 @@ where
     type Rejection = XmlRejection;
 
     async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let config = XmlConfig::from_extensions(req.extensions());
+        let request_id = config.request_id(req.headers());
 
-        if xml_content_type(req.headers()) {
-            let bytes = Bytes::from_request(req, state).await?;
-
-            let value = quick_xml::de::from_reader(&*bytes)?;
+        let extracted: Result<Self, XmlRejection> = async move {
+            if xml_content_type(req.headers(), &config) {
+                let document = Document::from_request(req, state, &config).await?;
 
-            Ok(Self(value))
-        } else {
-            Err(XmlRejection::MissingXMLContentType)
+                Ok(Self(document.deserialize(&config)?))
+            } else {
+                Err(config.missing_content_type(&req))
+            }
         }
-
+        .await;
+        extracted.map_err(|rejection| rejection.with_request_id(request_id))
     }
 }
 
-fn xml_content_type(headers: &HeaderMap) -> bool {
-    let content_type = if let Some(content_type) = headers.get(header::CONTENT_TYPE) {
-        content_type
-    } else {
+/// Whether the request is labelled as XML.
+///
+/// A request carrying several `Content-Type` headers is only taken as XML if every one of them
+/// is an XML type: a body that is XML for one reader and something else for another is
+/// ambiguous, so it is rejected rather than guessed at.
+pub(crate) fn xml_content_type(headers: &HeaderMap, config: &XmlConfig) -> bool {
+    let mut content_types = headers.get_all(header::CONTENT_TYPE).iter().peekable();
+    if content_types.peek().is_none() {
         return false;
-    };
+    }
 
-    let content_type = if let Ok(content_type) = content_type.to_str() {
-        content_type
-    } else {
-        return false;
-    };
+    content_types.all(|content_type| {
+        let content_type = if let Ok(content_type) = content_type.to_str() {
+            content_type
+        } else {
+            return false;
+        };
 
-    let mime = if let Ok(mime) = content_type.parse::<mime::Mime>() {
-        mime
-    } else {
-        return false;
-    };
+        let mime = if let Ok(mime) = content_type.parse::<mime::Mime>() {
+            mime
+        } else {
+            return false;
+        };
 
-    let is_xml_content_type =  (mime.type_() == "application" || mime.type_() == "text")
-        && (mime.subtype() == "xml" || mime.suffix().map_or(false, |name| name == "xml"));
+        config.is_xml(&mime)
+    })
+}
+
+/// Whether a `Content-Type` header of the request isn't a media type at all, such as `xml` or
+/// one with bytes that aren't visible ASCII.
+pub(crate) fn malformed_content_type(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::CONTENT_TYPE)
+        .iter()
+        .any(|content_type| {
+            content_type
+                .to_str()
+                .ok()
+                .and_then(|content_type| content_type.parse::<mime::Mime>().ok())
+                .is_none()
+        })
+}
+
+/// Whether `mime` is `application/xml`, `text/xml` or has a `+xml` suffix, as SOAP 1.2's
+/// `application/soap+xml` does.
+///
+/// [`mime::Mime`] lowercases the type, subtype and suffix while parsing, so the comparison is
+/// case-insensitive, and any parameters are ignored.
+pub(crate) fn is_xml_mime(mime: &mime::Mime) -> bool {
+    let is_xml_content_type = (mime.type_() == "application" || mime.type_() == "text")
+        && (mime.subtype() == "xml" || mime.suffix().is_some_and(|name| name == "xml"));
 
     is_xml_content_type
 }
 
+impl<T> Xml<T> {
+    /// Consume the `Xml` and return the wrapped value.
+    ///
+    /// ```rust
+    /// use serde::Deserialize;
+    /// use rustcms_axum_xml::Xml;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct CreateUser {
+    ///     email: String,
+    /// }
+    ///
+    /// async fn create_user(payload: Xml<CreateUser>) {
+    ///     let user: CreateUser = payload.into_inner();
+    /// }
+    /// ```
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+
+    /// Transform the wrapped value, for example to turn an extracted request into the
+    /// response to it.
+    ///
+    /// `Xml` carries no options of its own; [`XmlResponse::map`] keeps the options of an
+    /// [`XmlResponse`].
+    ///
+    /// ```rust
+    /// use serde::{Deserialize, Serialize};
+    /// use rustcms_axum_xml::Xml;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct CreateUser {
+    ///     email: String,
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// struct User {
+    ///     email: String,
+    ///     active: bool,
+    /// }
+    ///
+    /// async fn create_user(payload: Xml<CreateUser>) -> Xml<User> {
+    ///     payload.map(|user| User {
+    ///         email: user.email,
+    ///         active: true,
+    ///     })
+    /// }
+    /// ```
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Xml<U> {
+        Xml(f(self.0))
+    }
+}
+
+impl<T> Xml<T>
+where
+    T: DeserializeOwned,
+{
+    /// Deserialize `bytes` the way the extractor deserializes a request body, with the default
+    /// [`XmlConfig`].
+    ///
+    /// For XML that doesn't arrive as the body of its own, such as a form field:
+    ///
+    /// ```rust
+    /// use serde::Deserialize;
+    /// use rustcms_axum_xml::Xml;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Order {
+    ///     id: u64,
+    /// }
+    ///
+    /// let Xml(order) = Xml::<Order>::from_bytes(b"<Order><id>7</id></Order>").unwrap();
+    /// assert_eq!(order.id, 7);
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, XmlRejection> {
+        Self::from_bytes_with_config(bytes, &XmlConfig::default())
+    }
+
+    /// Deserialize `bytes` the way the extractor deserializes a request body, with the limits
+    /// and options of `config`.
+    ///
+    /// The encoding is detected from the XML declaration, as there is no `Content-Type` to
+    /// take a charset from.
+    pub fn from_bytes_with_config(bytes: &[u8], config: &XmlConfig) -> Result<Self, XmlRejection> {
+        if config.limit().is_some_and(|limit| bytes.len() > limit) {
+            return Err(XmlRejection::PayloadTooLarge);
+        }
+
+        de::deserialize_body(bytes, None, config).map(Self)
+    }
+}
+
+impl<T> Xml<T>
+where
+    T: Serialize,
+{
+    /// Serialize `value` exactly as it is in the body of an `Xml(value)` response.
+    pub fn to_bytes(value: &T) -> Result<Vec<u8>, backend::Error> {
+        response::XmlEncoder::default().encode(value)
+    }
+
+    /// Serialize the wrapped value exactly as it is in the body of the response, returning
+    /// the error instead of responding with `500 Internal Server Error`.
+    ///
+    /// For handlers that handle serialization failures themselves, such as by falling back to
+    /// another format:
+    ///
+    /// ```rust
+    /// use axum::response::{IntoResponse, Response};
+    /// use serde::Serialize;
+    /// use rustcms_axum_xml::Xml;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Report {
+    ///     total: u64,
+    /// }
+    ///
+    /// async fn report() -> Response {
+    ///     let report = Xml(Report { total: 3 });
+    ///     match report.try_into_bytes() {
+    ///         Ok(bytes) => ([("content-type", "application/xml")], bytes).into_response(),
+    ///         Err(_) => format!("total: {}", report.total).into_response(),
+    ///     }
+    /// }
+    /// ```
+    pub fn try_into_bytes(&self) -> Result<Vec<u8>, backend::Error> {
+        Self::to_bytes(&self.0)
+    }
+}
+
 impl<T> Deref for Xml<T> {
     type Target = T;
 
@@ -170,36 +483,58 @@ impl<T> DerefMut for Xml<T> {
     }
 }
 
+impl<T> AsRef<T> for Xml<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> AsMut<T> for Xml<T> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
 impl<T> From<T> for Xml<T> {
     fn from(inner: T) -> Self {
         Self(inner)
     }
 }
 
+/// Rewrap the value of a [`Json`](axum::Json), for services translating between the formats.
+///
+/// As `Xml::from` can also wrap the `Json` itself, name the target type:
+///
+/// ```rust
+/// use axum::Json;
+/// use rustcms_axum_xml::Xml;
+///
+/// let xml: Xml<u32> = Json(7).into();
+/// assert_eq!(xml.0, 7);
+///
+/// let json: Json<u32> = xml.into();
+/// assert_eq!(json.0, 7);
+/// ```
+#[cfg(feature = "json")]
+impl<T> From<axum::Json<T>> for Xml<T> {
+    fn from(axum::Json(inner): axum::Json<T>) -> Self {
+        Self(inner)
+    }
+}
+
+/// Rewrap the value of an [`Xml`] as a [`Json`](axum::Json).
+#[cfg(feature = "json")]
+impl<T> From<Xml<T>> for axum::Json<T> {
+    fn from(Xml(inner): Xml<T>) -> Self {
+        Self(inner)
+    }
+}
+
 impl<T> IntoResponse for Xml<T>
 where
     T: Serialize,
 {
     fn into_response(self) -> Response {
-        let mut bytes = Vec::new();
-        match quick_xml::se::to_writer(&mut bytes, &self.0) {
-            Ok(_) => (
-                [(
-                    header::CONTENT_TYPE,
-                    HeaderValue::from_static("application/xml"),
-                )],
-                bytes,
-            )
-                .into_response(),
-            Err(err) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                [(
-                    header::CONTENT_TYPE,
-                    HeaderValue::from_static(mime::TEXT_PLAIN_UTF_8.as_ref()),
-                )],
-                err.to_string(),
-            )
-                .into_response(),
-        }
+        XmlResponse::new(self.0).into_response()
     }
-}
\ No newline at end of file
+}
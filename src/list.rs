@@ -0,0 +1,217 @@
+//! Sequences wrapped in an element of their own.
+
+use axum_core::response::{IntoResponse, Response};
+use serde::{
+    de::{Deserialize, Deserializer},
+    ser::{Serialize, SerializeStruct, Serializer},
+};
+use std::{
+    borrow::Cow,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{format::is_primitive, response::XmlResponse};
+
+/// Element name of the items of an [`XmlList`] unless changed with
+/// [`XmlList::with_item_name`].
+const DEFAULT_ITEM_NAME: &str = "item";
+
+/// A sequence written as one element per item inside a wrapper element.
+///
+/// A bare `Vec<T>` doesn't map onto XML well: as a response it becomes one root element per
+/// item, which isn't a document. As a struct field, the quick-xml backend runs strings and
+/// numbers together into a single attribute, and serde-xml-rs fails on structs. `XmlList`
+/// always writes `<list><item>..</item><item>..</item></list>`, with the wrapper named after the
+/// struct field it is in, or set with
+/// [`XmlResponse::with_root_name`](crate::XmlResponse::with_root_name) at the top level.
+///
+/// Items are read back whatever their element names, and an empty wrapper is an empty list.
+///
+/// ```rust
+/// use rustcms_axum_xml::{Xml, XmlList};
+///
+/// let tags = XmlList::new(vec!["tea".to_owned(), "cake".to_owned()]).with_item_name("tag");
+/// let bytes = Xml::to_bytes(&tags).unwrap();
+/// assert_eq!(bytes, b"<list><tag>tea</tag><tag>cake</tag></list>");
+///
+/// let Xml(read) = Xml::<XmlList<String>>::from_bytes(&bytes).unwrap();
+/// assert_eq!(*read, ["tea", "cake"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XmlList<T> {
+    items: Vec<T>,
+    item_name: &'static str,
+}
+
+impl<T> XmlList<T> {
+    /// Wrap `items`, to be written as `<item>` elements.
+    pub fn new(items: Vec<T>) -> Self {
+        Self {
+            items,
+            item_name: DEFAULT_ITEM_NAME,
+        }
+    }
+
+    /// Write each item as an element called `name`.
+    pub fn with_item_name(mut self, name: &'static str) -> Self {
+        self.item_name = name;
+        self
+    }
+
+    /// Consume the `XmlList` and return the items.
+    pub fn into_inner(self) -> Vec<T> {
+        self.items
+    }
+}
+
+impl<T> Default for XmlList<T> {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl<T> From<Vec<T>> for XmlList<T> {
+    fn from(items: Vec<T>) -> Self {
+        Self::new(items)
+    }
+}
+
+impl<T> FromIterator<T> for XmlList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl<T> IntoIterator for XmlList<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<T> Deref for XmlList<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+impl<T> DerefMut for XmlList<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.items
+    }
+}
+
+impl<T> Serialize for XmlList<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // One field per item rather than a single sequence field, as serde-xml-rs closes the
+        // wrapper early when a sequence of structs is a field.
+        let mut list = serializer.serialize_struct("list", self.items.len())?;
+        for item in &self.items {
+            list.serialize_field(self.item_name, &Element(item))?;
+        }
+        list.end()
+    }
+}
+
+impl<'de, T> Deserialize<'de> for XmlList<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        #[serde(bound = "T: Deserialize<'de>")]
+        struct Items<T> {
+            #[serde(rename = "$value", default = "Vec::new")]
+            items: Vec<T>,
+        }
+
+        Items::deserialize(deserializer).map(|Items { items }| Self::new(items))
+    }
+}
+
+/// Response of a sequence as a document of its own, with the element names set per endpoint.
+///
+/// The same as responding with an [`XmlList`] renamed with
+/// [`XmlResponse::with_root_name`](crate::XmlResponse::with_root_name), without a wrapper
+/// struct or the names spread over two calls.
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use rustcms_axum_xml::XmlCollection;
+///
+/// async fn tags() -> XmlCollection<String> {
+///     // <tags><tag>tea</tag><tag>cake</tag></tags>
+///     XmlCollection::new("tags", "tag", vec!["tea".to_owned(), "cake".to_owned()])
+/// }
+///
+/// let app: Router = Router::new().route("/tags", get(tags));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XmlCollection<T> {
+    root: Cow<'static, str>,
+    item: &'static str,
+    items: Vec<T>,
+}
+
+impl<T> XmlCollection<T> {
+    /// Respond with `items` as `<root><item>..</item><item>..</item></root>`.
+    pub fn new(root: impl Into<Cow<'static, str>>, item: &'static str, items: Vec<T>) -> Self {
+        Self {
+            root: root.into(),
+            item,
+            items,
+        }
+    }
+
+    /// A response builder for the collection, to set further options such as
+    /// [`XmlResponse::with_declaration`](crate::XmlResponse::with_declaration).
+    pub fn builder(self) -> XmlResponse<XmlList<T>> {
+        XmlResponse::new(XmlList::new(self.items).with_item_name(self.item))
+            .with_root_name(self.root)
+    }
+}
+
+impl<T> IntoResponse for XmlCollection<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        self.builder().into_response()
+    }
+}
+
+/// A struct field value to be written as an element, such as an item of an [`XmlList`].
+///
+/// quick-xml writes string and number fields as attributes, so these are put in a newtype,
+/// which it writes as an element. Structs and other compound values are written as they are,
+/// as a newtype around them would nest each one in a second element.
+pub(crate) struct Element<'a, T>(pub(crate) &'a T);
+
+impl<T> Serialize for Element<'_, T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if is_primitive(self.0) {
+            serializer.serialize_newtype_struct("Element", self.0)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
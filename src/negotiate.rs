@@ -0,0 +1,85 @@
+use axum_core::response::{IntoResponse, Response};
+use http::{
+    header::{self, HeaderValue},
+    HeaderMap,
+};
+use serde::Serialize;
+
+use crate::{
+    accept::{quality, xml_quality},
+    response::serialize_error_response,
+    Xml, XmlResponse,
+};
+
+/// Response that is XML or JSON depending on the request's `Accept` header.
+///
+/// JSON is sent only when the client prefers `application/json` over every XML type, taking
+/// quality values into account. Everything else, including a missing `Accept` header and
+/// `*/*`, gets XML.
+///
+/// ```rust
+/// use axum::{http::HeaderMap, routing::get, Router};
+/// use serde::Serialize;
+/// use rustcms_axum_xml::{Negotiated, Xml};
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// async fn get_user(headers: HeaderMap) -> Negotiated<User> {
+///     Xml::or_json(User { name: "ferris".to_owned() }, &headers)
+/// }
+///
+/// let app: Router = Router::new().route("/user", get(get_user));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Negotiated<T> {
+    value: T,
+    json: bool,
+}
+
+impl<T> Negotiated<T> {
+    /// Pick the response format for `value` from the `Accept` header in `headers`.
+    pub fn new(value: T, headers: &HeaderMap) -> Self {
+        let xml = xml_quality(headers);
+        let json = quality(headers, &mime::APPLICATION_JSON);
+
+        Self {
+            value,
+            json: json > xml,
+        }
+    }
+}
+
+impl<T> Xml<T> {
+    /// Respond with `value` as XML, or as JSON when the client prefers it.
+    ///
+    /// See [`Negotiated`].
+    pub fn or_json(value: T, headers: &HeaderMap) -> Negotiated<T> {
+        Negotiated::new(value, headers)
+    }
+}
+
+impl<T> IntoResponse for Negotiated<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        if !self.json {
+            return XmlResponse::new(self.value).into_response();
+        }
+
+        match serde_json::to_vec(&self.value) {
+            Ok(bytes) => (
+                [(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
+                )],
+                bytes,
+            )
+                .into_response(),
+            Err(err) => serialize_error_response(&err, false),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! OpenAPI documentation for [`Xml`] through [utoipa](https://docs.rs/utoipa).
+
+use std::{borrow::Cow, collections::BTreeMap};
+use utoipa::{
+    openapi::{content::ContentBuilder, response::ResponseBuilder, RefOr, Response, Schema},
+    IntoResponses, PartialSchema, ToSchema,
+};
+
+use crate::Xml;
+
+impl<T> PartialSchema for Xml<T>
+where
+    T: PartialSchema,
+{
+    fn schema() -> RefOr<Schema> {
+        T::schema()
+    }
+}
+
+impl<T> ToSchema for Xml<T>
+where
+    T: ToSchema,
+{
+    fn name() -> Cow<'static, str> {
+        T::name()
+    }
+
+    fn schemas(schemas: &mut Vec<(String, RefOr<Schema>)>) {
+        T::schemas(schemas)
+    }
+}
+
+/// `Xml<T>` has the schema of `T`, and in `responses(...)` it lists itself as a `200` response
+/// with `application/xml` content of that schema. For request bodies, name `T` together with
+/// the XML content type:
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+/// use utoipa::{OpenApi, ToSchema};
+/// use rustcms_axum_xml::Xml;
+///
+/// #[derive(Deserialize, Serialize, ToSchema)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// #[utoipa::path(
+///     post,
+///     path = "/users",
+///     request_body(content = User, content_type = "application/xml"),
+///     responses(Xml<User>)
+/// )]
+/// async fn create_user(Xml(user): Xml<User>) -> Xml<User> {
+///     Xml(user)
+/// }
+///
+/// #[derive(OpenApi)]
+/// #[openapi(paths(create_user))]
+/// struct ApiDoc;
+/// ```
+impl<T> IntoResponses for Xml<T>
+where
+    T: PartialSchema,
+{
+    fn responses() -> BTreeMap<String, RefOr<Response>> {
+        let content = ContentBuilder::new().schema(Some(T::schema())).build();
+        let response = ResponseBuilder::new()
+            .description("XML document")
+            .content("application/xml", content)
+            .build();
+
+        BTreeMap::from([("200".to_owned(), response.into())])
+    }
+}
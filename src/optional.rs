@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use axum_core::{extract::FromRequest, BoxError};
+use http::{header, Request};
+use http_body::Body as HttpBody;
+use serde::de::DeserializeOwned;
+
+use crate::{config::XmlConfig, de::Document, rejection::XmlRejection, xml_content_type, Xml};
+
+/// XML Extractor for request bodies that may be left out.
+///
+/// Resolves to `OptionalXml(None)` when the request has no `Content-Type` header or a body that
+/// is empty or only whitespace, and to `OptionalXml(Some(Xml(value)))` when it carries an XML
+/// document. A body that is labelled as XML but fails to deserialize is still rejected, as is a
+/// non-XML `Content-Type`.
+///
+/// ```rust,no_run
+/// use axum::{routing::patch, Router};
+/// use serde::Deserialize;
+/// use rustcms_axum_xml::{OptionalXml, Xml};
+///
+/// #[derive(Deserialize)]
+/// struct UpdateUser {
+///     email: Option<String>,
+/// }
+///
+/// async fn update_user(OptionalXml(update): OptionalXml<UpdateUser>) {
+///     if let Some(Xml(update)) = update {
+///         // apply the changes
+///     }
+/// }
+///
+/// let app: Router = Router::new().route("/users/me", patch(update_user));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OptionalXml<T>(pub Option<Xml<T>>);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for OptionalXml<T>
+where
+    T: DeserializeOwned,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = XmlRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let config = XmlConfig::from_extensions(req.extensions());
+        let request_id = config.request_id(req.headers());
+
+        let extracted: Result<Self, XmlRejection> = async move {
+            let headers = req.headers();
+            if !headers.contains_key(header::CONTENT_TYPE) {
+                return Ok(Self(None));
+            }
+            if !xml_content_type(headers, &config) {
+                return Err(config.missing_content_type(&req));
+            }
+
+            let document = Document::from_request(req, state, &config).await?;
+            if document.is_empty() {
+                return Ok(Self(None));
+            }
+
+            Ok(Self(Some(Xml(document.deserialize(&config)?))))
+        }
+        .await;
+        extracted.map_err(|rejection| rejection.with_request_id(request_id))
+    }
+}
@@ -0,0 +1,175 @@
+use async_trait::async_trait;
+use axum_core::{
+    extract::FromRequest,
+    response::{IntoResponse, Response},
+    BoxError,
+};
+use bytes::Bytes;
+use http::{
+    header::{self, HeaderValue},
+    Request,
+};
+use http_body::Body as HttpBody;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    body::read_body, config::XmlConfig, de::Document, rejection::XmlRejection, xml_content_type,
+};
+
+/// Unparsed XML request body / response.
+///
+/// As an extractor it checks the `Content-Type` like [`Xml`](crate::Xml) does and hands over
+/// the body exactly as received, which is what's needed to verify a signature or forward the
+/// document. As a response it sends the bytes as they are with `Content-Type: application/xml`.
+///
+/// ```rust,no_run
+/// use axum::{routing::post, Router};
+/// use rustcms_axum_xml::RawXml;
+///
+/// async fn forward(RawXml(body): RawXml) -> RawXml {
+///     // check the signature over `body`, then
+///     RawXml(body)
+/// }
+///
+/// let app: Router = Router::new().route("/forward", post(forward));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RawXml(pub Bytes);
+
+#[async_trait]
+impl<S, B> FromRequest<S, B> for RawXml
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = XmlRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let config = XmlConfig::from_extensions(req.extensions());
+        let request_id = config.request_id(req.headers());
+
+        let extracted: Result<Self, XmlRejection> = async move {
+            if xml_content_type(req.headers(), &config) {
+                Ok(Self(read_body(req, state, &config).await?))
+            } else {
+                Err(config.missing_content_type(&req))
+            }
+        }
+        .await;
+        extracted.map_err(|rejection| rejection.with_request_id(request_id))
+    }
+}
+
+impl IntoResponse for RawXml {
+    fn into_response(self) -> Response {
+        (
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/xml"),
+            )],
+            self.0,
+        )
+            .into_response()
+    }
+}
+
+impl From<Bytes> for RawXml {
+    fn from(bytes: Bytes) -> Self {
+        Self(bytes)
+    }
+}
+
+/// Pre-rendered XML response.
+///
+/// Sends the string verbatim with `Content-Type: application/xml`, for documents that are
+/// already XML and don't need to go through serde. Use [`RawXml`] for documents held as bytes.
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use rustcms_axum_xml::XmlString;
+///
+/// async fn feed() -> XmlString {
+///     XmlString("<feed/>".to_owned())
+/// }
+///
+/// let app: Router = Router::new().route("/feed", get(feed));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XmlString(pub String);
+
+impl IntoResponse for XmlString {
+    fn into_response(self) -> Response {
+        RawXml(self.0.into()).into_response()
+    }
+}
+
+impl From<String> for XmlString {
+    fn from(string: String) -> Self {
+        Self(string)
+    }
+}
+
+/// XML Extractor for both the deserialized value and the body it came from.
+///
+/// For auditing or checking a signature alongside using the value, without buffering the body
+/// twice. The request goes through the same checks as with [`Xml`](crate::Xml), and `raw` holds
+/// the body exactly as received, like [`RawXml`]: still compressed if it was sent with a
+/// `Content-Encoding`.
+///
+/// ```rust,no_run
+/// use axum::{routing::post, Router};
+/// use serde::Deserialize;
+/// use rustcms_axum_xml::XmlWithRaw;
+///
+/// #[derive(Deserialize)]
+/// struct Payment {
+///     amount: u64,
+/// }
+///
+/// async fn pay(body: XmlWithRaw<Payment>) {
+///     // check the signature over `body.raw`, then use `body.value`
+/// }
+///
+/// let app: Router = Router::new().route("/pay", post(pay));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct XmlWithRaw<T> {
+    /// The deserialized body.
+    pub value: T,
+    /// The body as received.
+    pub raw: Bytes,
+}
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for XmlWithRaw<T>
+where
+    T: DeserializeOwned,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = XmlRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let config = XmlConfig::from_extensions(req.extensions());
+        let request_id = config.request_id(req.headers());
+
+        let extracted: Result<Self, XmlRejection> = async move {
+            if xml_content_type(req.headers(), &config) {
+                let (document, raw) = Document::with_raw_body(req, state, &config).await?;
+
+                Ok(Self {
+                    value: document.deserialize(&config)?,
+                    raw,
+                })
+            } else {
+                Err(config.missing_content_type(&req))
+            }
+        }
+        .await;
+        extracted.map_err(|rejection| rejection.with_request_id(request_id))
+    }
+}
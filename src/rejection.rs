@@ -1,36 +1,583 @@
 use axum_core::body;
 use axum_core::extract::rejection::BytesRejection;
 use axum_core::response::Response;
-use http::StatusCode;
+use axum_core::BoxError;
+use http::{
+    header::{self, HeaderValue},
+    StatusCode,
+};
 use http_body::Full;
+use quick_xml::{events::BytesText, writer::Writer};
+use std::fmt;
 use thiserror::Error;
 
-use crate::IntoResponse;
+use crate::{backend, IntoResponse};
 
+/// Rejection used by the extractors of this crate.
+///
+/// New variants are added as the extractors learn to tell more failures apart, so matches on it
+/// need a wildcard arm.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum XmlRejection {
-    #[error("Failed to parse the request body as XML")]
-    InvalidXMLBody(#[from] quick_xml::DeError),
+    /// The body failed to deserialize.
+    ///
+    /// The status is `400 Bad Request` if the body isn't well-formed XML, such as a truncated
+    /// document, and `422 Unprocessable Entity` if it is XML that doesn't fit the type, such as
+    /// one missing a required field. Comments forbidden with
+    /// [`XmlConfig::forbid_comments`](crate::XmlConfig::forbid_comments) are rejected as not
+    /// well-formed.
+    ///
+    /// Match it as `InvalidXMLBody { source, .. }`, or read the fields with
+    /// [`XmlRejection::as_parse_error`] and [`XmlRejection::position`]. It is created from the
+    /// backend's error with `From`, without a position.
+    #[error("Failed to parse the request body as XML{}", DisplayPosition(.position))]
+    #[non_exhaustive]
+    InvalidXMLBody {
+        source: backend::Error,
+        position: Option<ErrorPosition>,
+    },
+    /// The `Content-Type` is missing or not XML.
+    ///
+    /// `status` is `415 Unsupported Media Type` unless changed with
+    /// [`XmlConfig::with_content_type_status`](crate::XmlConfig::with_content_type_status).
     #[error("Expected request with `Content-Type: application/xml`")]
-    MissingXMLContentType,
+    MissingXMLContentType { status: StatusCode },
+    /// The `Content-Type` header is not a media type at all, as opposed to a type other than
+    /// XML.
+    #[error("The `Content-Type` header is not a valid media type")]
+    InvalidContentType,
+    /// The body is empty or only whitespace.
+    ///
+    /// GET and HEAD requests without a body are rejected with this too, even when they have no
+    /// `Content-Type`.
+    #[error("The request body is empty")]
+    EmptyBody,
+    #[error("The DOCTYPE of the XML document is not accepted")]
+    ForbiddenDoctype,
+    #[error("The XML document is nested too deeply")]
+    DepthLimitExceeded,
+    #[error("The entities in the XML document expand too far")]
+    EntityExpansionLimit,
+    #[error("The XML document has too many elements")]
+    TooManyElements,
+    #[error("An element of the XML document has too many attributes")]
+    TooManyAttributes,
+    /// An attribute value of the document is longer than
+    /// [`XmlConfig::with_max_attribute_len`](crate::XmlConfig::with_max_attribute_len) allows.
+    #[error("An attribute value of the XML document is too large")]
+    AttributeTooLarge,
+    /// The document has more than one root element, so it isn't well-formed; `400 Bad Request`.
+    #[error("The XML document has more than one root element")]
+    MultipleRoots,
+    /// An end tag doesn't close the element open at that point, as in `<a><b></a>`, or
+    /// closes one that was never opened. `expected` is empty in the second case. Such a document
+    /// isn't well-formed, so the status is `400 Bad Request`.
+    #[error(
+        "End tag `</{found}>` does not match {} at {position}",
+        DisplayExpected(.expected)
+    )]
+    MalformedStructure {
+        expected: String,
+        found: String,
+        position: ErrorPosition,
+    },
+    /// The XML declaration names a version other than `1.0` and `1.1`, so the document isn't
+    /// well-formed; `400 Bad Request`.
+    #[error("XML version `{version}` is not supported")]
+    UnsupportedXmlVersion { version: String },
+    /// The root element of the document is not the one
+    /// [`XmlConfig::with_expected_root`](crate::XmlConfig::with_expected_root) asks for.
+    #[error("Expected the root element `{expected}`, found `{found}`")]
+    UnexpectedRoot { expected: String, found: String },
+    /// The document has an element or attribute `name` that the extracted type has no field
+    /// for, when unknown elements are denied with
+    /// [`XmlConfig::with_deny_unknown_elements`](crate::XmlConfig::with_deny_unknown_elements).
+    #[error("Unknown element `{name}`")]
+    UnknownElement { name: String },
+    /// The body of a [`SoapBody`](crate::SoapBody) extractor is not a SOAP envelope with a
+    /// payload in its `Body`.
+    #[error("Expected a SOAP envelope with a payload: {0}")]
+    NotSoapEnvelope(&'static str),
+    /// The SOAP envelope of a [`SoapBody`](crate::SoapBody) extractor carries a fault instead of
+    /// a request.
+    #[error("The SOAP envelope carries a fault")]
+    SoapFault,
+    #[error("The request body is too large")]
+    PayloadTooLarge,
+    #[error("The character encoding of the request body is not supported")]
+    UnsupportedEncoding,
+    /// The body is taken as UTF-8, as it declares no other encoding, but has a byte sequence
+    /// that is not valid UTF-8 at `position`.
+    #[error("The request body is not valid UTF-8 at {position}")]
+    InvalidUtf8 { position: ErrorPosition },
+    #[error("The `Content-Encoding` of the request body is not supported")]
+    UnsupportedContentEncoding,
+    #[error("Failed to decompress the request body")]
+    InvalidCompressedBody,
+    #[error("The request body was already taken by another extractor")]
+    BodyAlreadyExtracted,
+    #[error("Failed to read the request body")]
+    FailedToReadBody(#[source] BoxError),
+    #[cfg(feature = "validator")]
+    #[error("The request body is invalid: {0}")]
+    Validation(#[from] validator::ValidationErrors),
+    /// The body doesn't conform to the schema of a
+    /// [`SchemaValidatedXml`](crate::SchemaValidatedXml) extractor.
+    #[cfg(feature = "xsd")]
+    #[error("The request body does not match the schema: {0}")]
+    SchemaValidation(String),
+    /// A [`SchemaValidatedXml`](crate::SchemaValidatedXml) extractor was used without an
+    /// [`XmlSchema`](crate::XmlSchema) extension.
+    #[cfg(feature = "xsd")]
+    #[error("No XML schema is configured for this route")]
+    MissingSchema,
+    /// An [`XmlOrForm`](crate::XmlOrForm) extractor got a request that is neither XML nor
+    /// form-encoded.
+    ///
+    /// `status` is the one [`XmlRejection::MissingXMLContentType`] would have.
+    #[cfg(feature = "form")]
+    #[error(
+        "Expected request with `Content-Type: application/xml` or \
+         `application/x-www-form-urlencoded`"
+    )]
+    NotXmlOrForm { status: StatusCode },
+    /// The form-encoded body of an [`XmlOrForm`](crate::XmlOrForm) extractor failed to
+    /// deserialize.
+    #[cfg(feature = "form")]
+    #[error("Failed to parse the request body as a form: {0}")]
+    InvalidFormBody(#[source] serde_urlencoded::de::Error),
     #[error("{0}")]
     BytesRejection(#[from] BytesRejection),
+    /// Another rejection, of a request carrying the id
+    /// [`XmlConfig::with_request_id_header`](crate::XmlConfig::with_request_id_header) names.
+    ///
+    /// It responds like `rejection`, with the id added to the body so clients can quote it.
+    #[error("{rejection}")]
+    WithRequestId {
+        request_id: String,
+        #[source]
+        rejection: Box<XmlRejection>,
+    },
+}
+
+impl XmlRejection {
+    pub(crate) fn invalid_body(source: backend::Error, bytes: &[u8], offset: usize) -> Self {
+        XmlRejection::InvalidXMLBody {
+            source,
+            position: Some(ErrorPosition::new(bytes, offset)),
+        }
+    }
+
+    pub(crate) fn mismatched_tag(
+        expected: String,
+        found: String,
+        bytes: &[u8],
+        offset: usize,
+    ) -> Self {
+        XmlRejection::MalformedStructure {
+            expected,
+            found,
+            position: ErrorPosition::new(bytes, offset),
+        }
+    }
+
+    pub(crate) fn invalid_utf8(bytes: &[u8], offset: usize) -> Self {
+        XmlRejection::InvalidUtf8 {
+            position: ErrorPosition::new(bytes, offset),
+        }
+    }
+
+    /// Drop the position of a parse failure that was found in a rewritten copy of the body,
+    /// where it wouldn't point at what the client sent.
+    pub(crate) fn without_position(self) -> Self {
+        match self {
+            XmlRejection::InvalidXMLBody { source, .. } => XmlRejection::InvalidXMLBody {
+                source,
+                position: None,
+            },
+            rejection => rejection,
+        }
+    }
+
+    /// Move the position of a failure found in the last bytes of `bytes` so that it counts
+    /// from the start of `bytes` instead, `skipped` bytes earlier.
+    pub(crate) fn skipped(self, bytes: &[u8], skipped: usize) -> Self {
+        if skipped == 0 {
+            return self;
+        }
+        match self {
+            XmlRejection::InvalidXMLBody {
+                source,
+                position: Some(position),
+            } => XmlRejection::InvalidXMLBody {
+                source,
+                position: Some(ErrorPosition::new(bytes, position.offset + skipped)),
+            },
+            XmlRejection::MalformedStructure {
+                expected,
+                found,
+                position,
+            } => XmlRejection::MalformedStructure {
+                expected,
+                found,
+                position: ErrorPosition::new(bytes, position.offset + skipped),
+            },
+            rejection => rejection,
+        }
+    }
+
+    /// Byte offset into the body where parsing failed, if known.
+    pub fn position(&self) -> Option<usize> {
+        self.error_position().map(|position| position.offset)
+    }
+
+    /// One-based line and column where parsing failed, if known.
+    pub fn line_column(&self) -> Option<(usize, usize)> {
+        self.error_position()
+            .map(|position| (position.line, position.column))
+    }
+
+    /// Error the backend failed to parse the body with, if that is why the request was
+    /// rejected.
+    ///
+    /// This is [`quick_xml::DeError`] with the default `backend-quick-xml` feature and
+    /// `serde_xml_rs::Error` with `backend-serde-xml-rs`. The same error is also returned by
+    /// [`std::error::Error::source`].
+    pub fn as_parse_error(&self) -> Option<&backend::Error> {
+        match self {
+            XmlRejection::InvalidXMLBody { source, .. } => Some(source),
+            XmlRejection::WithRequestId { rejection, .. } => rejection.as_parse_error(),
+            _ => None,
+        }
+    }
+
+    /// Id of the rejected request, if it had one in the header
+    /// [`XmlConfig::with_request_id_header`](crate::XmlConfig::with_request_id_header) names.
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            XmlRejection::WithRequestId { request_id, .. } => Some(request_id),
+            _ => None,
+        }
+    }
+
+    /// The rejection without its request id, to match on the reason.
+    pub fn without_request_id(self) -> Self {
+        match self {
+            XmlRejection::WithRequestId { rejection, .. } => *rejection,
+            rejection => rejection,
+        }
+    }
+
+    pub(crate) fn with_request_id(self, request_id: Option<String>) -> Self {
+        match request_id {
+            Some(request_id) if self.request_id().is_none() => XmlRejection::WithRequestId {
+                request_id,
+                rejection: Box::new(self),
+            },
+            _ => self,
+        }
+    }
+
+    fn error_position(&self) -> Option<&ErrorPosition> {
+        match self {
+            XmlRejection::InvalidXMLBody { position, .. } => position.as_ref(),
+            XmlRejection::MalformedStructure { position, .. } => Some(position),
+            XmlRejection::InvalidUtf8 { position } => Some(position),
+            XmlRejection::WithRequestId { rejection, .. } => rejection.error_position(),
+            _ => None,
+        }
+    }
+}
+
+impl From<backend::Error> for XmlRejection {
+    fn from(source: backend::Error) -> Self {
+        XmlRejection::InvalidXMLBody {
+            source,
+            position: None,
+        }
+    }
+}
+
+/// Location in the request body where parsing stopped.
+///
+/// The parser reads the body one markup token at a time, so this points at or shortly after
+/// the token that couldn't be handled.
+///
+/// Failures the deserializer reports only come with a position when it read the body as it
+/// was sent. Bodies that were transcoded from another charset or rewritten first, to remove
+/// comments or expand entities for instance, are reported without one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorPosition {
+    /// Byte offset from the start of the body.
+    pub offset: usize,
+    /// One-based line number.
+    pub line: usize,
+    /// One-based column, counted in bytes.
+    pub column: usize,
+}
+
+impl ErrorPosition {
+    fn new(bytes: &[u8], offset: usize) -> Self {
+        let before = &bytes[..offset.min(bytes.len())];
+        let line_start = before
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+
+        Self {
+            offset,
+            line: before.iter().filter(|&&byte| byte == b'\n').count() + 1,
+            column: before.len() - line_start + 1,
+        }
+    }
+}
+
+impl fmt::Display for ErrorPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {} (byte {})",
+            self.line, self.column, self.offset
+        )
+    }
+}
+
+struct DisplayPosition<'a>(&'a Option<ErrorPosition>);
+
+impl fmt::Display for DisplayPosition<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(position) => write!(f, " at {}", position),
+            None => Ok(()),
+        }
+    }
+}
+
+struct DisplayExpected<'a>(&'a str);
+
+impl fmt::Display for DisplayExpected<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            f.write_str("any open element")
+        } else {
+            write!(f, "the open element `<{}>`", self.0)
+        }
+    }
+}
+
+impl XmlRejection {
+    /// HTTP status code of the response for this rejection.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            XmlRejection::InvalidXMLBody { source, .. } if backend::is_syntax_error(source) => {
+                StatusCode::BAD_REQUEST
+            }
+            XmlRejection::InvalidXMLBody { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            XmlRejection::MissingXMLContentType { status } => *status,
+            XmlRejection::InvalidContentType => StatusCode::BAD_REQUEST,
+            XmlRejection::EmptyBody => StatusCode::BAD_REQUEST,
+            XmlRejection::ForbiddenDoctype => StatusCode::BAD_REQUEST,
+            XmlRejection::DepthLimitExceeded => StatusCode::BAD_REQUEST,
+            XmlRejection::EntityExpansionLimit => StatusCode::BAD_REQUEST,
+            XmlRejection::TooManyElements => StatusCode::BAD_REQUEST,
+            XmlRejection::TooManyAttributes => StatusCode::BAD_REQUEST,
+            XmlRejection::AttributeTooLarge => StatusCode::BAD_REQUEST,
+            XmlRejection::MultipleRoots => StatusCode::BAD_REQUEST,
+            XmlRejection::MalformedStructure { .. } => StatusCode::BAD_REQUEST,
+            XmlRejection::UnsupportedXmlVersion { .. } => StatusCode::BAD_REQUEST,
+            XmlRejection::UnexpectedRoot { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            XmlRejection::UnknownElement { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            XmlRejection::NotSoapEnvelope(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            XmlRejection::SoapFault => StatusCode::UNPROCESSABLE_ENTITY,
+            XmlRejection::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            XmlRejection::UnsupportedEncoding => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            XmlRejection::InvalidUtf8 { .. } => StatusCode::BAD_REQUEST,
+            XmlRejection::UnsupportedContentEncoding => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            XmlRejection::InvalidCompressedBody => StatusCode::BAD_REQUEST,
+            XmlRejection::BodyAlreadyExtracted => StatusCode::INTERNAL_SERVER_ERROR,
+            XmlRejection::FailedToReadBody(_) => StatusCode::BAD_REQUEST,
+            #[cfg(feature = "validator")]
+            XmlRejection::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            #[cfg(feature = "xsd")]
+            XmlRejection::SchemaValidation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            #[cfg(feature = "xsd")]
+            XmlRejection::MissingSchema => StatusCode::INTERNAL_SERVER_ERROR,
+            #[cfg(feature = "form")]
+            XmlRejection::NotXmlOrForm { status } => *status,
+            #[cfg(feature = "form")]
+            XmlRejection::InvalidFormBody(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            XmlRejection::BytesRejection(e) => e.status(),
+            XmlRejection::WithRequestId { rejection, .. } => rejection.status(),
+        }
+    }
+
+    /// Stable identifier of the rejection, such as `xml.parse.invalid`.
+    ///
+    /// Unlike the message, the code never changes between releases, so logs and clients can
+    /// match on it.
+    pub fn code(&self) -> &'static str {
+        match self {
+            XmlRejection::InvalidXMLBody { .. } => "xml.parse.invalid",
+            XmlRejection::MissingXMLContentType { .. } => "xml.content_type.missing",
+            XmlRejection::InvalidContentType => "xml.content_type.invalid",
+            XmlRejection::EmptyBody => "xml.body.empty",
+            XmlRejection::ForbiddenDoctype => "xml.doctype.forbidden",
+            XmlRejection::DepthLimitExceeded => "xml.limit.depth",
+            XmlRejection::EntityExpansionLimit => "xml.limit.entity_expansion",
+            XmlRejection::TooManyElements => "xml.limit.elements",
+            XmlRejection::TooManyAttributes => "xml.limit.attributes",
+            XmlRejection::AttributeTooLarge => "xml.limit.attribute_size",
+            XmlRejection::MultipleRoots => "xml.parse.multiple_roots",
+            XmlRejection::MalformedStructure { .. } => "xml.parse.mismatched_tag",
+            XmlRejection::UnsupportedXmlVersion { .. } => "xml.version.unsupported",
+            XmlRejection::UnexpectedRoot { .. } => "xml.root.unexpected",
+            XmlRejection::UnknownElement { .. } => "xml.element.unknown",
+            XmlRejection::NotSoapEnvelope(_) => "xml.soap.not_envelope",
+            XmlRejection::SoapFault => "xml.soap.fault",
+            XmlRejection::PayloadTooLarge => "xml.body.too_large",
+            XmlRejection::UnsupportedEncoding => "xml.encoding.unsupported",
+            XmlRejection::InvalidUtf8 { .. } => "xml.encoding.invalid_utf8",
+            XmlRejection::UnsupportedContentEncoding => "xml.content_encoding.unsupported",
+            XmlRejection::InvalidCompressedBody => "xml.content_encoding.invalid",
+            XmlRejection::BodyAlreadyExtracted => "xml.body.already_extracted",
+            XmlRejection::FailedToReadBody(_) => "xml.body.read_failed",
+            #[cfg(feature = "validator")]
+            XmlRejection::Validation(_) => "xml.validation.failed",
+            #[cfg(feature = "xsd")]
+            XmlRejection::SchemaValidation(_) => "xml.schema.invalid",
+            #[cfg(feature = "xsd")]
+            XmlRejection::MissingSchema => "xml.schema.missing",
+            #[cfg(feature = "form")]
+            XmlRejection::NotXmlOrForm { .. } => "xml.content_type.not_xml_or_form",
+            #[cfg(feature = "form")]
+            XmlRejection::InvalidFormBody(_) => "xml.form.invalid",
+            XmlRejection::BytesRejection(_) => "xml.body.rejected",
+            XmlRejection::WithRequestId { rejection, .. } => rejection.code(),
+        }
+    }
+
+    /// Name of the variant, for logs and metrics.
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    pub(crate) fn variant_name(&self) -> &'static str {
+        match self {
+            XmlRejection::InvalidXMLBody { .. } => "InvalidXMLBody",
+            XmlRejection::MissingXMLContentType { .. } => "MissingXMLContentType",
+            XmlRejection::InvalidContentType => "InvalidContentType",
+            XmlRejection::EmptyBody => "EmptyBody",
+            XmlRejection::ForbiddenDoctype => "ForbiddenDoctype",
+            XmlRejection::DepthLimitExceeded => "DepthLimitExceeded",
+            XmlRejection::EntityExpansionLimit => "EntityExpansionLimit",
+            XmlRejection::TooManyElements => "TooManyElements",
+            XmlRejection::TooManyAttributes => "TooManyAttributes",
+            XmlRejection::AttributeTooLarge => "AttributeTooLarge",
+            XmlRejection::MultipleRoots => "MultipleRoots",
+            XmlRejection::MalformedStructure { .. } => "MalformedStructure",
+            XmlRejection::UnsupportedXmlVersion { .. } => "UnsupportedXmlVersion",
+            XmlRejection::UnexpectedRoot { .. } => "UnexpectedRoot",
+            XmlRejection::UnknownElement { .. } => "UnknownElement",
+            XmlRejection::NotSoapEnvelope(_) => "NotSoapEnvelope",
+            XmlRejection::SoapFault => "SoapFault",
+            XmlRejection::PayloadTooLarge => "PayloadTooLarge",
+            XmlRejection::UnsupportedEncoding => "UnsupportedEncoding",
+            XmlRejection::InvalidUtf8 { .. } => "InvalidUtf8",
+            XmlRejection::UnsupportedContentEncoding => "UnsupportedContentEncoding",
+            XmlRejection::InvalidCompressedBody => "InvalidCompressedBody",
+            XmlRejection::BodyAlreadyExtracted => "BodyAlreadyExtracted",
+            XmlRejection::FailedToReadBody(_) => "FailedToReadBody",
+            #[cfg(feature = "validator")]
+            XmlRejection::Validation(_) => "Validation",
+            #[cfg(feature = "xsd")]
+            XmlRejection::SchemaValidation(_) => "SchemaValidation",
+            #[cfg(feature = "xsd")]
+            XmlRejection::MissingSchema => "MissingSchema",
+            #[cfg(feature = "form")]
+            XmlRejection::NotXmlOrForm { .. } => "NotXmlOrForm",
+            #[cfg(feature = "form")]
+            XmlRejection::InvalidFormBody(_) => "InvalidFormBody",
+            XmlRejection::BytesRejection(_) => "BytesRejection",
+            XmlRejection::WithRequestId { rejection, .. } => rejection.variant_name(),
+        }
+    }
+
+    /// Human readable message sent as the response body.
+    pub fn body_text(&self) -> String {
+        match self {
+            XmlRejection::BytesRejection(e) => e.body_text(),
+            XmlRejection::WithRequestId {
+                request_id,
+                rejection,
+            } => format!("{} (request id: {})", rejection.body_text(), request_id),
+            e => e.to_string(),
+        }
+    }
 }
 
 impl IntoResponse for XmlRejection {
     fn into_response(self) -> crate::Response {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            code = self.code(),
+            rejection = self.variant_name(),
+            position = self.position(),
+            "rejected request: {}",
+            self
+        );
+        #[cfg(feature = "metrics")]
+        crate::telemetry::record_rejection(&self);
+
+        if cfg!(feature = "xml-errors") {
+            return match &self {
+                XmlRejection::WithRequestId {
+                    request_id,
+                    rejection,
+                } => xml_error_response(self.status(), &rejection.body_text(), Some(request_id)),
+                rejection => xml_error_response(self.status(), &rejection.body_text(), None),
+            };
+        }
+
         match self {
-            e @ XmlRejection::InvalidXMLBody(_) => {
-                let mut res = Response::new(body::boxed(Full::from(format!("{}", e))));
-                *res.status_mut() = StatusCode::UNPROCESSABLE_ENTITY;
-                res
-            }
-            e @ XmlRejection::MissingXMLContentType => {
-                let mut res = Response::new(body::boxed(Full::from(format!("{}", e))));
-                *res.status_mut() = StatusCode::UNSUPPORTED_MEDIA_TYPE;
+            XmlRejection::BytesRejection(e) => e.into_response(),
+            e => {
+                let mut res = Response::new(body::boxed(Full::from(e.body_text())));
+                *res.status_mut() = e.status();
                 res
             }
-            XmlRejection::BytesRejection(e) => e.into_response(),
         }
     }
 }
+
+/// Render an error as `<error><code>415</code><message>...</message></error>`, followed by a
+/// `<requestId>` if there is one.
+pub(crate) fn xml_error_response(
+    status: StatusCode,
+    message: &str,
+    request_id: Option<&str>,
+) -> Response {
+    let mut writer = Writer::new(Vec::new());
+    writer
+        .create_element("error")
+        .write_inner_content(|writer| {
+            writer
+                .create_element("code")
+                .write_text_content(BytesText::new(status.as_str()))?;
+            writer
+                .create_element("message")
+                .write_text_content(BytesText::new(message))?;
+            if let Some(request_id) = request_id {
+                writer
+                    .create_element("requestId")
+                    .write_text_content(BytesText::new(request_id))?;
+            }
+            Ok(())
+        })
+        .expect("writing to a `Vec` never fails");
+
+    let mut res = Response::new(body::boxed(Full::from(writer.into_inner())));
+    *res.status_mut() = status;
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/xml"),
+    );
+    res
+}
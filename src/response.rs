@@ -0,0 +1,982 @@
+use axum_core::response::{IntoResponse, IntoResponseParts, Response, ResponseParts};
+use http::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    StatusCode,
+};
+use quick_xml::{
+    events::{BytesDecl, BytesStart, Event},
+    writer::Writer,
+    Reader,
+};
+use serde::Serialize;
+use std::{borrow::Cow, collections::BTreeMap, convert::Infallible, fmt};
+
+use crate::{
+    backend::{self, Error},
+    cdata,
+    chars::{self, InvalidCharPolicy},
+    encoding::Encoding,
+    entity,
+    format::{BoolFormat, Formatted},
+    rejection::xml_error_response,
+    text, xsi_nil, Xml,
+};
+
+/// XML response with serialization options.
+///
+/// Created by the configuring constructors on [`Xml`], such as [`Xml::pretty`]. It serializes the
+/// inner value the same way [`Xml`] does and sets the same `Content-Type: application/xml` header,
+/// but lets the output be tuned. Options can be chained:
+///
+/// ```rust
+/// # use rustcms_axum_xml::Xml;
+/// # #[derive(serde::Serialize)]
+/// # struct User {}
+/// let response = Xml::with_root_name(User {}, "user").pretty();
+/// ```
+///
+/// [`Xml::builder`] starts from the defaults, and the status and extra headers can be set too:
+///
+/// ```rust
+/// # use rustcms_axum_xml::Xml;
+/// # #[derive(serde::Serialize)]
+/// # struct User {}
+/// use http::StatusCode;
+///
+/// let response = Xml::builder(User {})
+///     .status(StatusCode::CREATED)
+///     .header("location", "/users/1")
+///     .pretty();
+/// ```
+///
+/// A complete handler:
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use serde::Serialize;
+/// use rustcms_axum_xml::{Xml, XmlResponse};
+///
+/// #[derive(Serialize)]
+/// struct Status {
+///     healthy: bool,
+/// }
+///
+/// async fn status() -> XmlResponse<Status> {
+///     Xml::pretty(Status { healthy: true })
+/// }
+///
+/// let app: Router = Router::new().route("/status", get(status));
+/// ```
+#[derive(Debug, Clone)]
+pub struct XmlResponse<T> {
+    value: T,
+    encoder: XmlEncoder,
+    status: StatusCode,
+    headers: HeaderMap,
+    invalid_header: Option<String>,
+    verbose_errors: bool,
+}
+
+impl<T> XmlResponse<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Self::with_encoder(value, XmlEncoder::default())
+    }
+
+    pub(crate) fn with_encoder(value: T, encoder: XmlEncoder) -> Self {
+        Self {
+            value,
+            encoder,
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            invalid_header: None,
+            verbose_errors: false,
+        }
+    }
+
+    /// Transform the value to be serialized, keeping the status, headers and serialization
+    /// options set so far.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> XmlResponse<U> {
+        XmlResponse {
+            value: f(self.value),
+            encoder: self.encoder,
+            status: self.status,
+            headers: self.headers,
+            invalid_header: self.invalid_header,
+            verbose_errors: self.verbose_errors,
+        }
+    }
+
+    /// Respond with `status` instead of `200 OK`.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Add a header to the response.
+    ///
+    /// Headers are added after the `Content-Type`, so a `content-type` header set here takes
+    /// its place. `Content-Length` is always the length of the serialized body, whatever is set
+    /// here. `key` and `value` must be a valid header name and value, otherwise the response is
+    /// a 500 error.
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        if self.invalid_header.is_some() {
+            return self;
+        }
+
+        let header = HeaderName::try_from(key)
+            .map_err(Into::into)
+            .and_then(|key| Ok((key, HeaderValue::try_from(value).map_err(Into::into)?)));
+        match header {
+            Ok((key, value)) => {
+                self.headers.append(key, value);
+            }
+            Err(err) => self.invalid_header = Some(err.to_string()),
+        }
+        self
+    }
+
+    /// Indent the output with one tab per nesting level.
+    pub fn pretty(mut self) -> Self {
+        self.encoder = self.encoder.pretty();
+        self
+    }
+
+    /// Indent the output with `width` copies of `indent_char` per nesting level, as in
+    /// `with_indent(' ', 2)`.
+    ///
+    /// `indent_char` must be an ASCII character and `width` must not be zero, otherwise the
+    /// response is a 500 error.
+    pub fn with_indent(mut self, indent_char: char, width: usize) -> Self {
+        self.encoder = self.encoder.with_indent(indent_char, width);
+        self
+    }
+
+    /// Use `name` for the root element instead of the name of the serialized type.
+    pub fn with_root_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.encoder = self.encoder.with_root_name(name);
+        self
+    }
+
+    /// Start the document with `<?xml version="1.0" encoding="UTF-8"?>`.
+    pub fn with_declaration(mut self) -> Self {
+        self.encoder = self.encoder.with_declaration();
+        self
+    }
+
+    /// Add `standalone="yes"` or `standalone="no"` to the XML declaration, which is added if it
+    /// isn't already.
+    ///
+    /// See [`XmlEncoder::with_standalone`].
+    pub fn with_standalone(mut self, standalone: impl Into<Cow<'static, str>>) -> Self {
+        self.encoder = self.encoder.with_standalone(standalone);
+        self
+    }
+
+    /// Add a `charset` parameter to the `Content-Type`, as in `application/xml; charset=utf-8`.
+    ///
+    /// This replaces the `charset` that [`XmlResponse::with_output_encoding`] sets.
+    ///
+    /// `charset` must be a valid MIME token, otherwise the response is a 500 error.
+    pub fn with_charset(mut self, charset: impl Into<Cow<'static, str>>) -> Self {
+        self.encoder = self.encoder.with_charset(charset);
+        self
+    }
+
+    /// Send `content_type`, such as `text/xml`, instead of `application/xml`.
+    ///
+    /// `content_type` must be a valid MIME type, otherwise the response is a 500 error.
+    pub fn with_content_type(mut self, content_type: impl Into<Cow<'static, str>>) -> Self {
+        self.encoder = self.encoder.with_content_type(content_type);
+        self
+    }
+
+    /// Declare `uri` as the default namespace, with an `xmlns` attribute on the root element.
+    ///
+    /// The attribute goes on whatever the root element ends up being called, including a name
+    /// set with [`XmlResponse::with_root_name`].
+    pub fn with_namespace(mut self, uri: impl Into<Cow<'static, str>>) -> Self {
+        self.encoder = self.encoder.with_namespace(uri);
+        self
+    }
+
+    /// Declare `uri` under `prefix`, with an `xmlns:prefix` attribute on the root element.
+    ///
+    /// Combine it with a prefixed root name to put the root itself in the namespace:
+    ///
+    /// ```rust
+    /// # use rustcms_axum_xml::Xml;
+    /// # #[derive(serde::Serialize)]
+    /// # struct Body {}
+    /// let response = Xml::with_root_name(Body {}, "soap:Envelope")
+    ///     .with_prefixed_namespace("soap", "http://www.w3.org/2003/05/soap-envelope");
+    /// ```
+    pub fn with_prefixed_namespace(
+        mut self,
+        prefix: impl Into<Cow<'static, str>>,
+        uri: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.encoder = self.encoder.with_prefixed_namespace(prefix, uri);
+        self
+    }
+
+    /// Write elements without content as `<field></field>` when `expand` is true and as
+    /// `<field/>` when it is false.
+    ///
+    /// Without this, empty elements are written the way the serializer produces them.
+    pub fn with_expand_empty_elements(mut self, expand: bool) -> Self {
+        self.encoder = self.encoder.with_expand_empty_elements(expand);
+        self
+    }
+
+    /// Write the body in `encoding` instead of UTF-8.
+    ///
+    /// UTF-16 bodies start with a byte order mark and are labelled `charset=utf-16` in the
+    /// `Content-Type`, and a declaration added with [`XmlResponse::with_declaration`] names
+    /// UTF-16.
+    pub fn with_output_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoder = self.encoder.with_output_encoding(encoding);
+        self
+    }
+
+    /// Write `bool` values in `format`, such as `1` and `0` for [`BoolFormat::Numeric`].
+    pub fn with_bool_format(mut self, format: BoolFormat) -> Self {
+        self.encoder = self.encoder.with_bool_format(format);
+        self
+    }
+
+    /// Handle characters XML doesn't allow, such as `\u{0}`, according to `policy`.
+    ///
+    /// By default they make serialization fail. See [`InvalidCharPolicy`].
+    pub fn with_invalid_char_policy(mut self, policy: InvalidCharPolicy) -> Self {
+        self.encoder = self.encoder.with_invalid_char_policy(policy);
+        self
+    }
+
+    /// Write non-ASCII characters as numeric character references, such as `&#233;` for `é`,
+    /// for consumers that only read ASCII.
+    ///
+    /// See [`XmlEncoder::with_ascii_only`].
+    pub fn with_ascii_only(mut self, ascii_only: bool) -> Self {
+        self.encoder = self.encoder.with_ascii_only(ascii_only);
+        self
+    }
+
+    /// Write the replacement text of the named entities in `entities` as references to them.
+    ///
+    /// See [`XmlEncoder::with_entities`].
+    pub fn with_entities<I, K, V>(mut self, entities: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.encoder = self.encoder.with_entities(entities);
+        self
+    }
+
+    /// Send the reason in the body of the 500 response when serializing fails, instead of a
+    /// generic message.
+    ///
+    /// The reason names fields and types of the application, so it is kept from clients by
+    /// default and only logged, with the `tracing` feature. Turn this on during development.
+    pub fn with_verbose_errors(mut self, verbose: bool) -> Self {
+        self.verbose_errors = verbose;
+        self
+    }
+
+    /// Reserve `capacity` bytes for the body up front.
+    ///
+    /// The output buffer otherwise starts empty and is reallocated as it grows, which adds up
+    /// for large documents. The hint only affects performance, never the output.
+    pub fn with_capacity_hint(mut self, capacity: usize) -> Self {
+        self.encoder = self.encoder.with_capacity_hint(capacity);
+        self
+    }
+}
+
+impl<T> Xml<T> {
+    /// Start building a response for `value`, with the same defaults as `Xml(value)`.
+    ///
+    /// See [`XmlResponse`] for the options.
+    pub fn builder(value: T) -> XmlResponse<T> {
+        XmlResponse::new(value)
+    }
+
+    /// Respond with `value` serialized as indented XML.
+    ///
+    /// The default `Xml(value)` response stays compact.
+    pub fn pretty(value: T) -> XmlResponse<T> {
+        XmlResponse::new(value).pretty()
+    }
+
+    /// Respond with `value` serialized as XML indented by `width` copies of `indent_char`.
+    pub fn with_indent(value: T, indent_char: char, width: usize) -> XmlResponse<T> {
+        XmlResponse::new(value).with_indent(indent_char, width)
+    }
+
+    /// Respond with `value` serialized under a root element called `name`.
+    ///
+    /// Without this the root element is named after the type, so `struct User` becomes
+    /// `<User>...</User>`.
+    pub fn with_root_name(value: T, name: impl Into<Cow<'static, str>>) -> XmlResponse<T> {
+        XmlResponse::new(value).with_root_name(name)
+    }
+
+    /// Respond with `value` serialized after an XML declaration.
+    ///
+    /// The declaration names the encoding the body is written in, UTF-8 unless changed with
+    /// [`XmlResponse::with_output_encoding`].
+    pub fn with_declaration(value: T) -> XmlResponse<T> {
+        XmlResponse::new(value).with_declaration()
+    }
+
+    /// Respond with `value` serialized as XML, naming `charset` in the `Content-Type`.
+    pub fn with_charset(value: T, charset: impl Into<Cow<'static, str>>) -> XmlResponse<T> {
+        XmlResponse::new(value).with_charset(charset)
+    }
+
+    /// Respond with `value` serialized as XML under a `Content-Type` other than
+    /// `application/xml`.
+    pub fn with_content_type(
+        value: T,
+        content_type: impl Into<Cow<'static, str>>,
+    ) -> XmlResponse<T> {
+        XmlResponse::new(value).with_content_type(content_type)
+    }
+
+    /// Respond with `value` serialized as XML in the default namespace `uri`.
+    pub fn with_namespace(value: T, uri: impl Into<Cow<'static, str>>) -> XmlResponse<T> {
+        XmlResponse::new(value).with_namespace(uri)
+    }
+
+    /// Respond with `value` serialized as XML, with empty elements expanded to
+    /// `<field></field>` or collapsed to `<field/>` depending on `expand`.
+    pub fn with_expand_empty_elements(value: T, expand: bool) -> XmlResponse<T> {
+        XmlResponse::new(value).with_expand_empty_elements(expand)
+    }
+
+    /// Respond with `value` serialized as XML into a buffer of `capacity` bytes, for large
+    /// documents whose size can be estimated.
+    pub fn with_capacity_hint(value: T, capacity: usize) -> XmlResponse<T> {
+        XmlResponse::new(value).with_capacity_hint(capacity)
+    }
+
+    /// Respond with `value` serialized as XML in `encoding`.
+    pub fn with_output_encoding(value: T, encoding: Encoding) -> XmlResponse<T> {
+        XmlResponse::new(value).with_output_encoding(encoding)
+    }
+
+    /// Respond with `value` serialized as XML, with its `bool` values in `format`.
+    pub fn with_bool_format(value: T, format: BoolFormat) -> XmlResponse<T> {
+        XmlResponse::new(value).with_bool_format(format)
+    }
+
+    /// Respond with `value` serialized as XML, or with the reason serializing it failed.
+    pub fn with_verbose_errors(value: T, verbose: bool) -> XmlResponse<T> {
+        XmlResponse::new(value).with_verbose_errors(verbose)
+    }
+}
+
+impl<T> IntoResponse for XmlResponse<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        let encoded = match self.invalid_header {
+            Some(err) => Err(backend::custom(err)),
+            None => self
+                .encoder
+                .content_type()
+                .and_then(|content_type| Ok((content_type, self.encoder.encode(&self.value)?))),
+        };
+
+        match encoded {
+            Ok((_, bytes)) if bytes.is_empty() => {
+                #[cfg(feature = "metrics")]
+                crate::telemetry::record_response(0);
+
+                let mut res = Response::default();
+                *res.status_mut() = match self.status {
+                    StatusCode::OK => StatusCode::NO_CONTENT,
+                    status => status,
+                };
+                res.headers_mut().extend(self.headers);
+                res
+            }
+            Ok((content_type, bytes)) => {
+                #[cfg(feature = "metrics")]
+                crate::telemetry::record_response(bytes.len());
+
+                let content_length = HeaderValue::from(bytes.len());
+                let mut res = ([(header::CONTENT_TYPE, content_type)], bytes).into_response();
+                *res.status_mut() = self.status;
+                res.headers_mut().extend(self.headers);
+                res.headers_mut()
+                    .insert(header::CONTENT_LENGTH, content_length);
+                res
+            }
+            Err(err) => serialize_error_response(&err, self.verbose_errors),
+        }
+    }
+}
+
+/// 500 response for a value that failed to serialize, with `err` as the message only if
+/// `verbose`.
+///
+/// The message is sent as plain text, or in an `<error>` document with the `xml-errors`
+/// feature, for clients that can't take anything but XML.
+pub(crate) fn serialize_error_response(err: &dyn fmt::Display, verbose: bool) -> Response {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(error = %err, "failed to serialize XML response");
+    #[cfg(feature = "metrics")]
+    crate::telemetry::record_serialize_error();
+
+    let body = if verbose {
+        err.to_string()
+    } else {
+        "failed to serialize response".to_owned()
+    };
+    if cfg!(feature = "xml-errors") {
+        return xml_error_response(StatusCode::INTERNAL_SERVER_ERROR, &body, None);
+    }
+
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(mime::TEXT_PLAIN_UTF_8.as_ref()),
+        )],
+        body,
+    )
+        .into_response()
+}
+
+/// Response part setting `Content-Type: application/xml`.
+///
+/// For tagging a body built some other way as XML, without serializing anything. Use
+/// [`XmlBody`] to serialize a value into the body of such a response.
+///
+/// ```rust
+/// use axum::{http::StatusCode, response::IntoResponse, routing::get, Router};
+/// use rustcms_axum_xml::XmlContentType;
+///
+/// async fn cached_feed() -> impl IntoResponse {
+///     (StatusCode::OK, XmlContentType, "<feed/>")
+/// }
+///
+/// let app: Router = Router::new().route("/feed", get(cached_feed));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct XmlContentType;
+
+impl IntoResponseParts for XmlContentType {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/xml"),
+        );
+        Ok(res)
+    }
+}
+
+/// Body of a response built from parts, serialized from `T` and sent as `application/xml`.
+///
+/// Response parts such as a status or headers can't write a body, so `XmlBody` goes last in a
+/// tuple, after the parts, and only sets the `Content-Type` and the body. Unlike [`Xml`], it
+/// leaves the status to the rest of the tuple, also when the value serializes to nothing.
+/// Values that fail to serialize give the same `500 Internal Server Error` response as with
+/// [`Xml`], although a status in the tuple takes precedence over it.
+///
+/// ```rust
+/// use axum::{http::StatusCode, response::IntoResponse, routing::post, Router};
+/// use serde::Serialize;
+/// use rustcms_axum_xml::XmlBody;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// async fn create_user() -> impl IntoResponse {
+///     (
+///         StatusCode::CREATED,
+///         [("location", "/users/1")],
+///         XmlBody(User { name: "Ann".to_owned() }),
+///     )
+/// }
+///
+/// let app: Router = Router::new().route("/users", post(create_user));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct XmlBody<T>(pub T);
+
+impl<T> IntoResponse for XmlBody<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        let encoder = XmlEncoder::new();
+        let encoded = encoder
+            .content_type()
+            .and_then(|content_type| Ok((content_type, encoder.encode(&self.0)?)));
+
+        match encoded {
+            Ok((content_type, bytes)) => {
+                #[cfg(feature = "metrics")]
+                crate::telemetry::record_response(bytes.len());
+
+                ([(header::CONTENT_TYPE, content_type)], bytes).into_response()
+            }
+            Err(err) => serialize_error_response(&err, false),
+        }
+    }
+}
+
+/// Serialization options, set once and used for any number of values.
+///
+/// [`XmlResponse`] builds one of these for every response. Services that send many responses
+/// with the same options can keep a configured encoder around instead, and serialize with
+/// [`XmlEncoder::encode`] or respond with [`XmlEncoder::response`]:
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use serde::Serialize;
+/// use std::sync::OnceLock;
+/// use rustcms_axum_xml::{XmlEncoder, XmlResponse};
+///
+/// #[derive(Serialize)]
+/// struct Status {
+///     healthy: bool,
+/// }
+///
+/// fn encoder() -> &'static XmlEncoder {
+///     static ENCODER: OnceLock<XmlEncoder> = OnceLock::new();
+///     ENCODER.get_or_init(|| {
+///         XmlEncoder::new()
+///             .pretty()
+///             .with_declaration()
+///             .with_namespace("urn:example:status")
+///     })
+/// }
+///
+/// async fn status() -> XmlResponse<Status> {
+///     encoder().response(Status { healthy: true })
+/// }
+///
+/// let app: Router = Router::new().route("/status", get(status));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct XmlEncoder {
+    indent: Option<(char, usize)>,
+    root_name: Option<Cow<'static, str>>,
+    declaration: bool,
+    standalone: Option<Cow<'static, str>>,
+    charset: Option<Cow<'static, str>>,
+    content_type: Option<Cow<'static, str>>,
+    capacity: usize,
+    expand_empty_elements: Option<bool>,
+    bool_format: BoolFormat,
+    invalid_chars: InvalidCharPolicy,
+    ascii_only: bool,
+    entities: BTreeMap<String, String>,
+    output_encoding: Encoding,
+    namespaces: Vec<(Option<Cow<'static, str>>, Cow<'static, str>)>,
+}
+
+impl XmlEncoder {
+    /// Create an encoder with the same defaults as `Xml(value)`: compact UTF-8 without a
+    /// declaration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indent the output with one tab per nesting level.
+    pub fn pretty(mut self) -> Self {
+        self.indent = Some(('\t', 1));
+        self
+    }
+
+    /// Indent the output with `width` copies of `indent_char` per nesting level, as in
+    /// `with_indent(' ', 2)`.
+    ///
+    /// `indent_char` must be an ASCII character and `width` must not be zero, otherwise
+    /// encoding fails.
+    pub fn with_indent(mut self, indent_char: char, width: usize) -> Self {
+        self.indent = Some((indent_char, width));
+        self
+    }
+
+    /// Use `name` for the root element instead of the name of the serialized type.
+    pub fn with_root_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.root_name = Some(name.into());
+        self
+    }
+
+    /// Start the document with `<?xml version="1.0" encoding="UTF-8"?>`.
+    pub fn with_declaration(mut self) -> Self {
+        self.declaration = true;
+        self
+    }
+
+    /// Add a `standalone` attribute to the XML declaration, as in
+    /// `<?xml version="1.0" encoding="UTF-8" standalone="yes"?>`, and add the declaration if it
+    /// isn't already.
+    ///
+    /// `standalone` must be `yes` or `no`, otherwise encoding fails.
+    pub fn with_standalone(mut self, standalone: impl Into<Cow<'static, str>>) -> Self {
+        self.declaration = true;
+        self.standalone = Some(standalone.into());
+        self
+    }
+
+    /// Add a `charset` parameter to the `Content-Type`, as in `application/xml; charset=utf-8`.
+    ///
+    /// This replaces the `charset` that [`XmlEncoder::with_output_encoding`] sets.
+    ///
+    /// `charset` must be a valid MIME token, otherwise responses are 500 errors.
+    pub fn with_charset(mut self, charset: impl Into<Cow<'static, str>>) -> Self {
+        self.charset = Some(charset.into());
+        self
+    }
+
+    /// Send `content_type`, such as `text/xml`, instead of `application/xml`.
+    ///
+    /// `content_type` must be a valid MIME type, otherwise responses are 500 errors.
+    pub fn with_content_type(mut self, content_type: impl Into<Cow<'static, str>>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Declare `uri` as the default namespace, with an `xmlns` attribute on the root element.
+    ///
+    /// The attribute goes on whatever the root element ends up being called, including a name
+    /// set with [`XmlEncoder::with_root_name`].
+    pub fn with_namespace(mut self, uri: impl Into<Cow<'static, str>>) -> Self {
+        self.namespaces.push((None, uri.into()));
+        self
+    }
+
+    /// Declare `uri` under `prefix`, with an `xmlns:prefix` attribute on the root element.
+    pub fn with_prefixed_namespace(
+        mut self,
+        prefix: impl Into<Cow<'static, str>>,
+        uri: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.namespaces.push((Some(prefix.into()), uri.into()));
+        self
+    }
+
+    /// Write elements without content as `<field></field>` when `expand` is true and as
+    /// `<field/>` when it is false.
+    ///
+    /// Without this, empty elements are written the way the serializer produces them.
+    pub fn with_expand_empty_elements(mut self, expand: bool) -> Self {
+        self.expand_empty_elements = Some(expand);
+        self
+    }
+
+    /// Write the body in `encoding` instead of UTF-8.
+    ///
+    /// UTF-16 bodies start with a byte order mark and are labelled `charset=utf-16` in the
+    /// `Content-Type`, and a declaration added with [`XmlEncoder::with_declaration`] names
+    /// UTF-16.
+    pub fn with_output_encoding(mut self, encoding: Encoding) -> Self {
+        self.output_encoding = encoding;
+        self
+    }
+
+    /// Write `bool` values in `format`, such as `1` and `0` for [`BoolFormat::Numeric`].
+    pub fn with_bool_format(mut self, format: BoolFormat) -> Self {
+        self.bool_format = format;
+        self
+    }
+
+    /// Handle characters XML doesn't allow, such as `\u{0}`, according to `policy`.
+    ///
+    /// By default they make encoding fail. See [`InvalidCharPolicy`].
+    pub fn with_invalid_char_policy(mut self, policy: InvalidCharPolicy) -> Self {
+        self.invalid_chars = policy;
+        self
+    }
+
+    /// Write non-ASCII characters in text and attribute values as numeric character
+    /// references, such as `&#233;` for `é`. Off by default.
+    ///
+    /// Element and attribute names can't contain references, so non-ASCII names are still
+    /// written as they are.
+    pub fn with_ascii_only(mut self, ascii_only: bool) -> Self {
+        self.ascii_only = ascii_only;
+        self
+    }
+
+    /// Write the replacement text of the named entities in `entities` as references to them,
+    /// such as `"©"` as `&copy;` for a map of `"copy"` to `"©"`, in text and attribute values.
+    /// Calls add to the entities registered so far.
+    ///
+    /// Only for consumers that know the entities, as XML parsers reject references to
+    /// entities the document doesn't declare; see [`XmlConfig::with_entities`] for reading
+    /// them. Where replacement texts overlap, the longer one is written as a reference.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a name isn't a valid XML name or is one of the five predefined entities.
+    ///
+    /// [`XmlConfig::with_entities`]: crate::XmlConfig::with_entities
+    pub fn with_entities<I, K, V>(mut self, entities: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        for (name, replacement) in entities {
+            let name = name.into();
+            entity::check_name(&name);
+            self.entities.insert(name, replacement.into());
+        }
+        self
+    }
+
+    /// Reserve `capacity` bytes for the body up front.
+    ///
+    /// The output buffer otherwise starts empty and is reallocated as it grows, which adds up
+    /// for large documents. The hint only affects performance, never the output.
+    pub fn with_capacity_hint(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Serialize `value` with these options.
+    ///
+    /// A value that serializes to nothing, such as `None`, gives an empty body, without the XML
+    /// declaration.
+    pub fn encode<T>(&self, value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let body = self.encode_utf8(value)?;
+        if body.is_empty() {
+            return Ok(body);
+        }
+        let mut body = self.invalid_chars.apply(body)?;
+        if !self.entities.is_empty() {
+            body = entity::write_references(body, &self.entities);
+        }
+        if self.ascii_only {
+            body = chars::escape_non_ascii(body);
+        }
+        Ok(self.output_encoding.encode(body))
+    }
+
+    /// Respond with `value` serialized with these options.
+    ///
+    /// The status, headers and other response settings can still be changed on the returned
+    /// [`XmlResponse`].
+    pub fn response<T>(&self, value: T) -> XmlResponse<T> {
+        XmlResponse::with_encoder(value, self.clone())
+    }
+
+    pub(crate) fn content_type(&self) -> Result<HeaderValue, Error> {
+        let mut content_type = match &self.content_type {
+            Some(content_type) => {
+                if content_type.parse::<mime::Mime>().is_err() {
+                    return Err(backend::custom(format_args!(
+                        "invalid content type `{}`",
+                        content_type
+                    )));
+                }
+                content_type.to_string()
+            }
+            None if self.charset().is_none() => {
+                return Ok(HeaderValue::from_static("application/xml"))
+            }
+            None => "application/xml".to_owned(),
+        };
+
+        if let Some(charset) = self.charset() {
+            if charset.is_empty() || !charset.bytes().all(is_token_byte) {
+                return Err(backend::custom(format_args!(
+                    "invalid charset `{}`",
+                    charset
+                )));
+            }
+            content_type = format!("{}; charset={}", content_type, charset);
+        }
+
+        HeaderValue::from_str(&content_type).map_err(backend::custom)
+    }
+
+    fn charset(&self) -> Option<&str> {
+        self.charset
+            .as_deref()
+            .or_else(|| self.output_encoding.charset())
+    }
+
+    fn encode_utf8<T>(&self, value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let root_name = self.root_name.as_deref();
+        let mut body = backend::to_vec(
+            &Formatted::new(value, self.bool_format),
+            root_name,
+            self.capacity,
+        )?;
+        body = xsi_nil::declare_xsi(body)?;
+        if body.is_empty() {
+            // A `None` or unit value, which has no document to declare.
+            return Ok(body);
+        }
+
+        let rewrite = self.indent.is_some()
+            || self.expand_empty_elements.is_some()
+            || !self.namespaces.is_empty()
+            || cdata::contains_marker(&body)
+            || has_prefixed_attribute(&body);
+        if !rewrite && !self.declaration {
+            return Ok(body);
+        }
+
+        let buffer = Vec::with_capacity(body.len() + DECLARATION_LEN);
+        let mut writer = match self.indent {
+            Some((indent_char, width)) => {
+                if !indent_char.is_ascii() {
+                    return Err(backend::custom(format_args!(
+                        "invalid indent character {:?}",
+                        indent_char
+                    )));
+                }
+                if width == 0 {
+                    return Err(backend::custom("indent width must not be zero"));
+                }
+                Writer::new_with_indent(buffer, indent_char as u8, width)
+            }
+            None => Writer::new(buffer),
+        };
+
+        if self.declaration {
+            let standalone = match self.standalone.as_deref() {
+                None => None,
+                Some(standalone @ ("yes" | "no")) => Some(standalone),
+                Some(standalone) => {
+                    return Err(backend::custom(format_args!(
+                        "invalid standalone value {:?}, expected \"yes\" or \"no\"",
+                        standalone
+                    )))
+                }
+            };
+            writer
+                .write_event(Event::Decl(BytesDecl::new(
+                    "1.0",
+                    Some(self.output_encoding.declared_name()),
+                    standalone,
+                )))
+                .map_err(backend::writer_error)?;
+        }
+
+        if rewrite {
+            self.rewrite(&body, &mut writer)?;
+        } else {
+            writer.inner().extend_from_slice(&body);
+        }
+
+        Ok(writer.into_inner())
+    }
+
+    /// Re-emit compact XML through `writer`, declaring the namespaces on the root, expanding
+    /// or collapsing empty elements, writing [`Cdata`](crate::Cdata) values as CDATA and
+    /// dropping the `@` from attribute names on the way.
+    ///
+    /// The quick-xml serializer writes nested structs through intermediate buffers and loses the
+    /// indentation state on the way, so the document is re-indented from its events instead.
+    fn rewrite(&self, xml: &[u8], writer: &mut Writer<Vec<u8>>) -> Result<(), Error> {
+        let mut reader = Reader::from_reader(xml);
+        // A start tag held back until it's known whether the element is empty.
+        let mut pending: Option<BytesStart<'_>> = None;
+        let mut root = true;
+
+        loop {
+            let mut event = reader.read_event().map_err(backend::writer_error)?;
+            if let Event::Start(start) | Event::Empty(start) = &mut event {
+                if has_prefixed_attribute(start.attributes_raw()) {
+                    if let Some(renamed) = text::rename_attributes(start, |key| {
+                        key.strip_prefix(b"@").map(<[u8]>::to_vec)
+                    }) {
+                        *start = renamed;
+                    }
+                }
+                if root {
+                    self.declare_namespaces(start);
+                    root = false;
+                }
+            }
+            let event = match (pending.take(), event) {
+                (Some(start), Event::End(_)) => Event::Empty(start),
+                (Some(start), event) => {
+                    writer
+                        .write_event(Event::Start(start))
+                        .map_err(backend::writer_error)?;
+                    event
+                }
+                (None, event) => event,
+            };
+
+            match event {
+                Event::Eof => return Ok(()),
+                Event::Start(start) if self.expand_empty_elements == Some(false) => {
+                    pending = Some(start);
+                }
+                Event::Empty(start) if self.expand_empty_elements == Some(true) => {
+                    let end = start.to_end().into_owned();
+                    writer
+                        .write_event(Event::Start(start))
+                        .map_err(backend::writer_error)?;
+                    writer
+                        .write_event(Event::End(end))
+                        .map_err(backend::writer_error)?;
+                }
+                Event::Text(text) => cdata::write_text(writer, text)?,
+                event => writer.write_event(event).map_err(backend::writer_error)?,
+            }
+        }
+    }
+
+    /// Declare the namespaces on `root`, except for those it declares already.
+    fn declare_namespaces(&self, root: &mut BytesStart<'_>) {
+        for (prefix, uri) in &self.namespaces {
+            let key = match prefix {
+                Some(prefix) => Cow::Owned(format!("xmlns:{}", prefix)),
+                None => Cow::Borrowed("xmlns"),
+            };
+            let declared = root
+                .attributes()
+                .with_checks(false)
+                .flatten()
+                .any(|attribute| attribute.key.as_ref() == key.as_bytes());
+            if !declared {
+                root.push_attribute((key.as_ref(), uri.as_ref()));
+            }
+        }
+    }
+}
+
+/// Whether the serializer may have written an attribute from a field renamed with an `@`
+/// prefix, which isn't a valid attribute name.
+fn has_prefixed_attribute(xml: &[u8]) -> bool {
+    xml.windows(2).any(|window| window == b" @")
+}
+
+/// Whether `byte` may appear in a MIME token, such as a parameter value.
+fn is_token_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+/// Length of `<?xml version="1.0" encoding="UTF-8"?>`.
+const DECLARATION_LEN: usize = 38;
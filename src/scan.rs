@@ -0,0 +1,433 @@
+use quick_xml::{
+    events::{BytesDecl, BytesStart, Event},
+    Reader,
+};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{backend, config::XmlConfig, rejection::XmlRejection};
+
+/// What [`check`] found in a document besides the checks passing.
+#[derive(Debug, Default)]
+pub(crate) struct Scanned {
+    /// Whether the document has comments or processing instructions.
+    pub(crate) has_comments: bool,
+    /// Whether an element asks for its whitespace to be kept with `xml:space="preserve"`.
+    pub(crate) has_xml_space: bool,
+    /// The internal general entities declared in the DOCTYPE, with the references in their
+    /// replacement text to other declared entities resolved.
+    pub(crate) entities: BTreeMap<String, String>,
+}
+
+/// Check a request body against the limits in `config` before deserializing it.
+///
+/// Only the markup is inspected here. Malformed documents are left for the deserializer to
+/// reject, so that the error reported is the same with or without these checks, except for
+/// end tags that don't match the open element, which are reported as
+/// [`XmlRejection::MalformedStructure`] with both tag names and the position of the end tag's
+/// name, and text after the root element, which the deserializer would silently drop.
+pub(crate) fn check(bytes: &[u8], config: &XmlConfig) -> Result<Scanned, XmlRejection> {
+    let mut reader = Reader::from_reader(bytes);
+    let mut depth = 0;
+    let mut elements = 0;
+    let mut entities = Entities::default();
+    let mut scanned = Scanned::default();
+    let mut root_closed = false;
+
+    loop {
+        let offset = reader.buffer_position();
+        match reader.read_event() {
+            Ok(Event::Decl(decl)) => check_version(&decl)?,
+            Ok(Event::DocType(doctype)) => {
+                check_doctype(&doctype, config)?;
+                entities = Entities::declared_in(&doctype, config)?;
+            }
+            Ok(Event::Start(start)) => {
+                check_single_root(depth, root_closed)?;
+                depth += 1;
+                check_depth(depth, config)?;
+                elements += 1;
+                check_element(&start, elements, config)?;
+                if elements == 1 {
+                    check_root(&start, config)?;
+                }
+                entities.expand(&start, config)?;
+                scanned.has_xml_space = scanned.has_xml_space || preserves_space(&start);
+            }
+            Ok(Event::Empty(start)) => {
+                check_single_root(depth, root_closed)?;
+                root_closed = root_closed || depth == 0;
+                check_depth(depth + 1, config)?;
+                elements += 1;
+                check_element(&start, elements, config)?;
+                if elements == 1 {
+                    check_root(&start, config)?;
+                }
+                entities.expand(&start, config)?;
+            }
+            Ok(Event::Text(text)) => {
+                if root_closed && !is_blank(&text) {
+                    return Err(trailing_text(bytes, offset));
+                }
+                entities.expand(&text, config)?;
+            }
+            Ok(Event::CData(_)) if root_closed => {
+                return Err(trailing_text(bytes, offset));
+            }
+            Ok(Event::End(_)) => {
+                depth = depth.saturating_sub(1);
+                root_closed = root_closed || depth == 0;
+            }
+            Ok(Event::Comment(_) | Event::PI(_)) => {
+                if config.comments_forbidden() {
+                    return Err(XmlRejection::invalid_body(
+                        backend::syntax_error(
+                            "comments and processing instructions are not accepted",
+                        ),
+                        bytes,
+                        reader.buffer_position(),
+                    ));
+                }
+                scanned.has_comments = true;
+            }
+            Err(quick_xml::Error::EndEventMismatch { expected, found }) => {
+                return Err(XmlRejection::mismatched_tag(
+                    expected,
+                    found,
+                    bytes,
+                    reader.buffer_position(),
+                ))
+            }
+            Ok(Event::Eof) | Err(_) => {
+                scanned.entities = entities.into_resolved();
+                return Ok(scanned);
+            }
+            Ok(_) => {}
+        }
+    }
+}
+
+/// Reject an element starting at `depth` 0 once the root element has been closed.
+///
+/// Whitespace around the root is fine, but the deserializer would stop after the first root
+/// and silently drop the rest.
+fn check_single_root(depth: usize, root_closed: bool) -> Result<(), XmlRejection> {
+    if depth == 0 && root_closed {
+        return Err(XmlRejection::MultipleRoots);
+    }
+    Ok(())
+}
+
+/// The rejection for text other than whitespace after the root element, at `offset`.
+fn trailing_text(bytes: &[u8], offset: usize) -> XmlRejection {
+    XmlRejection::invalid_body(
+        backend::syntax_error("text after the root element"),
+        bytes,
+        offset,
+    )
+}
+
+fn is_blank(text: &[u8]) -> bool {
+    text.iter().all(u8::is_ascii_whitespace)
+}
+
+/// Reject a DOCTYPE, `doctype`, that `config` doesn't accept: any DOCTYPE under
+/// [`XmlConfig::forbid_doctype`], and ones referring to external entities unless
+/// [`XmlConfig::allow_doctype`] is set.
+pub(crate) fn check_doctype(doctype: &[u8], config: &XmlConfig) -> Result<(), XmlRejection> {
+    if config.doctype_forbidden() {
+        return Err(XmlRejection::ForbiddenDoctype);
+    }
+    if !config.doctype_allowed() && declares_external_entity(doctype) {
+        return Err(XmlRejection::ForbiddenDoctype);
+    }
+    Ok(())
+}
+
+/// Whether `start` is marked `xml:space="preserve"`.
+fn preserves_space(start: &BytesStart<'_>) -> bool {
+    matches!(
+        start.try_get_attribute("xml:space"),
+        Ok(Some(attribute)) if attribute.value.as_ref() == b"preserve"
+    )
+}
+
+/// Reject declarations of XML versions other than 1.0 and 1.1.
+///
+/// Declarations without a readable version are left for the deserializer to reject.
+fn check_version(decl: &BytesDecl<'_>) -> Result<(), XmlRejection> {
+    match decl.version() {
+        Ok(version) if !matches!(version.as_ref(), b"1.0" | b"1.1") => {
+            Err(XmlRejection::UnsupportedXmlVersion {
+                version: String::from_utf8_lossy(&version).into_owned(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn check_depth(depth: usize, config: &XmlConfig) -> Result<(), XmlRejection> {
+    if depth > config.max_depth() {
+        return Err(XmlRejection::DepthLimitExceeded);
+    }
+    Ok(())
+}
+
+/// Check the `count`th element of a document, `start`, against the element and attribute
+/// caps.
+///
+/// Attributes that can't be read are left for the deserializer to reject.
+fn check_element(
+    start: &BytesStart<'_>,
+    count: usize,
+    config: &XmlConfig,
+) -> Result<(), XmlRejection> {
+    if count > config.max_elements() {
+        return Err(XmlRejection::TooManyElements);
+    }
+    let mut attributes = 0;
+    for attribute in start.attributes().with_checks(false) {
+        attributes += 1;
+        if attributes > config.max_attributes() {
+            return Err(XmlRejection::TooManyAttributes);
+        }
+        if attribute.is_ok_and(|attribute| attribute.value.len() > config.max_attribute_len()) {
+            return Err(XmlRejection::AttributeTooLarge);
+        }
+    }
+    Ok(())
+}
+
+/// Check the name of the root element, `root`, against the one `config` expects.
+fn check_root(root: &BytesStart<'_>, config: &XmlConfig) -> Result<(), XmlRejection> {
+    let expected = match config.expected_root() {
+        Some(expected) => expected,
+        None => return Ok(()),
+    };
+
+    let name = if expected.contains(':') {
+        root.name().into_inner()
+    } else {
+        root.local_name().into_inner()
+    };
+    if name != expected.as_bytes() {
+        return Err(XmlRejection::UnexpectedRoot {
+            expected: expected.to_owned(),
+            found: String::from_utf8_lossy(root.name().into_inner()).into_owned(),
+        });
+    }
+    Ok(())
+}
+
+/// Whether a DOCTYPE refers to anything outside the document, either an external DTD subset
+/// or an external entity, through a `SYSTEM` or `PUBLIC` identifier.
+fn declares_external_entity(doctype: &[u8]) -> bool {
+    let mut quote = None;
+    let mut word_start = None;
+
+    for (index, &byte) in doctype.iter().enumerate() {
+        if let Some(open) = quote {
+            if byte == open {
+                quote = None;
+            }
+            continue;
+        }
+
+        let is_word_byte = byte.is_ascii_alphanumeric();
+        match (word_start, is_word_byte) {
+            (None, true) => word_start = Some(index),
+            (Some(start), false) => {
+                if is_external_id(&doctype[start..index]) {
+                    return true;
+                }
+                word_start = None;
+            }
+            _ => {}
+        }
+
+        if byte == b'"' || byte == b'\'' {
+            quote = Some(byte);
+        }
+    }
+
+    word_start.is_some_and(|start| is_external_id(&doctype[start..]))
+}
+
+fn is_external_id(word: &[u8]) -> bool {
+    word == b"SYSTEM" || word == b"PUBLIC"
+}
+
+/// Replacement text of the general entities declared in a DOCTYPE, and how much of the
+/// document has been expanded so far.
+#[derive(Default)]
+struct Entities {
+    values: HashMap<Vec<u8>, Vec<u8>>,
+    expanded: usize,
+}
+
+impl Entities {
+    /// Resolve every internal entity declared in `doctype`, within the limits of
+    /// [`XmlConfig::with_entity_limit`].
+    fn declared_in(doctype: &[u8], config: &XmlConfig) -> Result<Self, XmlRejection> {
+        let declarations = entity_declarations(doctype);
+        let mut entities = Self::default();
+
+        for &(name, _) in &declarations {
+            entities.resolve(name, &declarations, 0, config)?;
+        }
+
+        Ok(entities)
+    }
+
+    /// The replacement text of the entity `name`, or `None` if it isn't declared.
+    fn resolve(
+        &mut self,
+        name: &[u8],
+        declarations: &[(&[u8], &[u8])],
+        depth: usize,
+        config: &XmlConfig,
+    ) -> Result<Option<Vec<u8>>, XmlRejection> {
+        if let Some(value) = self.values.get(name) {
+            return Ok(Some(value.clone()));
+        }
+        // The first declaration of an entity is the binding one.
+        let value = match declarations.iter().find(|(declared, _)| *declared == name) {
+            Some(&(_, value)) => value,
+            None => return Ok(None),
+        };
+        // Entities referring to themselves, directly or not, never stop growing.
+        if depth >= config.entity_max_depth() {
+            return Err(XmlRejection::EntityExpansionLimit);
+        }
+
+        let mut resolved = Vec::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(amp) = rest.iter().position(|&byte| byte == b'&') {
+            resolved.extend_from_slice(&rest[..amp]);
+            rest = &rest[amp..];
+            let replacement = match reference_name(&rest[1..]) {
+                Some(reference) => self
+                    .resolve(reference, declarations, depth + 1, config)?
+                    .map(|replacement| (reference.len(), replacement)),
+                None => None,
+            };
+            match replacement {
+                Some((len, replacement)) => {
+                    resolved.extend_from_slice(&replacement);
+                    rest = &rest[len + 2..];
+                }
+                // Character references and the predefined or registered entities are left
+                // for later.
+                None => {
+                    resolved.push(b'&');
+                    rest = &rest[1..];
+                }
+            }
+            if resolved.len() > config.entity_max_bytes() {
+                return Err(XmlRejection::EntityExpansionLimit);
+            }
+        }
+        resolved.extend_from_slice(rest);
+        if resolved.len() > config.entity_max_bytes() {
+            return Err(XmlRejection::EntityExpansionLimit);
+        }
+
+        self.values.insert(name.to_vec(), resolved.clone());
+        Ok(Some(resolved))
+    }
+
+    /// Account for the entity references in a piece of markup or text.
+    fn expand(&mut self, raw: &[u8], config: &XmlConfig) -> Result<(), XmlRejection> {
+        if self.values.is_empty() {
+            return Ok(());
+        }
+
+        for reference in entity_references(raw) {
+            if let Some(value) = self.values.get(reference) {
+                self.expanded = self.expanded.saturating_add(value.len());
+                if self.expanded > config.entity_max_bytes() {
+                    return Err(XmlRejection::EntityExpansionLimit);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn into_resolved(self) -> BTreeMap<String, String> {
+        self.values
+            .into_iter()
+            .map(|(name, value)| {
+                (
+                    String::from_utf8_lossy(&name).into_owned(),
+                    String::from_utf8_lossy(&value).into_owned(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Names and replacement text of the internal general entities declared in a DOCTYPE.
+///
+/// Parameter entities and external entities are skipped: they are never expanded here.
+fn entity_declarations(doctype: &[u8]) -> Vec<(&[u8], &[u8])> {
+    const ENTITY: &[u8] = b"<!ENTITY";
+
+    let mut declarations = Vec::new();
+    let mut rest = doctype;
+
+    while let Some(start) = find(rest, ENTITY) {
+        rest = trim_start(&rest[start + ENTITY.len()..]);
+        if rest.first() == Some(&b'%') {
+            continue;
+        }
+
+        let name_len = rest
+            .iter()
+            .position(|byte| byte.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let name = &rest[..name_len];
+        rest = trim_start(&rest[name_len..]);
+
+        match rest.first() {
+            Some(&quote) if quote == b'"' || quote == b'\'' => {
+                let value_len = rest[1..]
+                    .iter()
+                    .position(|&byte| byte == quote)
+                    .unwrap_or(rest.len() - 1);
+                declarations.push((name, &rest[1..1 + value_len]));
+                rest = &rest[(value_len + 2).min(rest.len())..];
+            }
+            _ => {}
+        }
+    }
+
+    declarations
+}
+
+/// Names of the `&name;` references in `raw`, leaving out character references.
+fn entity_references(raw: &[u8]) -> impl Iterator<Item = &[u8]> {
+    raw.split(|&byte| byte == b'&')
+        .skip(1)
+        .filter_map(reference_name)
+}
+
+/// Name of the reference whose `&` comes right before `after`, leaving out character
+/// references.
+fn reference_name(after: &[u8]) -> Option<&[u8]> {
+    let end = after.iter().position(|&byte| byte == b';')?;
+    let name = &after[..end];
+    (!name.is_empty() && name[0] != b'#').then_some(name)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn trim_start(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    &bytes[start..]
+}
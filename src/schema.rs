@@ -0,0 +1,186 @@
+use async_trait::async_trait;
+use axum_core::{extract::FromRequest, BoxError};
+use http::Request;
+use http_body::Body as HttpBody;
+use libxml::{
+    error::StructuredError,
+    parser::{Parser, ParserOptions},
+    schemas::{SchemaParserContext, SchemaValidationContext},
+};
+use serde::de::DeserializeOwned;
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use crate::{
+    backend,
+    config::XmlConfig,
+    de::{self, Document},
+    rejection::XmlRejection,
+    xml_content_type,
+};
+
+/// An XML Schema (XSD) for [`SchemaValidatedXml`] to check request bodies against.
+///
+/// Add it to the routes that use the extractor as an [`Extension`]. The schema is checked when
+/// the `XmlSchema` is created, and compiled again for every request from there, as libxml2's
+/// compiled schemas can't be shared between threads safely. Requires the `xsd` feature.
+///
+/// [`Extension`]: https://docs.rs/axum/0.6/axum/struct.Extension.html
+#[derive(Clone)]
+pub struct XmlSchema {
+    xsd: Arc<[u8]>,
+}
+
+impl XmlSchema {
+    /// Load the schema from the XSD document `xsd`.
+    pub fn new(xsd: impl Into<Vec<u8>>) -> Result<Self, SchemaError> {
+        let schema = Self {
+            xsd: xsd.into().into(),
+        };
+        schema
+            .context()
+            .map_err(|errors| SchemaError(messages(&errors)))?;
+        Ok(schema)
+    }
+
+    fn context(&self) -> Result<SchemaValidationContext, Vec<StructuredError>> {
+        SchemaValidationContext::from_parser(&mut SchemaParserContext::from_buffer(&self.xsd))
+    }
+
+    /// Check the UTF-8 `document` against the schema.
+    ///
+    /// libxml2 would recover from malformed documents by default; they are rejected as syntax
+    /// errors instead, the same as [`Xml`](crate::Xml) does.
+    fn validate(&self, document: &[u8]) -> Result<(), XmlRejection> {
+        let mut context = self
+            .context()
+            .map_err(|errors| XmlRejection::SchemaValidation(messages(&errors)))?;
+        let options = ParserOptions {
+            recover: false,
+            no_net: true,
+            ..ParserOptions::default()
+        };
+        let document = Parser::default()
+            .parse_string_with_options(document, options)
+            .map_err(|_| backend::syntax_error("the document is not well-formed"))?;
+
+        context
+            .validate_document(&document)
+            .map_err(|errors| XmlRejection::SchemaValidation(messages(&errors)))
+    }
+}
+
+impl fmt::Debug for XmlSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XmlSchema")
+            .field("len", &self.xsd.len())
+            .finish()
+    }
+}
+
+/// Error returned by [`XmlSchema::new`] for a document that isn't a valid XML Schema.
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid XML schema: {0}")]
+pub struct SchemaError(String);
+
+/// The libxml2 error messages, one line each, as `line 3: ...`.
+fn messages(errors: &[StructuredError]) -> String {
+    let messages: Vec<String> = errors
+        .iter()
+        .filter_map(|error| {
+            let message = error.message.as_deref()?.trim();
+            Some(match error.line {
+                Some(line) if line > 0 => format!("line {}: {}", line, message),
+                _ => message.to_owned(),
+            })
+        })
+        .collect();
+
+    if messages.is_empty() {
+        "unknown error".to_owned()
+    } else {
+        messages.join("; ")
+    }
+}
+
+/// XML Extractor which validates the body against an XML Schema before deserializing it.
+///
+/// Checks the request like [`Xml`](crate::Xml) does, then validates the document against the
+/// [`XmlSchema`] extension of the route, and only deserializes it if it conforms. Documents
+/// that don't are rejected with [`XmlRejection::SchemaValidation`], a `422 Unprocessable
+/// Entity` with libxml2's messages, and routes without a schema with
+/// [`XmlRejection::MissingSchema`]. The schema sees the document with the entities of
+/// [`XmlConfig::with_entities`](crate::XmlConfig::with_entities) and those declared in its
+/// DOCTYPE already expanded. Requires the `xsd` feature.
+///
+/// ```rust,no_run
+/// use axum::{routing::post, Extension, Router};
+/// use serde::Deserialize;
+/// use rustcms_axum_xml::{SchemaValidatedXml, XmlSchema};
+///
+/// #[derive(Deserialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// async fn create_user(SchemaValidatedXml(user): SchemaValidatedXml<User>) {
+///     // user matched the schema
+/// }
+///
+/// let schema = XmlSchema::new(std::fs::read("user.xsd").unwrap()).unwrap();
+/// let app: Router = Router::new()
+///     .route("/users", post(create_user))
+///     .layer(Extension(schema));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SchemaValidatedXml<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for SchemaValidatedXml<T>
+where
+    T: DeserializeOwned,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = XmlRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let config = XmlConfig::from_extensions(req.extensions());
+        let request_id = config.request_id(req.headers());
+        let schema = req.extensions().get::<XmlSchema>().cloned();
+
+        let extracted: Result<Self, XmlRejection> = async move {
+            let schema = schema.ok_or(XmlRejection::MissingSchema)?;
+            if !xml_content_type(req.headers(), &config) {
+                return Err(config.missing_content_type(&req));
+            }
+
+            let document = Document::from_request(req, state, &config).await?;
+            let (bytes, expanded) = document.into_expanded(&config)?;
+            schema.validate(&expanded)?;
+
+            Ok(Self(de::deserialize_utf8(&bytes, &config)?))
+        }
+        .await;
+        extracted.map_err(|rejection| rejection.with_request_id(request_id))
+    }
+}
+
+impl<T> Deref for SchemaValidatedXml<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for SchemaValidatedXml<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
@@ -0,0 +1,308 @@
+use async_trait::async_trait;
+use axum_core::{body, extract::FromRequest, response::Response, BoxError};
+use http::{
+    header::{self, HeaderValue},
+    Request, StatusCode,
+};
+use http_body::{Body as HttpBody, Full};
+use quick_xml::{
+    events::{attributes::Attribute, BytesStart, BytesText, Event},
+    name::QName,
+    writer::Writer,
+    Reader,
+};
+use serde::de::DeserializeOwned;
+use std::ops::{Deref, DerefMut};
+
+use crate::{
+    config::XmlConfig,
+    de::{self, Document},
+    rejection::XmlRejection,
+    xml_content_type, Xml,
+};
+
+/// Namespace of the SOAP 1.2 envelope.
+const SOAP_NAMESPACE: &str = "http://www.w3.org/2003/05/soap-envelope";
+
+/// Fault code of a SOAP 1.2 fault, saying whose fault it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoapFaultCode {
+    /// The envelope is not a SOAP 1.2 envelope.
+    VersionMismatch,
+    /// A header marked `mustUnderstand` wasn't understood.
+    MustUnderstand,
+    /// A header or the body uses an unsupported data encoding.
+    DataEncodingUnknown,
+    /// The request was malformed or lacked information.
+    Sender,
+    /// The request was fine but couldn't be processed.
+    Receiver,
+}
+
+impl SoapFaultCode {
+    fn as_str(self) -> &'static str {
+        match self {
+            SoapFaultCode::VersionMismatch => "soap:VersionMismatch",
+            SoapFaultCode::MustUnderstand => "soap:MustUnderstand",
+            SoapFaultCode::DataEncodingUnknown => "soap:DataEncodingUnknown",
+            SoapFaultCode::Sender => "soap:Sender",
+            SoapFaultCode::Receiver => "soap:Receiver",
+        }
+    }
+
+    /// Status of the HTTP response carrying the fault, as the SOAP 1.2 HTTP binding has it.
+    fn status(self) -> StatusCode {
+        match self {
+            SoapFaultCode::Sender => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl Xml<()> {
+    /// Respond with a SOAP 1.2 fault.
+    ///
+    /// The body is a `soap:Envelope` holding a `soap:Fault` with `code` and `reason`, sent as
+    /// `application/soap+xml` with `400 Bad Request` for [`SoapFaultCode::Sender`] faults and
+    /// `500 Internal Server Error` for the others.
+    ///
+    /// ```rust
+    /// use axum::response::Response;
+    /// use rustcms_axum_xml::{SoapFaultCode, Xml};
+    ///
+    /// async fn missing_order() -> Response {
+    ///     Xml::soap_fault(SoapFaultCode::Sender, "The order number is missing")
+    /// }
+    /// ```
+    pub fn soap_fault(code: SoapFaultCode, reason: &str) -> Response {
+        let mut writer = Writer::new(Vec::new());
+        writer
+            .create_element("soap:Envelope")
+            .with_attribute(("xmlns:soap", SOAP_NAMESPACE))
+            .write_inner_content(|writer| {
+                writer
+                    .create_element("soap:Body")
+                    .write_inner_content(|writer| {
+                        writer
+                            .create_element("soap:Fault")
+                            .write_inner_content(|writer| {
+                                writer.create_element("soap:Code").write_inner_content(
+                                    |writer| {
+                                        writer
+                                            .create_element("soap:Value")
+                                            .write_text_content(BytesText::new(code.as_str()))?;
+                                        Ok(())
+                                    },
+                                )?;
+                                writer.create_element("soap:Reason").write_inner_content(
+                                    |writer| {
+                                        writer
+                                            .create_element("soap:Text")
+                                            .with_attribute(("xml:lang", "en"))
+                                            .write_text_content(BytesText::new(reason))?;
+                                        Ok(())
+                                    },
+                                )?;
+                                Ok(())
+                            })?;
+                        Ok(())
+                    })?;
+                Ok(())
+            })
+            .expect("writing to a `Vec` never fails");
+
+        let mut res = Response::new(body::boxed(Full::from(writer.into_inner())));
+        *res.status_mut() = code.status();
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/soap+xml"),
+        );
+        res
+    }
+}
+
+/// XML Extractor for the payload of a SOAP request.
+///
+/// The request is checked like with [`Xml`], which accepts SOAP 1.2's `application/soap+xml`
+/// and SOAP 1.1's `text/xml`. The body must be an `Envelope` with the payload as the first
+/// element of its `Body`; any `Header` is skipped. Only the payload is deserialized, with the
+/// namespaces declared on the envelope still in scope. Elements are matched by local name, so
+/// any prefix and either SOAP version works.
+///
+/// Bodies that aren't such an envelope are rejected with [`XmlRejection::NotSoapEnvelope`],
+/// and envelopes carrying a `Fault` with [`XmlRejection::SoapFault`].
+///
+/// ```rust,no_run
+/// use axum::{routing::post, Router};
+/// use serde::Deserialize;
+/// use rustcms_axum_xml::SoapBody;
+///
+/// #[derive(Deserialize)]
+/// struct GetPrice {
+///     item: String,
+/// }
+///
+/// async fn get_price(SoapBody(request): SoapBody<GetPrice>) {
+///     // request is the `GetPrice` element inside `soap:Body`
+/// }
+///
+/// let app: Router = Router::new().route("/soap", post(get_price));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SoapBody<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for SoapBody<T>
+where
+    T: DeserializeOwned,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = XmlRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let config = XmlConfig::from_extensions(req.extensions());
+        let request_id = config.request_id(req.headers());
+
+        let extracted: Result<Self, XmlRejection> = async move {
+            if xml_content_type(req.headers(), &config) {
+                let document = Document::from_request(req, state, &config).await?;
+                let payload = payload(&document.into_utf8(&config)?)?;
+
+                Ok(Self(de::deserialize_utf8(&payload, &config)?))
+            } else {
+                Err(config.missing_content_type(&req))
+            }
+        }
+        .await;
+        extracted.map_err(|rejection| rejection.with_request_id(request_id))
+    }
+}
+
+impl<T> Deref for SoapBody<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for SoapBody<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Cut the payload out of a SOAP envelope, declaring the namespaces of the `Envelope` and
+/// `Body` on it.
+///
+/// Documents that fail to parse are rejected as not being an envelope.
+fn payload(xml: &[u8]) -> Result<Vec<u8>, XmlRejection> {
+    let mut reader = Reader::from_reader(xml);
+    let mut namespaces = Vec::new();
+    let not_envelope = XmlRejection::NotSoapEnvelope;
+
+    let envelope = next_element(&mut reader).ok_or(not_envelope("no `Envelope` element"))?;
+    if envelope.start.local_name().as_ref() != b"Envelope" || !envelope.has_content {
+        return Err(not_envelope("no `Envelope` element"));
+    }
+    collect_namespaces(&envelope.start, &mut namespaces);
+
+    loop {
+        let element = next_element(&mut reader).ok_or(not_envelope("no `Body` in the envelope"))?;
+        match element.start.local_name().as_ref() {
+            b"Header" if element.has_content => {
+                reader
+                    .read_to_end(element.start.name())
+                    .map_err(|_| not_envelope("the `Header` is not well-formed"))?;
+            }
+            b"Header" => {}
+            b"Body" if element.has_content => {
+                collect_namespaces(&element.start, &mut namespaces);
+                break;
+            }
+            b"Body" => return Err(not_envelope("the `Body` is empty")),
+            _ => return Err(not_envelope("no `Body` in the envelope")),
+        }
+    }
+
+    let payload = next_element(&mut reader).ok_or(not_envelope("the `Body` is empty"))?;
+    if payload.start.local_name().as_ref() == b"Fault" {
+        return Err(XmlRejection::SoapFault);
+    }
+    if payload.has_content {
+        reader
+            .read_to_end(payload.start.name())
+            .map_err(|_| not_envelope("the payload is not well-formed"))?;
+    }
+
+    let mut tag = payload.start.to_owned();
+    let declared: Vec<Vec<u8>> = tag
+        .attributes()
+        .flatten()
+        .map(|attribute| attribute.key.as_ref().to_vec())
+        .collect();
+    for (key, value) in &namespaces {
+        if !declared.contains(key) {
+            tag.push_attribute(Attribute {
+                key: QName(key),
+                value: value.as_slice().into(),
+            });
+        }
+    }
+
+    let mut writer = Writer::new(Vec::with_capacity(xml.len()));
+    let tag = if payload.has_content {
+        Event::Start(tag)
+    } else {
+        Event::Empty(tag)
+    };
+    writer
+        .write_event(tag)
+        .expect("writing to a `Vec` never fails");
+    let mut payload_bytes = writer.into_inner();
+    payload_bytes.extend_from_slice(&xml[payload.tag_end..reader.buffer_position()]);
+    Ok(payload_bytes)
+}
+
+/// Start tag read by [`next_element`].
+struct Element<'a> {
+    start: BytesStart<'a>,
+    /// Whether the tag is a start tag rather than an empty element.
+    has_content: bool,
+    /// Offset just past the tag.
+    tag_end: usize,
+}
+
+/// Read up to and including the next start tag.
+///
+/// Returns `None` at an end tag, at the end of the document and on errors.
+fn next_element<'a>(reader: &mut Reader<&'a [u8]>) -> Option<Element<'a>> {
+    loop {
+        let (start, has_content) = match reader.read_event().ok()? {
+            Event::Start(start) => (start, true),
+            Event::Empty(start) => (start, false),
+            Event::End(_) | Event::Eof => return None,
+            _ => continue,
+        };
+        return Some(Element {
+            start,
+            has_content,
+            tag_end: reader.buffer_position(),
+        });
+    }
+}
+
+/// Add the namespace declarations on `start` to `namespaces`, replacing earlier declarations of
+/// the same prefix.
+fn collect_namespaces(start: &BytesStart<'_>, namespaces: &mut Vec<(Vec<u8>, Vec<u8>)>) {
+    for attribute in start.attributes().flatten() {
+        let key = attribute.key.as_ref();
+        if key == b"xmlns" || key.starts_with(b"xmlns:") {
+            namespaces.retain(|(declared, _)| declared != key);
+            namespaces.push((key.to_vec(), attribute.value.into_owned()));
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use async_trait::async_trait;
+use axum_core::{extract::FromRequest, BoxError};
+use bytes::{Buf, Bytes, BytesMut};
+use http::{header, Request};
+use http_body::Body as HttpBody;
+use quick_xml::{events::Event, Reader};
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Read};
+use std::ops::{Deref, DerefMut};
+use tokio::sync::mpsc;
+
+use crate::{
+    backend, body::content_length, config::XmlConfig, rejection::XmlRejection, scan,
+    xml_content_type,
+};
+
+/// Number of body chunks held between the connection and the parser.
+const CHANNEL_CAPACITY: usize = 4;
+
+/// XML Extractor which deserializes the request body as it arrives.
+///
+/// [`Xml`](crate::Xml) buffers the whole body before parsing it, so a multi-megabyte document
+/// is held in memory in full. `StreamingXml` hands the body to the parser chunk by chunk
+/// instead, on a blocking thread, and only ever keeps a few chunks in memory on top of the
+/// value being built. The `Content-Type` is checked like [`Xml`](crate::Xml) does and the
+/// [`XmlConfig::with_limit`] cap is enforced while the body streams in. Small chunks are
+/// collected into pieces of [`XmlConfig::with_stream_buffer_size`] bytes before they are handed
+/// over.
+///
+/// The nesting depth limit and the DOCTYPE checks of [`XmlConfig`] are applied as the
+/// elements arrive. Checks that need the whole document up front are not: the body must be
+/// UTF-8 and uncompressed, and the element, attribute and entity limits are skipped.
+/// Requires the `streaming` feature and a Tokio runtime.
+///
+/// ```rust,no_run
+/// use axum::{routing::post, Router};
+/// use serde::Deserialize;
+/// use rustcms_axum_xml::StreamingXml;
+///
+/// #[derive(Deserialize)]
+/// struct Import {
+///     record: Vec<Record>,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Record {
+///     id: u64,
+/// }
+///
+/// async fn import(StreamingXml(import): StreamingXml<Import>) {
+///     // import.record holds every record of the upload
+/// }
+///
+/// let app: Router = Router::new().route("/import", post(import));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StreamingXml<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for StreamingXml<T>
+where
+    T: DeserializeOwned + Send + 'static,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = XmlRejection;
+
+    async fn from_request(req: Request<B>, _state: &S) -> Result<Self, Self::Rejection> {
+        let config = XmlConfig::from_extensions(req.extensions());
+        let request_id = config.request_id(req.headers());
+
+        let extracted: Result<Self, XmlRejection> = async move {
+            if !xml_content_type(req.headers(), &config) {
+                return Err(config.missing_content_type(&req));
+            }
+            if req.headers().contains_key(header::CONTENT_ENCODING) {
+                return Err(XmlRejection::UnsupportedContentEncoding);
+            }
+            let limit = config.limit();
+            let length = content_length(req.headers());
+            if limit.is_some_and(|limit| length.is_some_and(|len| len > limit)) {
+                return Err(XmlRejection::PayloadTooLarge);
+            }
+
+            let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+            let checks = config.clone();
+            let parser = tokio::task::spawn_blocking(move || {
+                let mut reader = CheckedReader::new(ChannelReader::new(receiver), checks);
+                backend::from_reader::<_, T>(&mut reader)
+                    .map_err(|err| reader.rejection.take().unwrap_or_else(|| err.into()))
+            });
+
+            let buffer_size = config.stream_buffer_size();
+            let mut pending = BytesMut::new();
+            let mut body = Box::pin(req.into_body());
+            let mut received = 0;
+            let mut blank = true;
+            let mut hung_up = false;
+            loop {
+                let chunk = match body.data().await {
+                    Some(chunk) => chunk.map_err(|err| XmlRejection::FailedToReadBody(err.into())),
+                    None => break,
+                };
+                let mut chunk = chunk?;
+                let chunk = chunk.copy_to_bytes(chunk.remaining());
+
+                received += chunk.len();
+                if limit.is_some_and(|limit| received > limit) {
+                    return Err(XmlRejection::PayloadTooLarge);
+                }
+                blank = blank && chunk.iter().all(u8::is_ascii_whitespace);
+
+                let piece = if pending.is_empty() && chunk.len() >= buffer_size {
+                    chunk
+                } else {
+                    pending.extend_from_slice(&chunk);
+                    if pending.len() < buffer_size {
+                        continue;
+                    }
+                    pending.split().freeze()
+                };
+
+                // The parser hangs up once it has read a complete document or failed.
+                if sender.send(piece).await.is_err() {
+                    hung_up = true;
+                    break;
+                }
+            }
+            if !hung_up && !pending.is_empty() {
+                let _ = sender.send(pending.freeze()).await;
+            }
+            drop(sender);
+
+            match parser.await {
+                Ok(Ok(value)) => Ok(Self(value)),
+                Ok(Err(_)) if received == 0 && length.is_some_and(|len| len > 0) => {
+                    Err(XmlRejection::BodyAlreadyExtracted)
+                }
+                Ok(Err(_)) if blank => Err(XmlRejection::EmptyBody),
+                Ok(Err(rejection)) => Err(rejection),
+                Err(err) => std::panic::resume_unwind(err.into_panic()),
+            }
+        }
+        .await;
+        extracted.map_err(|rejection| rejection.with_request_id(request_id))
+    }
+}
+
+impl<T> Deref for StreamingXml<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for StreamingXml<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Blocking reader over the chunks of a body received from the async side.
+struct ChannelReader {
+    receiver: mpsc::Receiver<Bytes>,
+    chunk: Bytes,
+}
+
+impl ChannelReader {
+    fn new(receiver: mpsc::Receiver<Bytes>) -> Self {
+        Self {
+            receiver,
+            chunk: Bytes::new(),
+        }
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.fill_buf()?.read(buf)?;
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for ChannelReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.chunk.is_empty() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => self.chunk = chunk,
+                None => break,
+            }
+        }
+        Ok(&self.chunk)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.chunk.advance(amt.min(self.chunk.len()));
+    }
+}
+
+/// Reader checking a body against the limits in `config` one event at a time, on its way from
+/// a [`ChannelReader`] to the deserializer.
+///
+/// Each event is read from the body first and its bytes are only handed on once it passed.
+/// Once the body stops being well-formed the rest is handed on unchecked, for the deserializer
+/// to report the error.
+struct CheckedReader {
+    reader: Reader<RecordingReader>,
+    config: XmlConfig,
+    event: Vec<u8>,
+    depth: usize,
+    checking: bool,
+    rejection: Option<XmlRejection>,
+}
+
+impl CheckedReader {
+    fn new(source: ChannelReader, config: XmlConfig) -> Self {
+        Self {
+            reader: Reader::from_reader(RecordingReader {
+                source,
+                recorded: VecDeque::new(),
+            }),
+            config,
+            event: Vec::new(),
+            depth: 0,
+            checking: true,
+            rejection: None,
+        }
+    }
+
+    /// Read and check the next event, leaving its bytes in the recording.
+    fn next_event(&mut self) -> io::Result<()> {
+        self.event.clear();
+        let checked = match self.reader.read_event_into(&mut self.event) {
+            Ok(Event::Start(_)) => {
+                self.depth += 1;
+                scan::check_depth(self.depth, &self.config)
+            }
+            Ok(Event::Empty(_)) => scan::check_depth(self.depth + 1, &self.config),
+            Ok(Event::End(_)) => {
+                self.depth = self.depth.saturating_sub(1);
+                Ok(())
+            }
+            Ok(Event::DocType(doctype)) => scan::check_doctype(&doctype, &self.config),
+            Ok(Event::Eof) | Err(_) => {
+                self.checking = false;
+                Ok(())
+            }
+            Ok(_) => Ok(()),
+        };
+
+        checked.map_err(|rejection| {
+            self.checking = false;
+            self.rejection = Some(rejection);
+            io::Error::new(io::ErrorKind::InvalidData, "request body rejected")
+        })
+    }
+}
+
+impl Read for CheckedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.fill_buf()?.read(buf)?;
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for CheckedReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.rejection.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "request body rejected",
+            ));
+        }
+        while self.checking && self.reader.get_ref().recorded.is_empty() {
+            self.next_event()?;
+        }
+
+        let recording = self.reader.get_mut();
+        match recording.recorded.front() {
+            Some(recorded) => Ok(recorded),
+            None => recording.source.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let recording = self.reader.get_mut();
+        match recording.recorded.front_mut() {
+            Some(recorded) => {
+                recorded.advance(amt.min(recorded.len()));
+                if recorded.is_empty() {
+                    recording.recorded.pop_front();
+                }
+            }
+            None => recording.source.consume(amt),
+        }
+    }
+}
+
+/// Reader keeping the bytes the event reader consumed from a body, so that they can be handed
+/// on as they were.
+struct RecordingReader {
+    source: ChannelReader,
+    recorded: VecDeque<Bytes>,
+}
+
+impl Read for RecordingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.fill_buf()?.read(buf)?;
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for RecordingReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.source.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let amt = amt.min(self.source.chunk.len());
+        if amt > 0 {
+            self.recorded.push_back(self.source.chunk.slice(..amt));
+        }
+        self.source.consume(amt);
+    }
+}
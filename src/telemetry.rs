@@ -0,0 +1,30 @@
+//! Metrics recorded with the `metrics` feature, listed in the README.
+
+use crate::rejection::XmlRejection;
+
+pub(crate) fn record_request(body_len: usize, ok: bool) {
+    metrics::counter!(
+        "axum_xml.requests.total",
+        "outcome" => if ok { "ok" } else { "rejected" }
+    )
+    .increment(1);
+    metrics::histogram!("axum_xml.request.body_size").record(body_len as f64);
+}
+
+pub(crate) fn record_rejection(rejection: &XmlRejection) {
+    metrics::counter!(
+        "axum_xml.rejections.total",
+        "rejection" => rejection.variant_name(),
+        "code" => rejection.code()
+    )
+    .increment(1);
+}
+
+pub(crate) fn record_response(body_len: usize) {
+    metrics::counter!("axum_xml.responses.total").increment(1);
+    metrics::histogram!("axum_xml.response.body_size").record(body_len as f64);
+}
+
+pub(crate) fn record_serialize_error() {
+    metrics::counter!("axum_xml.serialize_errors.total").increment(1);
+}
@@ -0,0 +1,84 @@
+//! Helpers for testing handlers that respond with XML, available with the `test-util` feature.
+//!
+//! ```rust
+//! use rustcms_axum_xml::test_util::assert_xml_eq;
+//!
+//! assert_xml_eq(
+//!     br#"<user id="1" name="ann"></user>"#,
+//!     r#"<user name="ann" id="1"/>"#,
+//! );
+//! ```
+
+use crate::{encoding, rejection::XmlRejection, value::XmlValue};
+
+/// Assert that `actual` is the same XML document as `expected`, ignoring differences that
+/// don't change what it says.
+///
+/// Both documents are parsed and compared as trees. Attributes may come in any order,
+/// whitespace between elements is skipped, `<a></a>` equals `<a/>`, and escaping, CDATA
+/// sections, comments and the XML declaration make no difference. Element and attribute names
+/// and text must match exactly, including namespace prefixes. `actual` may be in any
+/// encoding the crate reads.
+///
+/// # Panics
+///
+/// Panics if the documents differ, printing both in the form they are compared in, or if
+/// either of them isn't well-formed.
+#[track_caller]
+pub fn assert_xml_eq(actual: &[u8], expected: &str) {
+    let actual_tree = canonical(actual).unwrap_or_else(|err| {
+        panic!(
+            "actual document is not well-formed: {}\n{}",
+            err,
+            String::from_utf8_lossy(actual)
+        )
+    });
+    let expected_tree = canonical(expected.as_bytes()).unwrap_or_else(|err| {
+        panic!(
+            "expected document is not well-formed: {}\n{}",
+            err, expected
+        )
+    });
+
+    if actual_tree != expected_tree {
+        panic!(
+            "XML documents differ\n  actual: {}\nexpected: {}",
+            String::from_utf8_lossy(&actual_tree.to_bytes()),
+            String::from_utf8_lossy(&expected_tree.to_bytes())
+        );
+    }
+}
+
+fn canonical(bytes: &[u8]) -> Result<XmlValue, XmlRejection> {
+    let utf8 = encoding::to_utf8(bytes, None)?;
+    let mut tree = XmlValue::parse(&utf8, false)?;
+    normalize(&mut tree);
+    Ok(tree)
+}
+
+/// Sort the attributes of every element, join adjacent text nodes, such as the two sides of
+/// a comment, and drop text that is only whitespace.
+fn normalize(node: &mut XmlValue) {
+    let (attributes, children) = match node {
+        XmlValue::Element {
+            attributes,
+            children,
+            ..
+        } => (attributes, children),
+        XmlValue::Text(_) => return,
+    };
+    attributes.sort();
+
+    let mut joined: Vec<XmlValue> = Vec::with_capacity(children.len());
+    for mut child in children.drain(..) {
+        match (&child, joined.last_mut()) {
+            (XmlValue::Text(text), Some(XmlValue::Text(previous))) => previous.push_str(text),
+            _ => {
+                normalize(&mut child);
+                joined.push(child);
+            }
+        }
+    }
+    joined.retain(|child| !matches!(child, XmlValue::Text(text) if text.trim().is_empty()));
+    *children = joined;
+}
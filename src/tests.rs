@@ -1,16 +1,24 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
 use std::net::{SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{assert_eq, println};
 
 use axum::body::{Body, HttpBody};
-use axum::routing::post;
-use axum::{BoxError, Router, Server};
+use axum::extract::FromRequest;
+use axum::routing::{get, post};
+use axum::{BoxError, Extension, Router, Server};
 use http::{Request, StatusCode};
 use reqwest::RequestBuilder;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tower::make::Shared;
 use tower_service::Service;
 
-use crate::Xml;
+use crate::{
+    AcceptedXml, BoolFormat, BorrowedXml, Cdata, DynXml, Encoding, InvalidCharPolicy, OptionalXml,
+    RawXml, SoapBody, SoapFaultCode, Xml, XmlBody, XmlCollection, XmlConfig, XmlContentType,
+    XmlEncoder, XmlLenient, XmlList, XmlRejection, XmlString, XmlValue, XmlWithRaw,
+};
 
 pub struct TestClient {
     client: reqwest::Client,
@@ -43,9 +51,17 @@ impl TestClient {
         }
     }
 
+    pub(crate) fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(format!("http://{}{}", self.addr, url))
+    }
+
     pub(crate) fn post(&self, url: &str) -> RequestBuilder {
         self.client.post(format!("http://{}{}", self.addr, url))
     }
+
+    pub(crate) fn head(&self, url: &str) -> RequestBuilder {
+        self.client.head(format!("http://{}{}", self.addr, url))
+    }
 }
 
 #[tokio::test]
@@ -119,5 +135,5001 @@ async fn xml_content_types() {
     assert!(valid_xml_content_type("application/xml;charset=utf-8").await);
     assert!(valid_xml_content_type("application/cloudevents+xml").await);
     assert!(valid_xml_content_type("text/xml").await);
+    assert!(valid_xml_content_type("Application/XML").await);
+    assert!(valid_xml_content_type("TEXT/Xml; Charset=UTF-8").await);
+    assert!(valid_xml_content_type("application/xml; charset=utf-8; boundary=something").await);
+    assert!(valid_xml_content_type("application/vnd.company+XML").await);
     assert!(!valid_xml_content_type("application/json").await);
+    assert!(!valid_xml_content_type("application/XMLish").await);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Inner {
+    name: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Outer {
+    inner: Inner,
+    other: Inner,
+}
+
+fn outer() -> Outer {
+    Outer {
+        inner: Inner {
+            name: "a".to_owned(),
+        },
+        other: Inner {
+            name: "b".to_owned(),
+        },
+    }
+}
+
+/// `outer()` as written by each backend: quick-xml turns primitive fields into attributes,
+/// serde-xml-rs into child elements.
+#[cfg(feature = "backend-quick-xml")]
+const OUTER_XML: &str = r#"<Outer><inner name="a"/><other name="b"/></Outer>"#;
+#[cfg(feature = "backend-serde-xml-rs")]
+const OUTER_XML: &str = "<Outer><inner><name>a</name></inner><other><name>b</name></other></Outer>";
+
+#[cfg(feature = "backend-quick-xml")]
+const OUTER_PRETTY_XML: &str = "<Outer>\n\t<inner name=\"a\"/>\n\t<other name=\"b\"/>\n</Outer>";
+#[cfg(feature = "backend-serde-xml-rs")]
+const OUTER_PRETTY_XML: &str = "<Outer>\n\t<inner>\n\t\t<name>a</name>\n\t</inner>\n\t<other>\n\t\t<name>b</name>\n\t</other>\n</Outer>";
+
+/// Global allocator counting the allocations made by each thread, and the peak number of bytes
+/// allocated by the whole process.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        let live = LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Run `f` and count the allocations it makes on this thread.
+#[cfg(feature = "backend-quick-xml")]
+fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+/// Await `f` and measure by how many bytes it raised the memory in use at its peak.
+///
+/// Other tests running at the same time count towards the peak as well, so only compare the
+/// result against generous bounds.
+#[cfg(feature = "streaming")]
+async fn peak_memory<R>(f: impl std::future::Future<Output = R>) -> (R, usize) {
+    let before = LIVE_BYTES.load(Ordering::Relaxed);
+    PEAK_BYTES.store(before, Ordering::Relaxed);
+    let result = f.await;
+    (
+        result,
+        PEAK_BYTES.load(Ordering::Relaxed).saturating_sub(before),
+    )
+}
+
+#[tokio::test]
+async fn serialize_compact_by_default() {
+    let app = Router::new().route("/", get(|| async { Xml(outer()) }));
+
+    let client = TestClient::new(app);
+    let res = client.get("/").send().await.unwrap();
+    let body = res.text().await.unwrap();
+
+    assert_eq!(body, OUTER_XML);
+}
+
+#[tokio::test]
+async fn serialize_pretty() {
+    let app = Router::new().route("/", get(|| async { Xml::pretty(outer()) }));
+
+    let client = TestClient::new(app);
+    let res = client.get("/").send().await.unwrap();
+    let content_type = res.headers()["content-type"].clone();
+    let body = res.text().await.unwrap();
+
+    assert_eq!(content_type, "application/xml");
+    assert_eq!(body, OUTER_PRETTY_XML);
+}
+
+#[tokio::test]
+async fn serialize_with_indent() {
+    let app = Router::new()
+        .route(
+            "/spaces",
+            get(|| async { Xml::with_indent(outer(), ' ', 2) }),
+        )
+        .route(
+            "/tabs",
+            get(|| async { Xml::with_indent(outer(), '\t', 1) }),
+        )
+        .route("/zero", get(|| async { Xml::with_indent(outer(), ' ', 0) }))
+        .route(
+            "/wide",
+            get(|| async { Xml::with_indent(outer(), '\u{3000}', 1) }),
+        );
+
+    let client = TestClient::new(app);
+    let get = |url: &'static str| client.get(url).send();
+
+    let spaces = get("/spaces").await.unwrap().text().await.unwrap();
+    let tabs = get("/tabs").await.unwrap().text().await.unwrap();
+    assert_eq!(tabs, OUTER_PRETTY_XML);
+    assert_ne!(spaces, tabs);
+    assert_eq!(spaces, tabs.replace('\t', "  "));
+
+    let res = get("/zero").await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let res = get("/wide").await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn serialize_with_root_name() {
+    let app = Router::new()
+        .route(
+            "/named",
+            get(|| async { Xml::with_root_name(outer(), "response") }),
+        )
+        .route("/unnamed", get(|| async { Xml(outer()) }));
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/named").send().await.unwrap();
+    let body = res.text().await.unwrap();
+    assert_eq!(body, OUTER_XML.replace("Outer", "response"));
+
+    let res = client.get("/unnamed").send().await.unwrap();
+    let body = res.text().await.unwrap();
+    assert_eq!(body, OUTER_XML);
+}
+
+#[tokio::test]
+async fn serialize_with_declaration() {
+    let app = Router::new()
+        .route("/", get(|| async { Xml::with_declaration(outer()) }))
+        .route(
+            "/pretty",
+            get(|| async { Xml::with_declaration(outer()).pretty() }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/").send().await.unwrap();
+    let body = res.text().await.unwrap();
+    assert_eq!(
+        body,
+        format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, OUTER_XML)
+    );
+
+    let res = client.get("/pretty").send().await.unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Outer>"));
+    assert_eq!(body.matches("<?xml").count(), 1);
+}
+
+#[tokio::test]
+async fn serialize_standalone_declaration() {
+    let app = Router::new()
+        .route(
+            "/",
+            get(|| async { Xml::builder(outer()).with_standalone("yes") }),
+        )
+        .route(
+            "/invalid",
+            get(|| async { Xml::with_declaration(outer()).with_standalone("maybe") }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/").send().await.unwrap();
+    assert_eq!(
+        res.text().await.unwrap(),
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>{}"#,
+            OUTER_XML
+        )
+    );
+
+    let res = client.get("/invalid").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let bytes = XmlEncoder::new()
+        .with_standalone("no")
+        .encode(&outer())
+        .unwrap();
+    assert!(bytes.starts_with(br#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>"#));
+
+    let err = XmlEncoder::new()
+        .with_standalone("YES")
+        .encode(&outer())
+        .unwrap_err();
+    assert!(err.to_string().contains("standalone"), "{err}");
+}
+
+#[tokio::test]
+async fn lenient_accepts_missing_content_type() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    let app = Router::new().route("/", post(|input: XmlLenient<Input>| async { input.0.foo }));
+
+    let client = TestClient::new(app);
+
+    let res = client
+        .post("/")
+        .body(r#"<Input foo="bar"/>"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+
+    let res = client
+        .post("/")
+        .body(r#"<Input foo="bar"/>"#)
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+
+    let res = client
+        .post("/")
+        .body(r#"<Input foo="bar"/>"#)
+        .header("content-type", "application/json")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    // quick-xml takes text outside of a root element for a value of the wrong type, where
+    // serde-xml-rs fails on the syntax.
+    let res = client.post("/").body("not xml").send().await.unwrap();
+    #[cfg(feature = "backend-quick-xml")]
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    #[cfg(feature = "backend-serde-xml-rs")]
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn invalid_body_reports_position() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        #[allow(dead_code)]
+        foo: String,
+    }
+
+    let body = "<Input>\n  <foo>bar</baz>\n</Input>";
+    let rejection =
+        crate::de::deserialize::<Input>(body.as_bytes(), None, &XmlConfig::new()).unwrap_err();
+
+    let position = rejection.position().expect("position is reported");
+    assert!(position > "<Input>\n  <foo>bar".len());
+    assert!(position <= body.len());
+    assert_eq!(rejection.line_column().map(|(line, _)| line), Some(2));
+
+    let app = Router::new().route("/", post(|_: Xml<Input>| async {}));
+
+    let client = TestClient::new(app);
+    let res = client
+        .post("/")
+        .body(body)
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res.text().await.unwrap().contains("at line 2, column"));
+}
+
+#[test]
+fn rewritten_body_reports_no_position() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        #[allow(dead_code)]
+        count: u32,
+    }
+
+    let config = XmlConfig::new().with_entities([("unit", "pieces")]);
+    let deserialize =
+        |body: &str| crate::de::deserialize::<Input>(body.as_bytes(), None, &config).unwrap_err();
+
+    let rejection = deserialize("<Input>\n  <count>many</count>\n</Input>");
+    assert!(rejection.position().is_some());
+
+    // Removing the comment or expanding the entity moves everything after it.
+    let rejection = deserialize("<Input>\n  <!-- how many? -->\n  <count>many</count>\n</Input>");
+    assert!(matches!(rejection, XmlRejection::InvalidXMLBody { .. }));
+    assert_eq!(rejection.position(), None);
+    let rejection = deserialize("<Input>\n  <count>many &unit;</count>\n</Input>");
+    assert!(matches!(rejection, XmlRejection::InvalidXMLBody { .. }));
+    assert_eq!(rejection.position(), None);
+
+    // The byte order mark is counted.
+    let body = "\u{FEFF}<Input><foo>bar</baz></Input>";
+    let rejection = deserialize(body);
+    let position = rejection.position().expect("position is reported");
+    assert!(position > "\u{FEFF}<Input><foo>bar".len());
+}
+
+#[tokio::test]
+async fn optional_xml() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    let app = Router::new().route(
+        "/",
+        post(|OptionalXml(input): OptionalXml<Input>| async {
+            match input {
+                Some(Xml(input)) => input.foo,
+                None => "none".to_owned(),
+            }
+        }),
+    );
+
+    let client = TestClient::new(app);
+
+    let res = client.post("/").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "none");
+
+    let res = client
+        .post("/")
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "none");
+
+    let res = client
+        .post("/")
+        .body(r#"<Input foo="bar"/>"#)
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+
+    let res = client
+        .post("/")
+        .body("<Input>")
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn custom_content_type_matcher() {
+    #[derive(Deserialize)]
+    struct Value {}
+
+    let config = XmlConfig::new()
+        .with_content_type_matcher(|mime| mime.type_() == "application" && mime.subtype() == "rss");
+    let app = Router::new()
+        .route("/", post(|Xml(_): Xml<Value>| async {}))
+        .layer(Extension(config));
+
+    let client = TestClient::new(app);
+
+    let res = client
+        .post("/")
+        .header("content-type", "application/rss")
+        .body("<Value />")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = client
+        .post("/")
+        .header("content-type", "application/xml")
+        .body("<Value />")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[cfg(feature = "xml-errors")]
+#[tokio::test]
+async fn rejections_as_xml() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        #[allow(dead_code)]
+        foo: String,
+    }
+
+    let app = Router::new().route("/", post(|_: Xml<Input>| async {}));
+
+    let client = TestClient::new(app);
+    let res = client
+        .post("/")
+        .body(r#"<Input foo="bar"/>"#)
+        .header("content-type", "application/json")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    assert_eq!(
+        res.text().await.unwrap(),
+        "<error><code>415</code><message>Expected request with `Content-Type: application/xml`</message></error>"
+    );
+
+    let res = client
+        .post("/")
+        .body("<Input>")
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = res.text().await.unwrap();
+    assert!(body.starts_with("<error><code>400</code><message>Failed to parse"));
+    assert!(body.ends_with("</message></error>"));
+}
+
+#[tokio::test]
+async fn reject_external_entities() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    const XXE: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE Input [ <!ENTITY xxe SYSTEM "file:///etc/passwd"> ]>
+<Input><foo>&xxe;</foo></Input>"#;
+    const INTERNAL: &str = r#"<!DOCTYPE Input [ <!ENTITY note "SYSTEM"> ]>
+<Input foo="bar"/>"#;
+
+    let handler = post(|input: Xml<Input>| async { input.0.foo });
+    let app = Router::new()
+        .route("/", handler.clone())
+        .route(
+            "/internal",
+            handler
+                .clone()
+                .layer(Extension(XmlConfig::new().forbid_doctype(false))),
+        )
+        .route(
+            "/allowed",
+            handler.layer(Extension(XmlConfig::new().allow_doctype(true))),
+        );
+
+    let client = TestClient::new(app);
+    let send = |url: &'static str, body: &'static str| {
+        client
+            .post(url)
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send("/", XXE).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = send("/internal", XXE).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = send("/internal", INTERNAL).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+
+    // Neither backend resolves external entities, so an allowed XXE payload gets past the
+    // DOCTYPE check without the file being read: quick-xml fails on the unknown entity and
+    // serde-xml-rs drops it.
+    let res = send("/allowed", XXE).await.unwrap();
+    #[cfg(feature = "backend-quick-xml")]
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    #[cfg(feature = "backend-serde-xml-rs")]
+    assert_eq!(res.text().await.unwrap(), "");
+}
+
+#[tokio::test]
+async fn reject_deeply_nested_documents() {
+    #[derive(Deserialize)]
+    struct Value {}
+
+    fn nested(depth: usize) -> String {
+        format!("{}{}", "<Value>".repeat(depth), "</Value>".repeat(depth))
+    }
+
+    let handler = post(|Xml(_): Xml<Value>| async {});
+    let app = Router::new().route("/", handler.clone()).route(
+        "/shallow",
+        handler.layer(Extension(XmlConfig::new().with_max_depth(2))),
+    );
+
+    let client = TestClient::new(app);
+    let send = |url: &'static str, body: String| {
+        client
+            .post(url)
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send("/", nested(10_000)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = send("/", nested(XmlConfig::DEFAULT_MAX_DEPTH))
+        .await
+        .unwrap();
+    assert_ne!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = send("/shallow", nested(2)).await.unwrap();
+    assert_ne!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = send("/shallow", "<Value><a><b/></a></Value>".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn reject_oversized_content_length() {
+    #[derive(Deserialize)]
+    struct Value {}
+
+    let app = Router::new()
+        .route("/", post(|Xml(_): Xml<Value>| async {}))
+        .layer(Extension(XmlConfig::new().with_limit(16)));
+
+    let client = TestClient::new(app);
+    let send = |body: &'static str| {
+        client
+            .post("/")
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send("<Value />").await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = send("<Value>too large for the limit</Value>")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn reject_oversized_chunked_body() {
+    #[derive(Debug, Deserialize)]
+    struct Value {}
+
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        for _ in 0..8 {
+            if sender.send_data("<Value></Value>".into()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut req = Request::builder()
+        .header("content-type", "application/xml")
+        .body(body)
+        .unwrap();
+    req.extensions_mut().insert(XmlConfig::new().with_limit(64));
+
+    let rejection = Xml::<Value>::from_request(req, &()).await.unwrap_err();
+    assert!(matches!(rejection, XmlRejection::PayloadTooLarge));
+}
+
+#[tokio::test]
+async fn reject_endless_chunked_body() {
+    use std::sync::Arc;
+
+    #[derive(Debug, Deserialize)]
+    struct Value {}
+
+    const CHUNK: &str = "<Value></Value>";
+    const LIMIT: usize = 1024;
+
+    // The body never ends, so the rejection can only come from counting it as it arrives.
+    let (mut sender, body) = Body::channel();
+    let sent = Arc::new(AtomicUsize::new(0));
+    let counter = sent.clone();
+    let producer = tokio::spawn(async move {
+        while sender.send_data(CHUNK.into()).await.is_ok() {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let mut req = Request::builder()
+        .header("content-type", "application/xml")
+        .body(body)
+        .unwrap();
+    req.extensions_mut()
+        .insert(XmlConfig::new().with_limit(LIMIT));
+
+    let rejection = Xml::<Value>::from_request(req, &()).await.unwrap_err();
+    assert!(matches!(rejection, XmlRejection::PayloadTooLarge));
+
+    producer.await.unwrap();
+    let chunks_to_limit = LIMIT / CHUNK.len() + 1;
+    assert!(sent.load(Ordering::SeqCst) <= chunks_to_limit + 2);
+}
+
+#[tokio::test]
+async fn honor_default_body_limit() {
+    use axum::extract::DefaultBodyLimit;
+
+    #[derive(Debug, Deserialize)]
+    struct Value {}
+
+    let app = Router::new()
+        .route(
+            "/",
+            post(|xml: Result<Xml<Value>, XmlRejection>| async move {
+                match xml {
+                    Ok(_) => "ok",
+                    Err(XmlRejection::PayloadTooLarge) => "too large",
+                    Err(_) => "other",
+                }
+            }),
+        )
+        .layer(DefaultBodyLimit::max(64));
+
+    let client = TestClient::new(app);
+
+    let res = client
+        .post("/")
+        .body("<Value></Value>")
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "ok");
+
+    let res = client
+        .post("/")
+        .body(format!("<Value>{}</Value>", " ".repeat(64)))
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "too large");
+}
+
+#[tokio::test]
+async fn raw_xml_round_trip() {
+    const DOCUMENT: &str = "<Input>\n  <!-- kept as sent -->\n  <foo a='1'>bar</foo>\n</Input>";
+
+    let app = Router::new().route("/", post(|raw: RawXml| async { raw }));
+
+    let client = TestClient::new(app);
+
+    let res = client
+        .post("/")
+        .body(DOCUMENT)
+        .header("content-type", "text/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    assert_eq!(res.text().await.unwrap(), DOCUMENT);
+
+    let res = client.post("/").body(DOCUMENT).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn xml_string_response() {
+    const DOCUMENT: &str = "<?xml version=\"1.0\"?>\n<feed>\u{e9}t\u{e9} &amp; <entry/></feed>";
+
+    let app = Router::new().route("/", get(|| async { XmlString(DOCUMENT.to_owned()) }));
+
+    let client = TestClient::new(app);
+    let res = client.get("/").send().await.unwrap();
+
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    assert_eq!(res.bytes().await.unwrap(), DOCUMENT.as_bytes());
+}
+
+#[cfg(feature = "encoding")]
+#[tokio::test]
+async fn decode_non_utf8_bodies() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    let app = Router::new().route("/", post(|input: Xml<Input>| async { input.0.foo }));
+
+    let client = TestClient::new(app);
+
+    let mut utf16 = vec![0xFF, 0xFE];
+    for unit in
+        "<?xml version=\"1.0\" encoding=\"UTF-16\"?><Input foo=\"caf\u{e9}\"/>".encode_utf16()
+    {
+        utf16.extend_from_slice(&unit.to_le_bytes());
+    }
+    let res = client
+        .post("/")
+        .body(utf16)
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "caf\u{e9}");
+
+    let latin1 =
+        b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><Input foo=\"caf\xE9\"/>".to_vec();
+    let res = client
+        .post("/")
+        .body(latin1.clone())
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "caf\u{e9}");
+
+    let res = client
+        .post("/")
+        .body(latin1[latin1.iter().position(|&b| b == b'>').unwrap() + 1..].to_vec())
+        .header("content-type", "application/xml; charset=latin1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "caf\u{e9}");
+}
+
+#[tokio::test]
+async fn reject_unsupported_encodings() {
+    #[derive(Debug, Deserialize)]
+    struct Input {}
+
+    let app = Router::new().route("/", post(|_: Xml<Input>| async {}));
+
+    let client = TestClient::new(app);
+    let res = client
+        .post("/")
+        .body(r#"<?xml version="1.0" encoding="x-no-such-encoding"?><Input/>"#)
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let res = client
+        .post("/")
+        .body(r#"<?xml version="1.0" encoding="utf-8"?><Input/>"#)
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    #[cfg(not(feature = "encoding"))]
+    {
+        let res = client
+            .post("/")
+            .body(r#"<?xml version="1.0" encoding="ISO-8859-1"?><Input/>"#)
+            .header("content-type", "application/xml")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
+
+#[tokio::test]
+async fn strip_utf8_bom() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    let app = Router::new().route("/", post(|input: Xml<Input>| async { input.0.foo }));
+
+    let client = TestClient::new(app);
+
+    let mut body = vec![0xEF, 0xBB, 0xBF];
+    body.extend_from_slice(br#"<?xml version="1.0" encoding="UTF-8"?><Input foo="bar"/>"#);
+    let res = client
+        .post("/")
+        .body(body)
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+}
+
+#[cfg(feature = "json")]
+#[tokio::test]
+async fn negotiate_xml_or_json() {
+    use http::HeaderMap;
+
+    let app = Router::new().route(
+        "/",
+        get(|headers: HeaderMap| async move { Xml::or_json(outer(), &headers) }),
+    );
+
+    let client = TestClient::new(app);
+    let send = |accept: &'static str| client.get("/").header("accept", accept).send();
+
+    let res = send("application/json").await.unwrap();
+    assert_eq!(res.headers()["content-type"], "application/json");
+    assert_eq!(
+        res.text().await.unwrap(),
+        r#"{"inner":{"name":"a"},"other":{"name":"b"}}"#
+    );
+
+    let res = send("application/xml").await.unwrap();
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    assert_eq!(res.text().await.unwrap(), OUTER_XML);
+
+    let res = send("*/*").await.unwrap();
+    assert_eq!(res.headers()["content-type"], "application/xml");
+
+    let res = send("application/xml;q=0.5, application/json")
+        .await
+        .unwrap();
+    assert_eq!(res.headers()["content-type"], "application/json");
+
+    let res = client.get("/").send().await.unwrap();
+    assert_eq!(res.headers()["content-type"], "application/xml");
+}
+
+#[cfg(feature = "json")]
+#[tokio::test]
+async fn convert_between_json_and_xml() {
+    use axum::Json;
+
+    let Xml(value) = Xml::<Outer>::from(Json(outer()));
+    assert_eq!(value, outer());
+
+    let Json(value) = Json::<Outer>::from(Xml(outer()));
+    assert_eq!(value, outer());
+
+    let app = Router::new().route(
+        "/",
+        post(|json: Json<Outer>| async { Xml::<Outer>::from(json) }),
+    );
+
+    let client = TestClient::new(app);
+    let res = client
+        .post("/")
+        .body(r#"{"inner":{"name":"a"},"other":{"name":"b"}}"#)
+        .header("content-type", "application/json")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    assert_eq!(res.text().await.unwrap(), OUTER_XML);
+}
+
+#[tokio::test]
+async fn not_acceptable_without_xml_in_accept() {
+    use http::HeaderMap;
+
+    let app = Router::new().route(
+        "/",
+        get(|headers: HeaderMap| async move { Xml::if_accepted(outer(), &headers) }),
+    );
+
+    let client = TestClient::new(app);
+    let send = |accept: &'static str| client.get("/").header("accept", accept).send();
+
+    for accept in [
+        "application/xml",
+        "text/xml",
+        "*/*",
+        "application/*",
+        "text/html, text/*;q=0.5",
+        "application/json, application/xml;q=0.1",
+        "*/*, application/xml;q=0, text/xml;q=0.5",
+    ] {
+        let res = send(accept).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{accept}");
+        assert_eq!(res.headers()["content-type"], "application/xml");
+        assert_eq!(res.text().await.unwrap(), OUTER_XML);
+    }
+
+    for accept in [
+        "application/json",
+        "text/html",
+        "*/*, application/xml;q=0, text/xml;q=0",
+        "application/xml;q=0",
+    ] {
+        let res = send(accept).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE, "{accept}");
+    }
+
+    let res = client.get("/").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let headers: HeaderMap = [(http::header::ACCEPT, "application/json".parse().unwrap())]
+        .into_iter()
+        .collect();
+    assert!(!AcceptedXml::new((), &headers).is_accepted());
+}
+
+#[tokio::test]
+async fn serialize_with_charset() {
+    let app = Router::new()
+        .route("/", get(|| async { Xml::with_charset(outer(), "utf-8") }))
+        .route(
+            "/invalid",
+            get(|| async { Xml::with_charset(outer(), "utf-8\r\nx: y") }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/").send().await.unwrap();
+    assert_eq!(
+        res.headers()["content-type"],
+        "application/xml; charset=utf-8"
+    );
+
+    let res = client.get("/invalid").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn serialize_with_content_type() {
+    let app = Router::new()
+        .route(
+            "/",
+            get(|| async { Xml::with_content_type(outer(), "text/xml") }),
+        )
+        .route(
+            "/charset",
+            get(|| async { Xml::with_content_type(outer(), "text/xml").with_charset("utf-8") }),
+        )
+        .route(
+            "/invalid",
+            get(|| async { Xml::with_content_type(outer(), "not a mime type") }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/").send().await.unwrap();
+    assert_eq!(res.headers()["content-type"], "text/xml");
+    assert_eq!(res.text().await.unwrap(), OUTER_XML);
+
+    let res = client.get("/charset").send().await.unwrap();
+    assert_eq!(res.headers()["content-type"], "text/xml; charset=utf-8");
+
+    let res = client.get("/invalid").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn decompress_request_bodies() {
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    const DOCUMENT: &[u8] = br#"<Input foo="bar"/>"#;
+
+    let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+    gzip.write_all(DOCUMENT).unwrap();
+    let gzip = gzip.finish().unwrap();
+
+    let mut deflate = ZlibEncoder::new(Vec::new(), Compression::default());
+    deflate.write_all(DOCUMENT).unwrap();
+    let deflate = deflate.finish().unwrap();
+
+    let mut bomb = GzEncoder::new(Vec::new(), Compression::best());
+    bomb.write_all(&vec![b' '; 1024 * 1024]).unwrap();
+    let bomb = bomb.finish().unwrap();
+
+    let mut large_bomb = GzEncoder::new(Vec::new(), Compression::best());
+    large_bomb
+        .write_all(&vec![b' '; XmlConfig::DEFAULT_DECOMPRESSED_LIMIT + 1])
+        .unwrap();
+    let large_bomb = large_bomb.finish().unwrap();
+
+    let handler = post(|input: Xml<Input>| async { input.0.foo });
+    let app = Router::new().route("/", handler.clone()).route(
+        "/limited",
+        handler.layer(Extension(XmlConfig::new().with_limit(4096))),
+    );
+
+    let client = TestClient::new(app);
+    let send = |url: &'static str, body: Vec<u8>, encoding: &'static str| {
+        client
+            .post(url)
+            .body(body)
+            .header("content-type", "application/xml")
+            .header("content-encoding", encoding)
+            .send()
+    };
+
+    let res = send("/", gzip, "gzip").await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+
+    let res = send("/", deflate, "deflate").await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+
+    assert!(bomb.len() < 4096);
+    let res = send("/limited", bomb, "gzip").await.unwrap();
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Without a configured limit the decompressed body is still capped.
+    assert!(large_bomb.len() < 64 * 1024);
+    let res = send("/", large_bomb, "gzip").await.unwrap();
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let res = send("/", DOCUMENT.to_vec(), "br").await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn reject_unknown_content_encoding() {
+    #[derive(Debug, Deserialize)]
+    struct Input {}
+
+    let app = Router::new().route("/", post(|_: Xml<Input>| async {}));
+
+    let client = TestClient::new(app);
+    let send = |encoding: &'static str| {
+        client
+            .post("/")
+            .body("<Input/>")
+            .header("content-type", "application/xml")
+            .header("content-encoding", encoding)
+            .send()
+    };
+
+    let res = send("x-unknown").await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let res = send("identity").await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[test]
+fn xml_into_inner_and_as_ref() {
+    let mut xml = Xml(String::from("value"));
+
+    xml.as_mut().push('s');
+    assert_eq!(xml.as_ref(), "values");
+    assert_eq!(xml.into_inner(), "values");
+}
+
+#[tokio::test]
+async fn round_trip_through_backend() {
+    let app = Router::new()
+        .route("/", get(|| async { Xml(outer()) }))
+        .route(
+            "/pretty",
+            get(|| async { Xml::pretty(outer()).with_declaration() }),
+        )
+        .route("/echo", post(|Xml(outer): Xml<Outer>| async { Xml(outer) }));
+
+    let client = TestClient::new(app);
+
+    for url in ["/", "/pretty"] {
+        let body = client.get(url).send().await.unwrap().text().await.unwrap();
+        let res = client
+            .post("/echo")
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await.unwrap(), OUTER_XML);
+    }
+
+    let value: Outer =
+        crate::de::deserialize(OUTER_XML.as_bytes(), None, &XmlConfig::new()).unwrap();
+    assert_eq!(value, outer());
+}
+
+#[tokio::test]
+async fn reject_empty_bodies() {
+    #[derive(Deserialize)]
+    struct Input {}
+
+    let app = Router::new().route("/", post(|_: Xml<Input>| async {}));
+
+    let client = TestClient::new(app);
+
+    for body in ["", " \r\n\t "] {
+        let res = client
+            .post("/")
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert!(res
+            .text()
+            .await
+            .unwrap()
+            .contains("The request body is empty"));
+    }
+}
+
+#[tokio::test]
+async fn reject_bodiless_methods_as_empty() {
+    #[derive(Debug, Deserialize)]
+    struct Input {}
+
+    let app = Router::new().route("/", get(|_: Xml<Input>| async {}));
+
+    let client = TestClient::new(app);
+
+    let res = client.head("/").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = client.get("/").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("The request body is empty"));
+
+    for method in [http::Method::HEAD, http::Method::GET] {
+        let req = Request::builder()
+            .method(method)
+            .body(Body::empty())
+            .unwrap();
+        let rejection = Xml::<Input>::from_request(req, &()).await.unwrap_err();
+        assert!(matches!(rejection, XmlRejection::EmptyBody));
+    }
+
+    // A body without a `Content-Type` is still the wrong type, whatever the method.
+    let res = client.get("/").body("<Input/>").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn reject_entity_expansion() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    const LAUGHS: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE Input [
+  <!ENTITY lol "lol">
+  <!ENTITY lol1 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+  <!ENTITY lol2 "&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;">
+  <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
+  <!ENTITY lol4 "&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;">
+  <!ENTITY lol5 "&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;">
+  <!ENTITY lol6 "&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;">
+  <!ENTITY lol7 "&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;">
+  <!ENTITY lol8 "&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;">
+  <!ENTITY lol9 "&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;">
+]>
+<Input><foo>&lol9;</foo></Input>"#;
+    const RECURSIVE: &str = r#"<!DOCTYPE Input [ <!ENTITY a "&b;"> <!ENTITY b "&a;"> ]>
+<Input><foo>&a;</foo></Input>"#;
+    const REPEATED: &str = r#"<!DOCTYPE Input [ <!ENTITY ten "0123456789"> ]>
+<Input><foo>&ten;&ten;&ten;&ten;</foo></Input>"#;
+
+    let handler = post(|input: Xml<Input>| async { input.0.foo });
+    let config = XmlConfig::new().forbid_doctype(false);
+    let app = Router::new()
+        .route("/", handler.clone().layer(Extension(config.clone())))
+        .route(
+            "/small",
+            handler.layer(Extension(config.with_entity_limit(32, 8))),
+        );
+
+    let client = TestClient::new(app);
+    let send = |url: &'static str, body: &'static str| {
+        client
+            .post(url)
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let started = std::time::Instant::now();
+    let res = send("/", LAUGHS).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+    let res = send("/", RECURSIVE).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = send("/", REPEATED).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "0123456789".repeat(4));
+
+    let res = send("/small", REPEATED).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[cfg(feature = "backend-quick-xml")]
+#[tokio::test]
+async fn borrowed_xml_avoids_allocations() {
+    #[derive(Deserialize)]
+    struct Owned {
+        item: Vec<OwnedItem>,
+    }
+
+    #[derive(Deserialize)]
+    struct OwnedItem {
+        name: String,
+    }
+
+    #[derive(Deserialize)]
+    struct Borrowed<'a> {
+        #[serde(borrow)]
+        item: Vec<BorrowedItem<'a>>,
+    }
+
+    #[derive(Deserialize)]
+    struct BorrowedItem<'a> {
+        name: &'a str,
+    }
+
+    let items: String = (0..1000)
+        .map(|index| format!(r#"<item name="item {}"/>"#, index))
+        .collect();
+    let document = format!("<Document>{}</Document>", items);
+
+    let req = Request::builder()
+        .header("content-type", "application/xml")
+        .body(Body::from(document.clone()))
+        .unwrap();
+    let body = BorrowedXml::from_request(req, &()).await.unwrap();
+
+    let (borrowed, borrowed_allocations) = count_allocations(|| body.deserialize::<Borrowed>());
+    let borrowed = borrowed.unwrap();
+    assert_eq!(borrowed.item.len(), 1000);
+    assert_eq!(borrowed.item[999].name, "item 999");
+
+    let (owned, owned_allocations) = count_allocations(|| {
+        crate::de::deserialize::<Owned>(document.as_bytes(), None, &XmlConfig::new())
+    });
+    assert_eq!(owned.unwrap().item[999].name, "item 999");
+
+    println!("owned: {owned_allocations} allocations, borrowed: {borrowed_allocations}");
+    assert!(borrowed_allocations + 1000 <= owned_allocations);
+}
+
+#[tokio::test]
+async fn borrowed_xml_extractor() {
+    use std::borrow::Cow;
+
+    #[derive(Deserialize)]
+    struct Input<'a> {
+        #[serde(borrow)]
+        foo: Cow<'a, str>,
+    }
+
+    let app = Router::new().route(
+        "/",
+        post(|body: BorrowedXml| async move {
+            let input: Input<'_> = body.deserialize().unwrap();
+            input.foo.into_owned()
+        }),
+    );
+
+    let client = TestClient::new(app);
+
+    let res = client
+        .post("/")
+        .body("<Input><foo>fish &amp; chips</foo></Input>")
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "fish & chips");
+
+    let res = client.post("/").body("<Input/>").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn borrowed_xml_applies_config() {
+    use std::borrow::Cow;
+
+    #[derive(Debug, Deserialize)]
+    struct Input<'a> {
+        #[serde(borrow)]
+        #[allow(dead_code)]
+        foo: Cow<'a, str>,
+        #[allow(dead_code)]
+        count: u32,
+    }
+
+    let extract = |body: &'static str, config: XmlConfig| async move {
+        let req = Request::builder()
+            .header("content-type", "application/xml")
+            .extension(config)
+            .body(Body::from(body))
+            .unwrap();
+        BorrowedXml::from_request(req, &()).await.unwrap()
+    };
+
+    let config = XmlConfig::new().with_deny_unknown_elements(true);
+    let body = extract("<Input><foo>a</foo><count>1</count><bar/></Input>", config).await;
+    let rejection = body.deserialize::<Input<'_>>().unwrap_err();
+    assert!(matches!(rejection, XmlRejection::UnknownElement { .. }));
+
+    let body = extract(
+        "<Input>\n  <foo>a</foo>\n  <count>many</count>\n</Input>",
+        XmlConfig::new(),
+    )
+    .await;
+    let rejection = body.deserialize::<Input<'_>>().unwrap_err();
+    assert_eq!(rejection.line_column().map(|(line, _)| line), Some(3));
+
+    // The comment is removed before parsing, which moves everything after it.
+    let body = extract(
+        "<Input><!-- note --><foo>a</foo><count>many</count></Input>",
+        XmlConfig::new(),
+    )
+    .await;
+    let rejection = body.deserialize::<Input<'_>>().unwrap_err();
+    assert!(matches!(rejection, XmlRejection::InvalidXMLBody { .. }));
+    assert_eq!(rejection.position(), None);
+}
+
+#[tokio::test]
+async fn borrowed_xml_cow_fields() {
+    use std::borrow::Cow;
+
+    #[derive(Deserialize)]
+    struct Dish<'a> {
+        #[serde(borrow)]
+        plain: Cow<'a, str>,
+        #[serde(borrow)]
+        escaped: Cow<'a, str>,
+        owned: Cow<'a, str>,
+    }
+
+    let req = Request::builder()
+        .header("content-type", "application/xml")
+        .body(Body::from(
+            "<Dish><plain>chips</plain><escaped>fish &amp; chips</escaped><owned>peas</owned></Dish>",
+        ))
+        .unwrap();
+    let body = BorrowedXml::from_request(req, &()).await.unwrap();
+    let dish: Dish<'_> = body.deserialize().unwrap();
+
+    assert_eq!(dish.plain, "chips");
+    assert_eq!(dish.escaped, "fish & chips");
+    assert_eq!(dish.owned, "peas");
+
+    #[cfg(feature = "backend-quick-xml")]
+    assert!(matches!(dish.plain, Cow::Borrowed(_)));
+    #[cfg(feature = "backend-serde-xml-rs")]
+    assert!(matches!(dish.plain, Cow::Owned(_)));
+    assert!(matches!(dish.escaped, Cow::Owned(_)));
+    assert!(matches!(dish.owned, Cow::Owned(_)));
+
+    #[cfg(feature = "backend-quick-xml")]
+    {
+        let plain = dish.plain.as_ptr() as usize;
+        let body_range = body.as_bytes().as_ptr_range();
+        assert!((body_range.start as usize..body_range.end as usize).contains(&plain));
+    }
+}
+
+#[cfg(feature = "streaming")]
+#[tokio::test]
+async fn streaming_xml() {
+    use crate::StreamingXml;
+
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    let app = Router::new()
+        .route(
+            "/",
+            post(|StreamingXml(input): StreamingXml<Input>| async { input.foo }),
+        )
+        .layer(Extension(XmlConfig::new().with_limit(64)));
+
+    let client = TestClient::new(app);
+    let send = |body: &'static str, content_type: &'static str| {
+        client
+            .post("/")
+            .body(body)
+            .header("content-type", content_type)
+            .send()
+    };
+
+    let res = send("<Input><foo>bar</foo></Input>", "application/xml")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+
+    let res = send("<Input><foo>bar</foo></Input>", "text/plain")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let res = send("  ", "application/xml").await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let oversized = "<Input><foo>0123456789012345678901234567890123456789</foo></Input>";
+    let res = send(oversized, "application/xml").await.unwrap();
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[cfg(feature = "streaming")]
+#[tokio::test]
+async fn streaming_xml_bounded_memory() {
+    use crate::StreamingXml;
+    use bytes::Bytes;
+    use serde::de::IgnoredAny;
+
+    #[derive(Deserialize)]
+    struct Import {
+        record: Vec<IgnoredAny>,
+    }
+
+    const RECORD: &str = r#"<record id="1234567890" name="a record in a large import"/>"#;
+    const RECORDS_PER_CHUNK: usize = 1024;
+    const CHUNKS: usize = 256;
+
+    // The chunks share one buffer, so the document only takes up memory if it is collected.
+    let chunk = Bytes::from(RECORD.repeat(RECORDS_PER_CHUNK));
+    let document_len = chunk.len() * CHUNKS;
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let _ = sender.send_data("<Import>".into()).await;
+        for _ in 0..CHUNKS {
+            if sender.send_data(chunk.clone()).await.is_err() {
+                return;
+            }
+        }
+        let _ = sender.send_data("</Import>".into()).await;
+    });
+
+    let req = Request::builder()
+        .header("content-type", "application/xml")
+        .body(body)
+        .unwrap();
+
+    let (import, peak) = peak_memory(StreamingXml::<Import>::from_request(req, &())).await;
+    assert_eq!(import.unwrap().record.len(), RECORDS_PER_CHUNK * CHUNKS);
+    assert!(
+        peak < document_len / 4,
+        "peak of {} bytes for a {} byte document",
+        peak,
+        document_len
+    );
+}
+
+/// Request whose body is `document` sent in pieces of `chunk_len` bytes, with `config`.
+#[cfg(feature = "streaming")]
+fn chunked_request(document: &str, chunk_len: usize, config: XmlConfig) -> Request<Body> {
+    let chunks: Vec<bytes::Bytes> = document
+        .as_bytes()
+        .chunks(chunk_len)
+        .map(bytes::Bytes::copy_from_slice)
+        .collect();
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        for chunk in chunks {
+            if sender.send_data(chunk).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    Request::builder()
+        .header("content-type", "application/xml")
+        .extension(config)
+        .body(body)
+        .unwrap()
+}
+
+#[cfg(feature = "streaming")]
+#[tokio::test]
+async fn streaming_xml_buffer_sizes() {
+    use crate::StreamingXml;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Import {
+        record: Vec<Record>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Record {
+        id: u64,
+        name: String,
+    }
+
+    let records: String = (0..500)
+        .map(|id| format!("<record><id>{}</id><name>record {}</name></record>", id, id))
+        .collect();
+    let document = format!("<Import>{}</Import>", records);
+
+    let mut imports = Vec::new();
+    for buffer_size in [1, 7, 64, 8 * 1024, 1024 * 1024] {
+        let config = XmlConfig::new().with_stream_buffer_size(buffer_size);
+        let req = chunked_request(&document, 5, config);
+        let StreamingXml(import) = StreamingXml::<Import>::from_request(req, &())
+            .await
+            .unwrap();
+        imports.push(import);
+    }
+
+    assert_eq!(imports[0].record.len(), 500);
+    assert_eq!(imports[0].record[42].name, "record 42");
+    assert!(imports.iter().all(|import| *import == imports[0]));
+
+    // A size past the end of the document still delivers the tail of it.
+    let config = XmlConfig::new().with_stream_buffer_size(1024 * 1024);
+    let req = chunked_request(&format!("<Import>{}</Import>", &records[..48]), 3, config);
+    let StreamingXml(import) = StreamingXml::<Import>::from_request(req, &())
+        .await
+        .unwrap();
+    assert_eq!(
+        import.record,
+        [Record {
+            id: 0,
+            name: "record 0".into()
+        }]
+    );
+}
+
+#[cfg(feature = "streaming")]
+#[tokio::test]
+async fn streaming_xml_limits() {
+    use crate::StreamingXml;
+
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    let extract = |document: String, config: XmlConfig| async move {
+        StreamingXml::<Input>::from_request(chunked_request(&document, 7, config), &())
+            .await
+            .map(|StreamingXml(input)| input.foo)
+    };
+
+    let deep = format!("{}{}", "<Input>".repeat(5000), "</Input>".repeat(5000));
+    let rejection = extract(deep, XmlConfig::new()).await.unwrap_err();
+    assert!(matches!(rejection, XmlRejection::DepthLimitExceeded));
+
+    let nested = "<Input><foo>bar</foo><extra><a/></extra></Input>".to_owned();
+    let rejection = extract(nested.clone(), XmlConfig::new().with_max_depth(2))
+        .await
+        .unwrap_err();
+    assert!(matches!(rejection, XmlRejection::DepthLimitExceeded));
+    let foo = extract(nested, XmlConfig::new().with_max_depth(3)).await;
+    assert_eq!(foo.unwrap(), "bar");
+
+    let doctype = r#"<!DOCTYPE Input [<!ENTITY x SYSTEM "file:///etc/passwd">]><Input><foo>bar</foo></Input>"#;
+    let rejection = extract(doctype.to_owned(), XmlConfig::new())
+        .await
+        .unwrap_err();
+    assert!(matches!(rejection, XmlRejection::ForbiddenDoctype));
+    let rejection = extract(doctype.to_owned(), XmlConfig::new().forbid_doctype(false))
+        .await
+        .unwrap_err();
+    assert!(matches!(rejection, XmlRejection::ForbiddenDoctype));
+
+    let internal = "<!DOCTYPE Input []><Input><foo>bar</foo></Input>".to_owned();
+    let foo = extract(internal, XmlConfig::new().forbid_doctype(false)).await;
+    assert_eq!(foo.unwrap(), "bar");
+}
+
+#[cfg(feature = "streaming")]
+#[test]
+#[should_panic(expected = "must not be zero")]
+fn zero_stream_buffer_size() {
+    let _ = XmlConfig::new().with_stream_buffer_size(0);
+}
+
+// Not a real benchmark, but shows what collecting small chunks saves; run with
+// `cargo test --release --features streaming stream_buffer_throughput -- --nocapture`.
+#[cfg(feature = "streaming")]
+#[tokio::test]
+async fn stream_buffer_throughput() {
+    use crate::StreamingXml;
+    use serde::de::IgnoredAny;
+    use std::time::Instant;
+
+    #[derive(Deserialize)]
+    struct Import {
+        record: Vec<IgnoredAny>,
+    }
+
+    let records = r#"<record id="1234567890" name="a record in a large import"/>"#.repeat(20_000);
+    let document = format!("<Import>{}</Import>", records);
+
+    for buffer_size in [1, 1024, XmlConfig::DEFAULT_STREAM_BUFFER_SIZE, 64 * 1024] {
+        let config = XmlConfig::new().with_stream_buffer_size(buffer_size);
+        let req = chunked_request(&document, 64, config);
+
+        let start = Instant::now();
+        let StreamingXml(import) = StreamingXml::<Import>::from_request(req, &())
+            .await
+            .unwrap();
+        let elapsed = start.elapsed();
+
+        assert_eq!(import.record.len(), 20_000);
+        let mib_per_sec = document.len() as f64 / elapsed.as_secs_f64() / (1024.0 * 1024.0);
+        println!("buffer size {buffer_size}: {elapsed:?}, {mib_per_sec:.1} MiB/s");
+    }
+}
+
+// serde-xml-rs can't serialize sequences inside structs.
+#[cfg(feature = "backend-quick-xml")]
+#[test]
+fn serialize_with_capacity_hint() {
+    use axum::response::IntoResponse;
+
+    #[derive(Serialize)]
+    struct List {
+        item: Vec<Inner>,
+    }
+
+    let list = || List {
+        item: (0..10_000)
+            .map(|index| Inner {
+                name: format!("item {}", index),
+            })
+            .collect(),
+    };
+
+    let (plain, plain_allocations) = count_allocations(|| Xml(list()).into_response());
+    let (hinted, hinted_allocations) =
+        count_allocations(|| Xml::with_capacity_hint(list(), 512 * 1024).into_response());
+
+    assert_eq!(plain.status(), StatusCode::OK);
+    assert_eq!(hinted.status(), StatusCode::OK);
+    assert!(
+        hinted_allocations < plain_allocations,
+        "{} allocations with the hint, {} without",
+        hinted_allocations,
+        plain_allocations
+    );
+}
+
+#[tokio::test]
+async fn duplicate_content_type_headers() {
+    #[derive(Deserialize)]
+    struct Value {}
+
+    let app = Router::new().route("/", post(|Xml(_): Xml<Value>| async {}));
+
+    let client = TestClient::new(app);
+    let send = |first: &'static str, second: &'static str| {
+        client
+            .post("/")
+            .header("content-type", first)
+            .header("content-type", second)
+            .body("<Value/>")
+            .send()
+    };
+
+    let res = send("application/xml", "text/xml").await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = send("application/xml", "application/json").await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let res = send("application/json", "application/xml").await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn response_builder() {
+    let app = Router::new()
+        .route(
+            "/",
+            get(|| async {
+                Xml::builder(outer())
+                    .status(StatusCode::CREATED)
+                    .header("x-foo", "bar")
+                    .header("x-foo", "baz")
+                    .pretty()
+            }),
+        )
+        .route(
+            "/invalid",
+            get(|| async { Xml::builder(outer()).header("x-foo", "bar\r\n") }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    let values: Vec<_> = res.headers().get_all("x-foo").iter().collect();
+    assert_eq!(values, ["bar", "baz"]);
+    assert_eq!(res.text().await.unwrap(), OUTER_PRETTY_XML);
+
+    let res = client.get("/invalid").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[test]
+fn xml_compares_like_inner() {
+    use std::collections::HashSet;
+
+    assert_eq!(Xml("a"), Xml("a"));
+    assert_ne!(Xml("a"), Xml("b"));
+    assert!(Xml(1) < Xml(2));
+    assert_eq!(Xml(2).max(Xml(1)), Xml(2));
+
+    let set: HashSet<_> = [Xml("a"), Xml("a"), Xml("b")].into_iter().collect();
+    assert_eq!(set.len(), 2);
+}
+
+#[tokio::test]
+async fn serialize_with_expand_empty_elements() {
+    #[derive(Serialize)]
+    struct Note {
+        body: Body,
+    }
+
+    #[derive(Serialize)]
+    struct Body {
+        #[serde(rename = "$value")]
+        text: String,
+    }
+
+    let note = || Note {
+        body: Body {
+            text: String::new(),
+        },
+    };
+    let app = Router::new()
+        .route("/", get(move || async move { Xml(note()) }))
+        .route(
+            "/expanded",
+            get(move || async move { Xml::with_expand_empty_elements(note(), true) }),
+        )
+        .route(
+            "/collapsed",
+            get(move || async move { Xml::with_expand_empty_elements(note(), false).pretty() }),
+        );
+
+    let client = TestClient::new(app);
+    let get = |url: &'static str| client.get(url).send();
+
+    let res = get("/expanded").await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "<Note><body></body></Note>");
+
+    let res = get("/collapsed").await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "<Note>\n\t<body/>\n</Note>");
+
+    #[cfg(feature = "backend-quick-xml")]
+    assert_eq!(
+        get("/").await.unwrap().text().await.unwrap(),
+        "<Note><body/></Note>"
+    );
+}
+
+#[tokio::test]
+async fn serialize_with_namespace() {
+    let app = Router::new()
+        .route(
+            "/",
+            get(|| async { Xml::with_namespace(outer(), "http://example.com/ns") }),
+        )
+        .route(
+            "/prefixed",
+            get(|| async {
+                Xml::with_root_name(outer(), "ex:Outer")
+                    .with_namespace("http://example.com/default")
+                    .with_prefixed_namespace("ex", "http://example.com/ns")
+            }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/").send().await.unwrap();
+    assert_eq!(
+        res.text().await.unwrap(),
+        OUTER_XML.replacen("<Outer>", r#"<Outer xmlns="http://example.com/ns">"#, 1)
+    );
+
+    let res = client.get("/prefixed").send().await.unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.starts_with(
+        r#"<ex:Outer xmlns="http://example.com/default" xmlns:ex="http://example.com/ns">"#
+    ));
+    assert!(body.ends_with("</ex:Outer>"));
+    assert_eq!(body.matches("xmlns").count(), 2);
+}
+
+#[tokio::test]
+async fn forbid_doctype_by_default() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    const PLAIN: &str = r#"<?xml version="1.0"?><Input foo="bar"/>"#;
+    const DOCTYPE: &str = r#"<?xml version="1.0"?><!DOCTYPE Input><Input foo="bar"/>"#;
+    const DECLARED: &str = r#"<!DOCTYPE Input [<!ENTITY b "b"> <!ENTITY bar "&b;&amp;ar">]>
+<Input foo="&bar;"/>"#;
+
+    let handler = post(|input: Xml<Input>| async { input.0.foo });
+    let app = Router::new().route("/", handler.clone()).route(
+        "/legacy",
+        handler.layer(Extension(XmlConfig::new().forbid_doctype(false))),
+    );
+
+    let client = TestClient::new(app);
+    let send = |url: &'static str, body: &'static str| {
+        client
+            .post(url)
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send("/", PLAIN).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = send("/", DOCTYPE).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = send("/legacy", DOCTYPE).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+
+    let res = send("/", DECLARED).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = send("/legacy", DECLARED).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "b&ar");
+}
+
+#[cfg(feature = "utoipa")]
+#[test]
+fn openapi_lists_xml_content() {
+    use utoipa::openapi::{RefOr, Schema};
+    use utoipa::{OpenApi, ToSchema};
+
+    #[derive(Serialize, Deserialize, ToSchema)]
+    struct User {
+        name: String,
+    }
+
+    #[utoipa::path(
+        post,
+        path = "/users",
+        request_body(content = User, content_type = "application/xml"),
+        responses(Xml<User>)
+    )]
+    #[allow(dead_code)]
+    async fn create_user(Xml(user): Xml<User>) -> Xml<User> {
+        Xml(user)
+    }
+
+    #[derive(OpenApi)]
+    #[openapi(paths(create_user))]
+    struct ApiDoc;
+
+    let doc = ApiDoc::openapi();
+    let operation = doc.paths.paths["/users"].post.as_ref().unwrap();
+
+    let response = match &operation.responses.responses["200"] {
+        RefOr::T(response) => response,
+        RefOr::Ref(_) => panic!("expected an inline response"),
+    };
+    let schema = response.content["application/xml"].schema.as_ref().unwrap();
+    assert!(
+        matches!(schema, RefOr::T(Schema::Object(object)) if object.properties.contains_key("name"))
+    );
+
+    let request = operation.request_body.as_ref().unwrap();
+    assert!(request.content.contains_key("application/xml"));
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn tracing_spans_and_events() {
+    use axum::response::IntoResponse;
+    use std::fmt::{self, Write};
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::{span, Event, Metadata, Subscriber};
+
+    /// Subscriber writing every span, recorded value and event it sees as a line of text.
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<String>>>);
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            write!(self.0, " {}={:?}", field.name(), value).unwrap();
+        }
+    }
+
+    impl Capture {
+        fn push(&self, line: Fields) -> u64 {
+            let mut lines = self.0.lock().unwrap();
+            lines.push(line.0);
+            lines.len() as u64
+        }
+
+        fn lines(&self) -> Vec<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            let mut fields = Fields(format!("span {}", span.metadata().name()));
+            span.record(&mut fields);
+            span::Id::from_u64(self.push(fields))
+        }
+
+        fn record(&self, _: &span::Id, values: &span::Record<'_>) {
+            let mut fields = Fields("record".to_owned());
+            values.record(&mut fields);
+            self.push(fields);
+        }
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields(format!("event {}", event.metadata().level()));
+            event.record(&mut fields);
+            self.push(fields);
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        #[allow(dead_code)]
+        foo: String,
+    }
+
+    let capture = Capture::default();
+    let _guard = tracing::subscriber::set_default(capture.clone());
+
+    let request = |body: &'static str| {
+        Request::builder()
+            .header("content-type", "application/xml")
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    Xml::<Input>::from_request(request(r#"<Input foo="bar"/>"#), &())
+        .await
+        .unwrap();
+    let rejection = Xml::<Input>::from_request(request("<Input>"), &())
+        .await
+        .unwrap_err();
+    let _ = rejection.into_response();
+    let _ = Xml::with_charset(outer(), "not a token").into_response();
+
+    let lines = capture.lines();
+    assert_eq!(
+        lines[..4],
+        [
+            "span xml.deserialize body_len=18",
+            "record outcome=\"ok\"",
+            "span xml.deserialize body_len=7",
+            "record outcome=\"rejected\"",
+        ]
+    );
+    assert!(lines[4].starts_with("event DEBUG message=rejected request: Failed to parse"));
+    assert!(lines[4].ends_with(r#" rejection="InvalidXMLBody" position=7"#));
+    assert!(lines[5].starts_with("event WARN message=failed to serialize XML response"));
+    assert_eq!(lines.len(), 6);
+}
+
+#[cfg(feature = "validator")]
+#[tokio::test]
+async fn validated_xml() {
+    use crate::ValidatedXml;
+    use validator::Validate;
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct Input {
+        #[validate(length(min = 3))]
+        foo: String,
+    }
+
+    let app = Router::new().route(
+        "/",
+        post(|ValidatedXml(input): ValidatedXml<Input>| async { input.foo }),
+    );
+
+    let client = TestClient::new(app);
+    let send = |body: &'static str| {
+        client
+            .post("/")
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send(r#"<Input foo="long enough"/>"#).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "long enough");
+
+    let res = send(r#"<Input foo="no"/>"#).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("The request body is invalid"));
+    assert!(body.contains("foo"));
+
+    let res = send("<Input>").await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res.text().await.unwrap().contains("Failed to parse"));
+}
+
+#[tokio::test]
+async fn trim_or_preserve_text() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        foo: String,
+        bar: String,
+    }
+
+    const INDENTED: &str = "<Input>\n  <foo>\n    a &amp; b\n  </foo>\n  <bar>  </bar>\n</Input>";
+
+    let handler =
+        post(|Xml(input): Xml<Input>| async move { format!("{:?}|{:?}", input.foo, input.bar) });
+    let app = Router::new().route("/", handler.clone()).route(
+        "/preserve",
+        handler.layer(Extension(XmlConfig::new().with_trim_text(false))),
+    );
+
+    let client = TestClient::new(app);
+    let send = |url: &'static str| {
+        client
+            .post(url)
+            .body(INDENTED)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send("/").await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), r#""a & b"|"""#);
+
+    #[cfg(feature = "backend-quick-xml")]
+    {
+        let res = send("/preserve").await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await.unwrap(), r#""\n    a & b\n  "|"  ""#);
+    }
+}
+
+#[tokio::test]
+async fn serialize_cdata() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Snippet {
+        code: Cdata,
+    }
+
+    #[derive(Serialize)]
+    struct Escaped {
+        code: String,
+    }
+
+    const CODE: &str = "if a < b && c { x[y[0]]> }";
+
+    let app = Router::new()
+        .route(
+            "/escaped",
+            get(|| async {
+                Xml(Escaped {
+                    code: CODE.to_owned(),
+                })
+            }),
+        )
+        .route(
+            "/cdata",
+            get(|| async {
+                Xml(Snippet {
+                    code: Cdata::from(CODE),
+                })
+            }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/escaped").send().await.unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.contains("if a &lt; b &amp;&amp; c { x[y[0]]&gt; }"));
+    assert!(!body.contains("CDATA"));
+
+    let res = client.get("/cdata").send().await.unwrap();
+    assert_eq!(
+        res.text().await.unwrap(),
+        "<Snippet><code><![CDATA[if a < b && c { x[y[0]]]]><![CDATA[> }]]></code></Snippet>"
+    );
+}
+
+#[tokio::test]
+async fn rejection_codes() {
+    use axum::body::Bytes;
+    use http_body::Limited;
+
+    let bytes_rejection =
+        Bytes::from_request(Request::new(Limited::new(Body::from("abc"), 1)), &())
+            .await
+            .unwrap_err();
+    let invalid = Xml::<Outer>::from_request(
+        Request::builder()
+            .header("content-type", "application/xml")
+            .body(Body::from("<Outer>"))
+            .unwrap(),
+        &(),
+    )
+    .await
+    .unwrap_err();
+
+    let cases = [
+        (invalid, "xml.parse.invalid"),
+        (
+            XmlRejection::MissingXMLContentType {
+                status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            },
+            "xml.content_type.missing",
+        ),
+        (XmlRejection::InvalidContentType, "xml.content_type.invalid"),
+        (XmlRejection::EmptyBody, "xml.body.empty"),
+        (XmlRejection::ForbiddenDoctype, "xml.doctype.forbidden"),
+        (XmlRejection::DepthLimitExceeded, "xml.limit.depth"),
+        (
+            XmlRejection::EntityExpansionLimit,
+            "xml.limit.entity_expansion",
+        ),
+        (XmlRejection::PayloadTooLarge, "xml.body.too_large"),
+        (
+            XmlRejection::UnsupportedEncoding,
+            "xml.encoding.unsupported",
+        ),
+        (
+            XmlRejection::UnsupportedContentEncoding,
+            "xml.content_encoding.unsupported",
+        ),
+        (
+            XmlRejection::InvalidCompressedBody,
+            "xml.content_encoding.invalid",
+        ),
+        (
+            XmlRejection::FailedToReadBody("boom".into()),
+            "xml.body.read_failed",
+        ),
+        (bytes_rejection.into(), "xml.body.rejected"),
+    ];
+    for (rejection, code) in cases {
+        assert_eq!(rejection.code(), code, "{:?}", rejection);
+    }
+
+    #[cfg(feature = "validator")]
+    assert_eq!(
+        XmlRejection::Validation(validator::ValidationErrors::new()).code(),
+        "xml.validation.failed"
+    );
+}
+
+#[tokio::test]
+async fn parse_error_source() {
+    use std::error::Error;
+
+    let rejection = Xml::<Outer>::from_request(
+        Request::builder()
+            .header("content-type", "application/xml")
+            .body(Body::from("<Outer><inner>"))
+            .unwrap(),
+        &(),
+    )
+    .await
+    .unwrap_err();
+
+    let parse_error = rejection.as_parse_error().unwrap();
+    let source = rejection.source().unwrap();
+    assert_eq!(source.to_string(), parse_error.to_string());
+
+    #[cfg(feature = "backend-quick-xml")]
+    assert!(matches!(
+        source.downcast_ref::<quick_xml::DeError>(),
+        Some(quick_xml::DeError::UnexpectedEof)
+    ));
+    #[cfg(feature = "backend-serde-xml-rs")]
+    assert!(source.downcast_ref::<serde_xml_rs::Error>().is_some());
+
+    assert!(XmlRejection::EmptyBody.as_parse_error().is_none());
+    assert!(XmlRejection::EmptyBody.source().is_none());
+}
+
+#[tokio::test]
+async fn content_type_status() {
+    let handler = post(|Xml(_): Xml<Outer>| async {});
+    let app = Router::new().route("/", handler.clone()).route(
+        "/bad-request",
+        handler.layer(Extension(
+            XmlConfig::new().with_content_type_status(StatusCode::BAD_REQUEST),
+        )),
+    );
+
+    let client = TestClient::new(app);
+    let send = |url: &'static str| {
+        client
+            .post(url)
+            .body(OUTER_XML)
+            .header("content-type", "text/plain")
+            .send()
+    };
+
+    let res = send("/").await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let res = send("/bad-request").await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("Expected request with `Content-Type: application/xml`"));
+}
+
+#[test]
+fn from_bytes_and_to_bytes() {
+    let Xml(outer) = Xml::<Outer>::from_bytes(OUTER_XML.as_bytes()).unwrap();
+    assert_eq!(outer, self::outer());
+    assert_eq!(Xml::to_bytes(&outer).unwrap(), OUTER_XML.as_bytes());
+
+    assert!(matches!(
+        Xml::<Outer>::from_bytes(b"  "),
+        Err(XmlRejection::EmptyBody)
+    ));
+    assert!(matches!(
+        Xml::<Outer>::from_bytes(b"<Outer>"),
+        Err(XmlRejection::InvalidXMLBody { .. })
+    ));
+    assert!(matches!(
+        Xml::<Outer>::from_bytes(b"<!DOCTYPE Outer><Outer/>"),
+        Err(XmlRejection::ForbiddenDoctype)
+    ));
+
+    let config = XmlConfig::new().with_limit(8);
+    assert!(matches!(
+        Xml::<Outer>::from_bytes_with_config(OUTER_XML.as_bytes(), &config),
+        Err(XmlRejection::PayloadTooLarge)
+    ));
+}
+
+#[test]
+fn skip_or_forbid_comments() {
+    const INTERLEAVED: &str =
+        "<?xml version=\"1.0\"?>\n<!-- export -->\n<?xml-stylesheet href=\"s.xsl\"?>\n\
+        <Outer>\n  <!-- first -->\n  <inner><name>a<!-- - -->b</name></inner>\n  <?pi?>\n  \
+        <other><name><!-- x -->c<?pi?></name></other>\n</Outer>\n<!-- end -->\n";
+
+    let Xml(outer) = Xml::<Outer>::from_bytes(INTERLEAVED.as_bytes()).unwrap();
+    assert_eq!(
+        outer,
+        Outer {
+            inner: Inner {
+                name: "ab".to_owned()
+            },
+            other: Inner {
+                name: "c".to_owned()
+            },
+        }
+    );
+
+    let strict = XmlConfig::new().forbid_comments(true);
+    assert!(matches!(
+        Xml::<Outer>::from_bytes_with_config(INTERLEAVED.as_bytes(), &strict),
+        Err(XmlRejection::InvalidXMLBody { .. })
+    ));
+    assert!(Xml::<Outer>::from_bytes_with_config(OUTER_XML.as_bytes(), &strict).is_ok());
+}
+
+#[tokio::test]
+async fn attributes_round_trip() {
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Item {
+        #[serde(rename = "@id")]
+        id: u32,
+        #[serde(rename = "@lang")]
+        lang: String,
+        #[serde(rename = "$value")]
+        title: String,
+    }
+
+    fn item() -> Item {
+        Item {
+            id: 7,
+            lang: "en & fr".to_owned(),
+            title: "Hello".to_owned(),
+        }
+    }
+
+    #[cfg(feature = "backend-quick-xml")]
+    const ITEM_XML: &str = r#"<Item id="7" lang="en &amp; fr">Hello</Item>"#;
+
+    let app = Router::new()
+        .route("/", get(|| async { Xml(item()) }))
+        .route(
+            "/echo",
+            post(|Xml(item): Xml<Item>| async move { Xml(item) }),
+        )
+        .layer(Extension(XmlConfig::new().with_attribute_prefix(true)));
+
+    let client = TestClient::new(app);
+
+    // serde-xml-rs doesn't keep the order of the attributes.
+    let res = client.get("/").send().await.unwrap();
+    let body = res.text().await.unwrap();
+    assert!(body.starts_with("<Item "));
+    assert!(body.contains(r#" id="7""#));
+    assert!(body.contains(r#" lang="en &amp; fr""#));
+    assert!(body.ends_with(">Hello</Item>"));
+
+    #[cfg(feature = "backend-quick-xml")]
+    {
+        let res = client
+            .post("/echo")
+            .body(ITEM_XML)
+            .header("content-type", "application/xml")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await.unwrap(), ITEM_XML);
+
+        let config = XmlConfig::new().with_attribute_prefix(true);
+        let Xml(parsed) =
+            Xml::<Item>::from_bytes_with_config(ITEM_XML.as_bytes(), &config).unwrap();
+        assert_eq!(parsed, item());
+    }
+}
+
+#[test]
+fn try_into_bytes() {
+    struct Broken;
+
+    impl Serialize for Broken {
+        fn serialize<S>(&self, _serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            Err(serde::ser::Error::custom("no bytes for you"))
+        }
+    }
+
+    let bytes = Xml(outer()).try_into_bytes().unwrap();
+    assert_eq!(bytes, OUTER_XML.as_bytes());
+
+    let err = Xml(Broken).try_into_bytes().unwrap_err();
+    assert!(err.to_string().contains("no bytes for you"), "{err}");
+}
+
+#[tokio::test]
+async fn sanitize_serialize_errors() {
+    struct Broken;
+
+    impl Serialize for Broken {
+        fn serialize<S>(&self, _serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            Err(serde::ser::Error::custom("secret field `password_hash`"))
+        }
+    }
+
+    let app = Router::new()
+        .route("/", get(|| async { Xml(Broken) }))
+        .route(
+            "/verbose",
+            get(|| async { Xml::with_verbose_errors(Broken, true) }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("failed to serialize response"));
+    assert!(!body.contains("password_hash"));
+
+    let res = client.get("/verbose").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("secret field `password_hash`"));
+}
+
+#[tokio::test]
+async fn compose_xml_response_parts() {
+    use axum::response::IntoResponse;
+
+    let app = Router::new()
+        .route(
+            "/",
+            get(|| async { (StatusCode::CREATED, [("x-custom", "1")], Xml(outer())) }),
+        )
+        .route(
+            "/tagged",
+            get(|| async {
+                (
+                    StatusCode::ACCEPTED,
+                    XmlContentType,
+                    [("x-custom", "2")],
+                    "<done/>",
+                )
+                    .into_response()
+            }),
+        )
+        .route(
+            "/body",
+            get(|| async { (StatusCode::CREATED, [("x-custom", "3")], XmlBody(outer())) }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    assert_eq!(res.headers()["x-custom"], "1");
+    assert_eq!(res.text().await.unwrap(), OUTER_XML);
+
+    let res = client.get("/tagged").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    assert_eq!(res.headers()["x-custom"], "2");
+    assert_eq!(res.text().await.unwrap(), "<done/>");
+
+    let res = client.get("/body").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    assert_eq!(res.headers()["x-custom"], "3");
+    assert_eq!(res.text().await.unwrap(), OUTER_XML);
+}
+
+#[tokio::test]
+async fn reject_too_many_elements() {
+    #[derive(Deserialize)]
+    struct List {
+        #[serde(rename = "item", default)]
+        _items: Vec<Item>,
+    }
+
+    #[derive(Deserialize)]
+    struct Item {}
+
+    let handler = post(|Xml(_): Xml<List>| async {});
+    let app = Router::new().route(
+        "/",
+        handler.layer(Extension(
+            XmlConfig::new()
+                .with_max_elements(100)
+                .with_max_attributes(3),
+        )),
+    );
+
+    let client = TestClient::new(app);
+    let send = |body: String| {
+        client
+            .post("/")
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send(format!("<List>{}</List>", "<item/>".repeat(99)))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = send(format!("<List>{}</List>", "<item/>".repeat(100)))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("The XML document has too many elements"));
+
+    let res = send(r#"<List><item a="1" b="2" c="3" d="4"/></List>"#.to_owned())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("An element of the XML document has too many attributes"));
+}
+
+#[tokio::test]
+async fn reject_body_already_extracted() {
+    // What a request looks like to `Xml` after an earlier extractor took the body out of it.
+    let consumed = || {
+        Request::builder()
+            .header("content-type", "application/xml")
+            .header("content-length", OUTER_XML.len())
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let rejection = Xml::<Outer>::from_request(consumed(), &())
+        .await
+        .unwrap_err();
+    assert!(matches!(rejection, XmlRejection::BodyAlreadyExtracted));
+    assert_eq!(rejection.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let rejection = RawXml::from_request(consumed(), &()).await.unwrap_err();
+    assert!(matches!(rejection, XmlRejection::BodyAlreadyExtracted));
+
+    let empty = Request::builder()
+        .header("content-type", "application/xml")
+        .header("content-length", 0)
+        .body(Body::empty())
+        .unwrap();
+    let rejection = Xml::<Outer>::from_request(empty, &()).await.unwrap_err();
+    assert!(matches!(rejection, XmlRejection::EmptyBody));
+}
+
+#[tokio::test]
+async fn xsi_nil_round_trip() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Reading {
+        #[serde(serialize_with = "crate::xsi_nil::serialize")]
+        celsius: Option<i32>,
+        place: Inner,
+    }
+
+    let reading = |celsius| Reading {
+        celsius,
+        place: Inner {
+            name: "a".to_owned(),
+        },
+    };
+
+    let app = Router::new()
+        .route(
+            "/",
+            post(|Xml(reading): Xml<Reading>| async move { Xml(reading) }),
+        )
+        .layer(Extension(XmlConfig::new().with_xsi_nil(true)));
+
+    let client = TestClient::new(app);
+
+    for value in [None, Some(-4)] {
+        let body = String::from_utf8(Xml::to_bytes(&reading(value)).unwrap()).unwrap();
+        assert_eq!(
+            body.contains(r#"<celsius xsi:nil="true"/>"#),
+            value.is_none()
+        );
+        assert_eq!(
+            body.contains(r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance""#),
+            value.is_none()
+        );
+
+        let res = client
+            .post("/")
+            .body(body.clone())
+            .header("content-type", "application/xml")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.text().await.unwrap(), body);
+
+        let config = XmlConfig::new().with_xsi_nil(true);
+        let Xml(parsed) = Xml::<Reading>::from_bytes_with_config(body.as_bytes(), &config).unwrap();
+        assert_eq!(parsed, reading(value));
+    }
+
+    let nil = r#"<Reading xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"><celsius xsi:nil="true"></celsius><place><name>a</name></place></Reading>"#;
+    assert!(Xml::<Reading>::from_bytes(nil.as_bytes()).is_err());
+}
+
+#[test]
+fn xsi_nil_strings_and_prefixes() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Note {
+        #[serde(serialize_with = "crate::xsi_nil::serialize")]
+        title: Option<String>,
+        body: String,
+    }
+
+    // Text that happens to contain a noncharacter is written as it is.
+    let note = Note {
+        title: Some("\u{FDD2}".to_owned()),
+        body: "\u{FDD2}".to_owned(),
+    };
+    let body = String::from_utf8(Xml::to_bytes(&note).unwrap()).unwrap();
+    assert!(!body.contains("xsi"), "{}", body);
+    assert_eq!(body.matches('\u{FDD2}').count(), 2);
+
+    let config = XmlConfig::new().with_xsi_nil(true);
+    let parse = |xml: &str| {
+        Xml::<Note>::from_bytes_with_config(xml.as_bytes(), &config)
+            .unwrap()
+            .0
+    };
+
+    let note = parse(
+        r#"<Note xmlns:i="http://www.w3.org/2001/XMLSchema-instance"><title i:nil="true"/><body>b</body></Note>"#,
+    );
+    assert_eq!(note.title, None);
+
+    let note = parse(
+        r#"<Note xmlns:xsi="urn:not-xsi"><title xsi:nil="true">t</title><body>b</body></Note>"#,
+    );
+    assert_eq!(note.title.as_deref(), Some("t"));
+}
+
+#[tokio::test]
+async fn soap_content_type_and_fault() {
+    let app = Router::new()
+        .route(
+            "/",
+            post(|Xml(outer): Xml<Outer>| async move { Xml(outer) }),
+        )
+        .route(
+            "/fault",
+            get(|| async { Xml::soap_fault(SoapFaultCode::Sender, "Bad <order>") }),
+        )
+        .route(
+            "/receiver",
+            get(|| async { Xml::soap_fault(SoapFaultCode::Receiver, "Try again") }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client
+        .post("/")
+        .body(OUTER_XML)
+        .header(
+            "content-type",
+            r#"application/soap+xml; charset=utf-8; action="urn:Get""#,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = client.get("/fault").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(res.headers()["content-type"], "application/soap+xml");
+    assert_eq!(
+        res.text().await.unwrap(),
+        "<soap:Envelope xmlns:soap=\"http://www.w3.org/2003/05/soap-envelope\">\
+            <soap:Body><soap:Fault>\
+            <soap:Code><soap:Value>soap:Sender</soap:Value></soap:Code>\
+            <soap:Reason><soap:Text xml:lang=\"en\">Bad &lt;order&gt;</soap:Text></soap:Reason>\
+            </soap:Fault></soap:Body>\
+            </soap:Envelope>"
+    );
+
+    let res = client.get("/receiver").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("<soap:Value>soap:Receiver</soap:Value>"));
+}
+
+#[tokio::test]
+async fn dyn_xml_round_trip() {
+    const DOCUMENT: &str = "<feed xmlns=\"urn:feed\" version=\"2\">\n  \
+        <!-- entries -->\n  \
+        <entry id=\"1\"><title>A &amp; B</title><tags><tag>x</tag><tag/></tags></entry>\n  \
+        <entry id=\"2\"><title><![CDATA[<raw>]]></title>mixed <b>bold</b> text</entry>\n\
+        </feed>";
+    const COMPACT: &str = "<feed xmlns=\"urn:feed\" version=\"2\">\
+        <entry id=\"1\"><title>A &amp; B</title><tags><tag>x</tag><tag/></tags></entry>\
+        <entry id=\"2\"><title>&lt;raw&gt;</title>mixed<b>bold</b>text</entry>\
+        </feed>";
+
+    let app = Router::new().route("/", post(|DynXml(value): DynXml| async { DynXml(value) }));
+
+    let client = TestClient::new(app);
+    let send = |body: &'static str| {
+        client
+            .post("/")
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send(DOCUMENT).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    let body = res.text().await.unwrap();
+    assert_eq!(body, COMPACT);
+
+    let res = send(COMPACT).await.unwrap();
+    assert_eq!(res.text().await.unwrap(), COMPACT);
+
+    let req = Request::builder()
+        .header("content-type", "application/xml")
+        .body(Body::from(DOCUMENT))
+        .unwrap();
+    let DynXml(feed) = DynXml::from_request(req, &()).await.unwrap();
+    assert_eq!(feed.name(), Some("feed"));
+    assert_eq!(feed.attribute("version"), Some("2"));
+    let entries: Vec<_> = feed.children_named("entry").collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        entries[0].children_named("title").next().unwrap().text(),
+        "A & B"
+    );
+    assert_eq!(entries[1].text(), "<raw>mixedboldtext");
+    assert_eq!(
+        entries[0].children()[1],
+        XmlValue::Element {
+            name: "tags".to_owned(),
+            attributes: Vec::new(),
+            children: vec![
+                XmlValue::Element {
+                    name: "tag".to_owned(),
+                    attributes: Vec::new(),
+                    children: vec![XmlValue::Text("x".to_owned())],
+                },
+                XmlValue::element("tag"),
+            ],
+        }
+    );
+
+    for invalid in ["<a><b></a>", "<a/><b/>", "<a>", "text"] {
+        let res = send(invalid).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", invalid);
+    }
+}
+
+#[tokio::test]
+async fn serialize_with_bool_format() {
+    #[derive(Serialize)]
+    struct Flags {
+        active: bool,
+        deleted: bool,
+        nested: Nested,
+    }
+
+    #[derive(Serialize)]
+    struct Nested {
+        flag: Option<bool>,
+    }
+
+    fn flags() -> Flags {
+        Flags {
+            active: true,
+            deleted: false,
+            nested: Nested { flag: Some(true) },
+        }
+    }
+
+    let app = Router::new()
+        .route("/", get(|| async { Xml(flags()) }))
+        .route(
+            "/numeric",
+            get(|| async { Xml::with_bool_format(flags(), BoolFormat::Numeric) }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/").send().await.unwrap();
+    let literal = res.text().await.unwrap();
+    assert!(literal.contains("true") && literal.contains("false"));
+
+    let res = client.get("/numeric").send().await.unwrap();
+    let numeric = res.text().await.unwrap();
+    #[cfg(feature = "backend-quick-xml")]
+    assert_eq!(
+        numeric,
+        r#"<Flags active="1" deleted="0"><nested flag="1"/></Flags>"#
+    );
+    assert_eq!(numeric, literal.replace("true", "1").replace("false", "0"));
+}
+
+#[tokio::test]
+async fn routes_inherit_router_config() {
+    let handler = post(|Xml(_): Xml<Outer>| async {});
+    let base = XmlConfig::new().with_limit(OUTER_XML.len() - 1);
+
+    let nested = Router::new().route("/", handler.clone());
+    let app = Router::new()
+        .route("/", handler.clone())
+        .route(
+            "/larger",
+            handler.layer(Extension(base.clone().with_limit(OUTER_XML.len()))),
+        )
+        .nest("/nested", nested)
+        .layer(Extension(base));
+
+    let client = TestClient::new(app);
+    let send = |url: &'static str| {
+        client
+            .post(url)
+            .body(OUTER_XML)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send("/").await.unwrap();
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let res = send("/nested").await.unwrap();
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let res = send("/larger").await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[test]
+fn small_bodies_deserialize_like_large_ones() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Item {
+        name: String,
+        quantity: u32,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Order {
+        id: u64,
+        #[serde(default, rename = "Item")]
+        items: Vec<Item>,
+    }
+
+    let documents = [
+        "<Order><id>7</id></Order>",
+        r#"<Order id="7"><Item name="tea" quantity="2"/><Item name="cake" quantity="1"/></Order>"#,
+        "<?xml version=\"1.0\"?>\n<Order>\n  <id> 7 </id>\n</Order>\n",
+        "<Order><id>seven</id></Order>",
+        "<Order><id>7</id>",
+        "<Order><ID>7</ID></Order>",
+    ];
+    for document in documents {
+        let bytes = document.as_bytes();
+        assert_eq!(
+            crate::de::from_slice::<Order>(bytes),
+            crate::de::from_reader::<Order>(bytes).ok(),
+            "{document}",
+        );
+
+        let extracted = crate::de::deserialize::<Order>(bytes, None, &XmlConfig::new());
+        match (extracted, crate::de::from_reader::<Order>(bytes)) {
+            (Ok(extracted), Ok(buffered)) => assert_eq!(extracted, buffered),
+            (Err(extracted), Err(buffered)) => {
+                assert_eq!(extracted.to_string(), buffered.to_string());
+                assert_eq!(extracted.position(), buffered.position());
+            }
+            (extracted, buffered) => panic!("{document}: {extracted:?} != {buffered:?}"),
+        }
+    }
+
+    let items = r#"<Item name="tea" quantity="2"/>"#.repeat(crate::de::SMALL_BODY / 30);
+    let large = format!("<Order><id>7</id>{items}</Order>");
+    assert!(large.len() > crate::de::SMALL_BODY);
+    let order = crate::de::deserialize::<Order>(large.as_bytes(), None, &XmlConfig::new()).unwrap();
+    assert_eq!(order.items.len(), crate::de::SMALL_BODY / 30);
+}
+
+#[test]
+fn sequences_round_trip() {
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Line {
+        sku: String,
+        quantity: u32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        lines: XmlList<Line>,
+        tags: XmlList<String>,
+    }
+
+    let lines: Vec<Line> = ["tea", "cake", "jam"]
+        .into_iter()
+        .zip(1..)
+        .map(|(sku, quantity)| Line {
+            sku: sku.to_owned(),
+            quantity,
+        })
+        .collect();
+    let tags = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+
+    let list = XmlList::new(lines.clone()).with_item_name("line");
+    let bytes = Xml::to_bytes(&list).unwrap();
+    let body = String::from_utf8(bytes).unwrap();
+    assert!(body.starts_with("<list><line"));
+    assert_eq!(body.matches("<line").count(), 3);
+    let Xml(read) = Xml::<XmlList<Line>>::from_bytes(body.as_bytes()).unwrap();
+    assert_eq!(read.into_inner(), lines);
+
+    let order = Order {
+        lines: XmlList::new(lines.clone()).with_item_name("line"),
+        tags: XmlList::new(tags.clone()).with_item_name("tag"),
+    };
+    let bytes = Xml::to_bytes(&order).unwrap();
+    let body = String::from_utf8(bytes).unwrap();
+    assert!(body.contains("<tags><tag>a</tag><tag>b</tag><tag>c</tag></tags>"));
+    let Xml(read) = Xml::<Order>::from_bytes(body.as_bytes()).unwrap();
+    assert_eq!(*read.lines, lines);
+    assert_eq!(*read.tags, tags);
+
+    let Xml(empty) = Xml::<XmlList<Line>>::from_bytes(b"<list/>").unwrap();
+    assert!(empty.is_empty());
+
+    #[cfg(feature = "backend-quick-xml")]
+    {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Flat {
+            #[serde(rename = "line")]
+            lines: Vec<Line>,
+        }
+
+        let flat = Flat {
+            lines: lines.clone(),
+        };
+        let bytes = Xml::to_bytes(&flat).unwrap();
+        assert_eq!(
+            bytes,
+            br#"<Flat><line sku="tea" quantity="1"/><line sku="cake" quantity="2"/><line sku="jam" quantity="3"/></Flat>"#,
+        );
+        assert_eq!(Xml::<Flat>::from_bytes(&bytes).unwrap().0, flat);
+    }
+}
+
+#[tokio::test]
+async fn content_length_matches_body() {
+    use axum::response::IntoResponse;
+
+    #[derive(Serialize)]
+    struct User {
+        name: String,
+    }
+
+    let user = || User {
+        name: "Ünïcode".to_owned(),
+    };
+    let app = Router::new()
+        .route("/", get(move || async move { Xml(user()) }))
+        .route(
+            "/declared",
+            get(move || async move {
+                Xml::with_declaration(user())
+                    .pretty()
+                    .header("content-length", "1")
+            }),
+        );
+
+    let client = TestClient::new(app);
+
+    for url in ["/", "/declared"] {
+        let res = client.get(url).send().await.unwrap();
+        let content_length: usize = res.headers()["content-length"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let body = res.bytes().await.unwrap();
+        assert_eq!(content_length, body.len(), "{url}");
+    }
+
+    let res = Xml(user()).into_response();
+    let bytes = Xml::to_bytes(&user()).unwrap();
+    assert_eq!(
+        res.headers()[http::header::CONTENT_LENGTH],
+        bytes.len().to_string(),
+    );
+}
+
+#[tokio::test]
+async fn utf16_output_encoding() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Greeting {
+        text: String,
+    }
+
+    let greeting = || Greeting {
+        text: "Grüße 👋".to_owned(),
+    };
+    let app = Router::new()
+        .route(
+            "/le",
+            get(move || async move {
+                Xml::with_output_encoding(greeting(), Encoding::Utf16Le).with_declaration()
+            }),
+        )
+        .route(
+            "/be",
+            get(move || async move { Xml::with_output_encoding(greeting(), Encoding::Utf16Be) }),
+        );
+
+    let client = TestClient::new(app);
+
+    let expected_le = format!(
+        r#"<?xml version="1.0" encoding="UTF-16"?>{}"#,
+        String::from_utf8(Xml::to_bytes(&greeting()).unwrap()).unwrap(),
+    );
+    let expected_be = String::from_utf8(Xml::to_bytes(&greeting()).unwrap()).unwrap();
+    type CodeUnit = fn([u8; 2]) -> u16;
+    let cases = [
+        ("/le", u16::from_le_bytes as CodeUnit, expected_le),
+        ("/be", u16::from_be_bytes, expected_be),
+    ];
+    for (url, code_unit, expected) in cases {
+        let res = client.get(url).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()["content-type"],
+            "application/xml; charset=utf-16"
+        );
+        let body = res.bytes().await.unwrap();
+
+        let units: Vec<u16> = body
+            .chunks_exact(2)
+            .map(|pair| code_unit([pair[0], pair[1]]))
+            .collect();
+        assert_eq!(units[0], 0xFEFF, "{url}");
+        assert_eq!(String::from_utf16(&units[1..]).unwrap(), expected, "{url}");
+
+        #[cfg(feature = "encoding")]
+        assert_eq!(Xml::<Greeting>::from_bytes(&body).unwrap().0, greeting());
+    }
+
+    assert!(Xml::to_bytes(&greeting())
+        .unwrap()
+        .starts_with(b"<Greeting"));
+}
+
+#[tokio::test]
+async fn reject_unexpected_root() {
+    #[derive(Debug, Deserialize)]
+    struct Request {
+        id: u32,
+    }
+
+    let app = Router::new()
+        .route(
+            "/",
+            post(|Xml(request): Xml<Request>| async move { request.id.to_string() }),
+        )
+        .layer(Extension(XmlConfig::new().with_expected_root("request")));
+
+    let client = TestClient::new(app);
+    let send = |body: &'static str| {
+        client
+            .post("/")
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send("<request><id>7</id></request>").await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "7");
+
+    let res = send(r#"<?xml version="1.0"?><m:request xmlns:m="urn:m"><id>7</id></m:request>"#)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = send("<response><id>7</id></response>").await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("Expected the root element `request`, found `response`"));
+
+    let config = XmlConfig::new().with_expected_root("m:request");
+    let rejection =
+        Xml::<Request>::from_bytes_with_config(b"<x:request><id>7</id></x:request>", &config)
+            .unwrap_err();
+    assert!(matches!(
+        rejection,
+        XmlRejection::UnexpectedRoot { ref expected, ref found }
+            if expected == "m:request" && found == "x:request"
+    ));
+    assert_eq!(rejection.code(), "xml.root.unexpected");
+}
+
+#[tokio::test]
+async fn echo_request_id_in_rejections() {
+    #[derive(Debug, Deserialize)]
+    struct Input {}
+
+    let config =
+        XmlConfig::new().with_request_id_header(http::HeaderName::from_static("x-request-id"));
+    let app = Router::new()
+        .route("/", post(|_: Xml<Input>| async {}))
+        .layer(Extension(config.clone()));
+
+    let client = TestClient::new(app);
+
+    let res = client
+        .post("/")
+        .body("<Input>")
+        .header("content-type", "application/xml")
+        .header("x-request-id", "req-42")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("req-42"), "{body}");
+    if cfg!(feature = "xml-errors") {
+        assert!(
+            body.ends_with("<requestId>req-42</requestId></error>"),
+            "{body}"
+        );
+    }
+
+    let res = client.post("/").body("<Input/>").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert!(!res.text().await.unwrap().contains("request id"));
+
+    let mut req = Request::builder()
+        .method("POST")
+        .header("x-request-id", "req-43")
+        .body(Body::from("<Input/>"))
+        .unwrap();
+    req.extensions_mut().insert(config);
+    let rejection = Xml::<Input>::from_request(req, &()).await.unwrap_err();
+    assert_eq!(rejection.request_id(), Some("req-43"));
+    assert_eq!(rejection.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(rejection.code(), "xml.content_type.missing");
+    assert!(matches!(
+        rejection.without_request_id(),
+        XmlRejection::MissingXMLContentType { .. }
+    ));
+}
+
+#[cfg(feature = "xml-errors")]
+#[tokio::test]
+async fn serialize_errors_as_xml() {
+    struct Broken;
+
+    impl Serialize for Broken {
+        fn serialize<S>(&self, _serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            Err(serde::ser::Error::custom("broken <value>"))
+        }
+    }
+
+    let app = Router::new()
+        .route("/", get(|| async { Xml(Broken) }))
+        .route(
+            "/verbose",
+            get(|| async { Xml::with_verbose_errors(Broken, true) }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    assert_eq!(
+        res.text().await.unwrap(),
+        "<error><code>500</code><message>failed to serialize response</message></error>"
+    );
+
+    let res = client.get("/verbose").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body = res.text().await.unwrap();
+    assert!(
+        body.starts_with("<error><code>500</code><message>"),
+        "{body}"
+    );
+    assert!(
+        body.ends_with("broken &lt;value&gt;</message></error>"),
+        "{body}"
+    );
+}
+
+#[tokio::test]
+async fn single_root_with_surrounding_whitespace() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    let app = Router::new().route("/", post(|Xml(input): Xml<Input>| async move { input.foo }));
+
+    let client = TestClient::new(app);
+    let send = |body: &'static str| {
+        client
+            .post("/")
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send("\n  <Input><foo>bar</foo></Input>\r\n\n")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+
+    for body in [
+        "<Input><foo>bar</foo></Input><Input><foo>baz</foo></Input>",
+        "<Input><foo>bar</foo></Input>\n<Other/>\n",
+        "<Input/><Input/>",
+    ] {
+        let res = send(body).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{body}");
+        assert!(res
+            .text()
+            .await
+            .unwrap()
+            .contains("The XML document has more than one root element"));
+    }
+
+    let rejection = Xml::<Input>::from_bytes(b"<Input/><Input/>").unwrap_err();
+    assert!(matches!(rejection, XmlRejection::MultipleRoots));
+    assert_eq!(rejection.code(), "xml.parse.multiple_roots");
+}
+
+#[tokio::test]
+async fn reject_text_after_root() {
+    #[derive(Debug, Deserialize)]
+    struct Outer {}
+
+    let app = Router::new().route("/", post(|_: Xml<Outer>| async {}));
+
+    let client = TestClient::new(app);
+    for body in [
+        "<Outer/>garbage",
+        "<Outer></Outer>\n more\n",
+        "<Outer/><![CDATA[x]]>",
+    ] {
+        let res = client
+            .post("/")
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{body}");
+    }
+
+    let rejection = Xml::<Outer>::from_bytes(b"<Outer/>garbage").unwrap_err();
+    assert!(matches!(rejection, XmlRejection::InvalidXMLBody { .. }));
+    assert_eq!(rejection.position(), Some(8));
+    let rejection = Xml::<Outer>::from_bytes(b"<Outer></Outer>\n more\n").unwrap_err();
+    assert_eq!(rejection.position(), Some(15));
+    assert!(Xml::<Outer>::from_bytes(b"<Outer/>\n\t ").is_ok());
+}
+
+#[tokio::test]
+async fn reuse_encoder() {
+    use std::sync::Arc;
+
+    #[derive(Serialize)]
+    struct User {
+        name: String,
+    }
+
+    let configured = || {
+        XmlEncoder::new()
+            .pretty()
+            .with_root_name("user")
+            .with_namespace("urn:users")
+            .with_charset("utf-8")
+    };
+
+    let encoder = configured();
+    for name in ["alice", "bob", "carol"] {
+        let user = User {
+            name: name.to_owned(),
+        };
+        let bytes = encoder.encode(&user).unwrap();
+        assert_eq!(bytes, configured().encode(&user).unwrap());
+
+        let body = String::from_utf8(bytes).unwrap();
+        assert!(body.starts_with("<user "));
+        assert!(body.contains(r#"xmlns="urn:users""#));
+        assert!(body.contains(name));
+    }
+
+    let encoder = Arc::new(encoder);
+    let app = Router::new().route(
+        "/:name",
+        get(
+            move |axum::extract::Path(name): axum::extract::Path<String>| {
+                let encoder = Arc::clone(&encoder);
+                async move { encoder.response(User { name }).status(StatusCode::CREATED) }
+            },
+        ),
+    );
+
+    let client = TestClient::new(app);
+    for name in ["alice", "bob"] {
+        let res = client.get(&format!("/{name}")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(
+            res.headers()["content-type"],
+            "application/xml; charset=utf-8"
+        );
+        assert!(res.text().await.unwrap().contains(name));
+    }
+}
+
+#[test]
+fn map_entries_round_trip() {
+    use std::collections::HashMap;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Limit {
+        max: u32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Settings {
+        #[serde(with = "crate::entries")]
+        labels: HashMap<String, String>,
+        #[serde(with = "crate::entries")]
+        limits: HashMap<String, Limit>,
+    }
+
+    let settings = Settings {
+        labels: HashMap::from([
+            ("display name".to_owned(), "Ops <team>".to_owned()),
+            ("2fa".to_owned(), "on".to_owned()),
+            ("a&b".to_owned(), String::new()),
+        ]),
+        limits: HashMap::from([("per minute".to_owned(), Limit { max: 60 })]),
+    };
+
+    let bytes = Xml::to_bytes(&settings).unwrap();
+    let body = String::from_utf8(bytes).unwrap();
+    assert_eq!(body.matches("<entry").count(), 4);
+    if cfg!(feature = "backend-quick-xml") {
+        assert!(
+            body.contains(r#"<entry key="display name"><value>Ops &lt;team&gt;</value></entry>"#)
+        );
+    }
+
+    let Xml(read) = Xml::<Settings>::from_bytes(body.as_bytes()).unwrap();
+    assert_eq!(read, settings);
+
+    let Xml(empty) =
+        Xml::<Settings>::from_bytes(b"<Settings><labels/><limits/></Settings>").unwrap();
+    assert!(empty.labels.is_empty() && empty.limits.is_empty());
+}
+
+#[tokio::test]
+async fn allowlist_content_types() {
+    #[derive(Deserialize)]
+    struct Feed {}
+
+    let app = Router::new()
+        .route("/", post(|_: Xml<Feed>| async {}))
+        .layer(Extension(XmlConfig::new().with_allowed_types([
+            "application/xml",
+            "application/atom+xml",
+        ])));
+
+    let client = TestClient::new(app);
+    let send = |content_type: &'static str| {
+        client
+            .post("/")
+            .body("<Feed/>")
+            .header("content-type", content_type)
+            .send()
+    };
+
+    for content_type in [
+        "application/xml",
+        "application/atom+xml",
+        "Application/Atom+XML; charset=utf-8",
+    ] {
+        let res = send(content_type).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{content_type}");
+    }
+    for content_type in ["application/evil+xml", "text/xml", "application/rss+xml"] {
+        let res = send(content_type).await.unwrap();
+        assert_eq!(
+            res.status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "{content_type}"
+        );
+    }
+}
+
+#[test]
+fn reserved_xml_attributes() {
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Title {
+        #[cfg_attr(feature = "backend-quick-xml", serde(rename = "xml:lang"))]
+        lang: String,
+        #[serde(rename = "$value")]
+        text: String,
+    }
+
+    let title = Title {
+        lang: "en".to_owned(),
+        text: "Hello".to_owned(),
+    };
+    let Xml(read) = Xml::<Title>::from_bytes(br#"<Title xml:lang="en">Hello</Title>"#).unwrap();
+    assert_eq!(read, title);
+
+    // serde-xml-rs writes the field as an element, so only quick-xml reads its output back.
+    #[cfg(feature = "backend-quick-xml")]
+    {
+        let bytes = Xml::to_bytes(&title).unwrap();
+        assert_eq!(bytes, br#"<Title xml:lang="en">Hello</Title>"#);
+        let Xml(read) = Xml::<Title>::from_bytes(&bytes).unwrap();
+        assert_eq!(read, title);
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Prefixed {
+            #[serde(rename = "@xml:lang")]
+            lang: String,
+            #[serde(rename = "@xml:space")]
+            space: String,
+        }
+
+        let config = XmlConfig::new().with_attribute_prefix(true);
+        let Xml(read) = Xml::<Prefixed>::from_bytes_with_config(
+            br#"<Title xml:lang="en" xml:space="preserve"/>"#,
+            &config,
+        )
+        .unwrap();
+        assert_eq!(read.lang, "en");
+        assert_eq!(read.space, "preserve");
+    }
+}
+
+#[cfg(feature = "backend-quick-xml")]
+#[test]
+fn xml_space_preserve_keeps_whitespace() {
+    #[derive(Debug, Deserialize)]
+    struct Poem {
+        title: String,
+        verse: Verse,
+        note: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Verse {
+        line: Vec<String>,
+        plain: String,
+    }
+
+    let bytes = br#"<Poem>
+        <title>  Ode  </title>
+        <verse xml:space="preserve">
+            <line>  Roses  </line>
+            <line>  are red  </line>
+            <plain xml:space="default">  trimmed  </plain>
+        </verse>
+        <note>  trimmed too  </note>
+    </Poem>"#;
+
+    let Xml(poem) = Xml::<Poem>::from_bytes(bytes).unwrap();
+    assert_eq!(poem.title, "Ode");
+    assert_eq!(poem.verse.line, ["  Roses  ", "  are red  "]);
+    assert_eq!(poem.verse.plain, "trimmed");
+    assert_eq!(poem.note, "trimmed too");
+}
+
+#[tokio::test]
+async fn invalid_char_policy() {
+    #[derive(Serialize)]
+    struct Note {
+        text: XmlList<String>,
+    }
+
+    let note = || Note {
+        text: XmlList::new(vec!["a\u{0}b\u{1B}c\u{FFFF}".to_owned()]),
+    };
+
+    let err = XmlEncoder::new().encode(&note()).unwrap_err();
+    assert!(err.to_string().contains("U+0000"), "{err}");
+
+    let stripped = XmlEncoder::new()
+        .with_invalid_char_policy(InvalidCharPolicy::Strip)
+        .encode(&note())
+        .unwrap();
+    assert_eq!(stripped, b"<Note><text><item>abc</item></text></Note>");
+
+    let escaped = XmlEncoder::new()
+        .with_invalid_char_policy(InvalidCharPolicy::Escape)
+        .encode(&note())
+        .unwrap();
+    assert_eq!(
+        escaped,
+        b"<Note><text><item>a&#x0;b&#x1B;c&#xFFFF;</item></text></Note>".as_slice()
+    );
+
+    let app = Router::new()
+        .route("/", get(move || async move { Xml(note()) }))
+        .route(
+            "/strip",
+            get(move || async move {
+                Xml::builder(note()).with_invalid_char_policy(InvalidCharPolicy::Strip)
+            }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let res = client.get("/strip").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.text().await.unwrap(),
+        "<Note><text><item>abc</item></text></Note>"
+    );
+}
+
+#[tokio::test]
+async fn ascii_only_output() {
+    #[derive(Serialize)]
+    struct Greeting {
+        name: String,
+        text: XmlList<String>,
+    }
+
+    let greeting = || Greeting {
+        name: "Zoë".to_owned(),
+        text: XmlList::new(vec!["café".to_owned()]),
+    };
+
+    let bytes = XmlEncoder::new()
+        .with_ascii_only(true)
+        .encode(&greeting())
+        .unwrap();
+    assert!(bytes.is_ascii());
+    let xml = String::from_utf8(bytes).unwrap();
+    assert!(xml.contains("caf&#233;"), "{xml}");
+    assert!(xml.contains("Zo&#235;"), "{xml}");
+
+    let app = Router::new()
+        .route("/", get(move || async move { Xml(greeting()) }))
+        .route(
+            "/ascii",
+            get(move || async move { Xml::builder(greeting()).with_ascii_only(true) }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/").send().await.unwrap();
+    assert!(res.text().await.unwrap().contains("café"));
+
+    let res = client.get("/ascii").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let text = res.text().await.unwrap();
+    assert!(text.is_ascii() && text.contains("caf&#233;"), "{text}");
+}
+
+#[tokio::test]
+async fn enum_variants_round_trip() {
+    #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+    enum Shape {
+        Circle,
+        Square(Side),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+    struct Side {
+        len: u32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+    struct Drawing {
+        #[serde(rename = "$value")]
+        shape: Shape,
+    }
+
+    let shapes = [
+        Shape::Circle,
+        Shape::Square(Side { len: 2 }),
+        Shape::Rect { w: 1, h: 2 },
+    ];
+    let (square, rect) = if cfg!(feature = "backend-quick-xml") {
+        (r#"<Square len="2"/>"#, r#"<Rect w="1" h="2"/>"#)
+    } else {
+        (
+            "<Square><len>2</len></Square>",
+            "<Rect><w>1</w><h>2</h></Rect>",
+        )
+    };
+    let circle = Xml::to_bytes(&Shape::Circle).unwrap();
+    assert!(circle.starts_with(b"<Circle"), "{circle:?}");
+    assert_eq!(Xml::to_bytes(&shapes[1]).unwrap(), square.as_bytes());
+    assert_eq!(Xml::to_bytes(&shapes[2]).unwrap(), rect.as_bytes());
+
+    let app = Router::new()
+        .route("/", post(|Xml(shape): Xml<Shape>| async { Xml(shape) }))
+        .route(
+            "/drawing",
+            post(|Xml(drawing): Xml<Drawing>| async { Xml(drawing) }),
+        );
+
+    let client = TestClient::new(app);
+
+    for shape in shapes {
+        let res = client
+            .post("/")
+            .body(Xml::to_bytes(&shape).unwrap())
+            .header("content-type", "application/xml")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let Xml(read) = Xml::<Shape>::from_bytes(&res.bytes().await.unwrap()).unwrap();
+        assert_eq!(read, shape);
+
+        let drawing = Drawing { shape };
+        let res = client
+            .post("/drawing")
+            .body(Xml::to_bytes(&drawing).unwrap())
+            .header("content-type", "application/xml")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let Xml(read) = Xml::<Drawing>::from_bytes(&res.bytes().await.unwrap()).unwrap();
+        assert_eq!(read, drawing);
+    }
+}
+
+#[tokio::test]
+async fn reject_oversized_attribute_values() {
+    #[derive(Deserialize)]
+    struct Input {
+        #[allow(dead_code)]
+        foo: String,
+    }
+
+    let app = Router::new()
+        .route("/", post(|_: Xml<Input>| async {}))
+        .route(
+            "/small",
+            post(|_: Xml<Input>| async {})
+                .layer(Extension(XmlConfig::new().with_max_attribute_len(16))),
+        );
+
+    let client = TestClient::new(app);
+    let send = |path: &'static str, value: String| {
+        client
+            .post(path)
+            .body(format!(r#"<Input foo="{value}"/>"#))
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send("/small", "x".repeat(16)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = send("/small", "x".repeat(17)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("An attribute value of the XML document is too large"));
+
+    let res = send("/", "x".repeat(XmlConfig::DEFAULT_MAX_ATTRIBUTE_LEN))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Well within the default body limit of 2 MB.
+    let res = send("/", "x".repeat(1024 * 1024)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res.text().await.unwrap().contains("too large"));
+
+    let err = Xml::<Input>::from_bytes_with_config(
+        br#"<Input foo="&amp;&amp;&amp;"/>"#,
+        &XmlConfig::new().with_max_attribute_len(10),
+    )
+    .err()
+    .unwrap();
+    assert!(matches!(err, XmlRejection::AttributeTooLarge));
+}
+
+#[tokio::test]
+async fn strip_namespace_prefixes() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct User {
+        id: String,
+        name: String,
+    }
+
+    let document =
+        r#"<ns:User xmlns:ns="urn:example:users" ns:id="7"><ns:name>Ann</ns:name></ns:User>"#;
+    let user = User {
+        id: "7".to_owned(),
+        name: "Ann".to_owned(),
+    };
+
+    let app = Router::new()
+        .route("/", post(|Xml(user): Xml<User>| async move { user.name }))
+        .route(
+            "/strip",
+            post(|Xml(user): Xml<User>| async move { user.name })
+                .layer(Extension(XmlConfig::new().with_strip_prefixes(true))),
+        );
+
+    let client = TestClient::new(app);
+    let send = |path: &'static str| {
+        client
+            .post(path)
+            .body(document)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send("/strip").await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "Ann");
+
+    let config = XmlConfig::new().with_strip_prefixes(true);
+    let Xml(read) = Xml::<User>::from_bytes_with_config(document.as_bytes(), &config).unwrap();
+    assert_eq!(read, user);
+
+    // serde-xml-rs always matches names without their prefix.
+    let res = send("/").await.unwrap();
+    if cfg!(feature = "backend-quick-xml") {
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    } else {
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
+
+#[cfg(feature = "xsd")]
+#[tokio::test]
+async fn validate_against_schema() {
+    use crate::{SchemaValidatedXml, XmlSchema};
+
+    const XSD: &str = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+        <xs:element name="User">
+            <xs:complexType>
+                <xs:sequence>
+                    <xs:element name="name" type="xs:string"/>
+                    <xs:element name="age" type="xs:integer"/>
+                </xs:sequence>
+            </xs:complexType>
+        </xs:element>
+    </xs:schema>"#;
+
+    #[derive(Deserialize)]
+    struct User {
+        name: String,
+        age: u32,
+    }
+
+    assert!(XmlSchema::new("<xs:schema").is_err());
+
+    let handler = post(
+        |SchemaValidatedXml(user): SchemaValidatedXml<User>| async move {
+            format!("{} {}", user.name, user.age)
+        },
+    );
+    let app = Router::new()
+        .route(
+            "/",
+            handler
+                .clone()
+                .layer(Extension(XmlSchema::new(XSD).unwrap())),
+        )
+        .merge(
+            Router::new()
+                .route("/entities", handler.clone())
+                .layer(Extension(XmlSchema::new(XSD).unwrap()))
+                .layer(Extension(XmlConfig::new().with_entities([("who", "Ann")]))),
+        )
+        .route("/missing", handler);
+
+    let client = TestClient::new(app);
+    let send = |path: &'static str, body: &'static str| {
+        client
+            .post(path)
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send("/", "<User><name>Ann</name><age>30</age></User>")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "Ann 30");
+
+    // Deserializes fine, but the schema wants the elements in order.
+    let res = send("/", "<User><age>30</age><name>Ann</name></User>")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = res.text().await.unwrap();
+    assert!(body.contains("does not match the schema"), "{body}");
+    assert!(body.contains("age"), "{body}");
+
+    let res = send("/", "<User><name>Ann</name><age>old</age></User>")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res = send("/", "<User><name>Ann</name>").await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = send("/", "<User><name>Ann</name><age>30</User>")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = send("/entities", "<User><name>&who;</name><age>30</age></User>")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "Ann 30");
+
+    let res = send("/missing", "<User><name>Ann</name><age>30</age></User>")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn unwrap_soap_body() {
+    #[derive(Deserialize)]
+    struct GetPrice {
+        item: String,
+    }
+
+    let handler = post(|SoapBody(request): SoapBody<GetPrice>| async move { request.item });
+    let app = Router::new().route("/", handler.clone()).route(
+        "/prefixed",
+        handler.layer(Extension(XmlConfig::new().with_strip_prefixes(true))),
+    );
+
+    let client = TestClient::new(app);
+    let send = |path: &'static str, body: &'static str| {
+        client
+            .post(path)
+            .body(body)
+            .header("content-type", "application/soap+xml; charset=utf-8")
+            .send()
+    };
+
+    let res = send(
+        "/",
+        r#"<?xml version="1.0"?>
+        <soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope">
+            <soap:Header><auth><token>secret</token></auth></soap:Header>
+            <soap:Body>
+                <GetPrice><item>Apples</item></GetPrice>
+            </soap:Body>
+        </soap:Envelope>"#,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "Apples");
+
+    // The payload uses a prefix declared on the envelope.
+    let res = send(
+        "/prefixed",
+        r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" xmlns:m="urn:prices"><s:Body><m:GetPrice><m:item>Pears</m:item></m:GetPrice></s:Body></s:Envelope>"#,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "Pears");
+
+    for body in [
+        "<GetPrice><item>Apples</item></GetPrice>",
+        "<soap:Envelope xmlns:soap=\"urn:soap\"><soap:Header/></soap:Envelope>",
+        "<soap:Envelope xmlns:soap=\"urn:soap\"><soap:Body/></soap:Envelope>",
+    ] {
+        let res = send("/", body).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+        assert!(
+            res.text().await.unwrap().contains("SOAP envelope"),
+            "{body}"
+        );
+    }
+
+    let res = send(
+        "/",
+        r#"<soap:Envelope xmlns:soap="urn:soap"><soap:Body><soap:Fault><soap:Reason>no</soap:Reason></soap:Fault></soap:Body></soap:Envelope>"#,
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(res.text().await.unwrap().contains("carries a fault"));
+}
+
+#[tokio::test]
+async fn reject_unsupported_xml_versions() {
+    #[derive(Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    let app = Router::new().route("/", post(|Xml(input): Xml<Input>| async move { input.foo }));
+
+    let client = TestClient::new(app);
+    let send = |version: &'static str| {
+        client
+            .post("/")
+            .body(format!(r#"<?xml version="{version}"?><Input foo="bar"/>"#))
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    for version in ["1.0", "1.1"] {
+        let res = send(version).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{version}");
+        assert_eq!(res.text().await.unwrap(), "bar");
+    }
+
+    for version in ["2.0", "1.0.1", "one"] {
+        let res = send(version).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{version}");
+        assert!(res
+            .text()
+            .await
+            .unwrap()
+            .contains(&format!("XML version `{version}` is not supported")));
+    }
+
+    let err = Xml::<Input>::from_bytes(br#"<?xml version="2.0"?><Input foo="bar"/>"#)
+        .err()
+        .unwrap();
+    assert_eq!(err.code(), "xml.version.unsupported");
+}
+
+#[tokio::test]
+async fn reject_mismatched_end_tags() {
+    #[derive(Debug, Deserialize)]
+    struct Value {}
+
+    let app = Router::new().route("/", post(|_: Xml<Value>| async {}));
+
+    let client = TestClient::new(app);
+
+    let res = client
+        .post("/")
+        .body("<a><b></a>")
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    #[cfg(not(feature = "xml-errors"))]
+    assert_eq!(
+        res.text().await.unwrap(),
+        "End tag `</a>` does not match the open element `<b>` at line 1, column 9 (byte 8)"
+    );
+
+    let rejection = Xml::<Value>::from_bytes(b"<a>\n  <b></a>").unwrap_err();
+    assert_eq!(rejection.line_column(), Some((2, 8)));
+    assert!(rejection.to_string().contains("`</a>`"));
+    assert!(rejection.to_string().contains("`<b>`"));
+
+    let rejection = Xml::<Value>::from_bytes(b"<a></a></b>").unwrap_err();
+    assert_eq!(rejection.code(), "xml.parse.mismatched_tag");
+    assert!(matches!(
+        rejection,
+        XmlRejection::MalformedStructure { expected, found, .. } if expected.is_empty() && found == "b"
+    ));
+}
+
+#[tokio::test]
+async fn extract_value_with_raw_body() {
+    #[derive(Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    const BODY: &str = "<?xml version=\"1.0\"?>\n<Input  foo = \"bar\" ><!-- signed --></Input>";
+
+    let app = Router::new().route(
+        "/",
+        post(|body: XmlWithRaw<Input>| async move {
+            assert_eq!(body.raw, BODY.as_bytes());
+            body.value.foo
+        }),
+    );
+
+    let client = TestClient::new(app);
+    let res = client
+        .post("/")
+        .body(BODY)
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+
+    let res = client
+        .post("/")
+        .body("<Input/>")
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res = client.post("/").body(BODY).send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[cfg(feature = "chrono")]
+#[tokio::test]
+async fn datetime_round_trip() {
+    use crate::XmlDateTime;
+    use chrono::{FixedOffset, TimeZone, Timelike, Utc};
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Meeting {
+        starts: XmlDateTime,
+        local: XmlDateTime<FixedOffset>,
+    }
+
+    let offset = FixedOffset::east_opt(2 * 3600).unwrap();
+    let meeting = Meeting {
+        starts: Utc
+            .with_ymd_and_hms(2024, 5, 30, 9, 30, 10)
+            .unwrap()
+            .with_nanosecond(500_000_000)
+            .unwrap()
+            .into(),
+        local: offset
+            .with_ymd_and_hms(2024, 5, 30, 11, 0, 0)
+            .unwrap()
+            .into(),
+    };
+
+    let bytes = Xml::to_bytes(&meeting).unwrap();
+    let xml = String::from_utf8(bytes.clone()).unwrap();
+    assert!(xml.contains("2024-05-30T09:30:10.500Z"), "{xml}");
+    assert!(xml.contains("2024-05-30T11:00:00+02:00"), "{xml}");
+
+    let Xml(read) = Xml::<Meeting>::from_bytes(&bytes).unwrap();
+    assert_eq!(read, meeting);
+
+    let app = Router::new().route(
+        "/",
+        post(|Xml(meeting): Xml<Meeting>| async move {
+            format!("{} {}", meeting.starts.to_rfc3339(), meeting.local.offset())
+        }),
+    );
+
+    let client = TestClient::new(app);
+
+    let res = client
+        .post("/")
+        .body(
+            "<Meeting><starts>2024-05-30T11:30:10+02:00</starts>\
+             <local>2024-05-30T11:00:00</local></Meeting>",
+        )
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.text().await.unwrap(),
+        "2024-05-30T09:30:10+00:00 +00:00"
+    );
+
+    let res = client
+        .post("/")
+        .body("<Meeting><starts>yesterday</starts><local>2024-05-30T11:00:00Z</local></Meeting>")
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let rejection = Xml::<XmlDateTime>::from_bytes(b"<at>yesterday</at>").unwrap_err();
+    let err = rejection.as_parse_error().unwrap();
+    assert!(err.to_string().contains("xs:dateTime"), "{err}");
+}
+
+#[tokio::test]
+async fn name_sequence_items() {
+    #[derive(Clone, Serialize)]
+    struct Item {
+        sku: String,
+    }
+
+    #[derive(Serialize)]
+    struct Listed {
+        entries: XmlList<Item>,
+    }
+
+    let items = ["a", "b", "c"].map(|sku| Item {
+        sku: sku.to_owned(),
+    });
+    let count = |xml: &str| xml.matches("<item").count();
+
+    #[cfg(feature = "backend-quick-xml")]
+    {
+        #[derive(Serialize)]
+        struct Order {
+            #[serde(rename = "item")]
+            items: Vec<Item>,
+        }
+
+        let xml = String::from_utf8(
+            Xml::to_bytes(&Order {
+                items: items.to_vec(),
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(count(&xml), 3, "{xml}");
+        assert!(xml.starts_with("<Order><item"), "{xml}");
+    }
+
+    let app = Router::new().route(
+        "/",
+        get(move || async move {
+            Xml(Listed {
+                entries: XmlList::new(items.to_vec()).with_item_name("item"),
+            })
+        }),
+    );
+
+    let client = TestClient::new(app);
+    let res = client.get("/").send().await.unwrap();
+    let xml = res.text().await.unwrap();
+    assert_eq!(count(&xml), 3, "{xml}");
+    assert!(xml.starts_with("<Listed><entries><item"), "{xml}");
+    assert!(xml.ends_with("</entries></Listed>"), "{xml}");
+}
+
+#[tokio::test]
+async fn tell_malformed_content_type_from_wrong_type() {
+    let app = Router::new().route("/", post(|_: Xml<Outer>| async {}));
+
+    let client = TestClient::new(app);
+    let send = |content_type: &'static str| {
+        client
+            .post("/")
+            .body("<Outer/>")
+            .header("content-type", content_type)
+            .send()
+    };
+
+    for content_type in ["xml", "application xml", "application/xml; charset"] {
+        let res = send(content_type).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{content_type}");
+        assert!(res.text().await.unwrap().contains("not a valid media type"));
+    }
+
+    let res = send("text/plain").await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("Expected request with `Content-Type: application/xml`"));
+
+    let rejection = Xml::<Outer>::from_request(
+        Request::builder()
+            .method("POST")
+            .header("content-type", "application/xml")
+            .header("content-type", "not a type")
+            .body(Body::from("<Outer/>"))
+            .unwrap(),
+        &(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(rejection, XmlRejection::InvalidContentType));
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn record_metrics() {
+    use axum::response::IntoResponse;
+    use metrics::{
+        Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+        SharedString, Unit,
+    };
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    /// Keeps the total of every counter and the values of every histogram, by name and labels.
+    #[derive(Default)]
+    struct Capture(Arc<Mutex<BTreeMap<String, Vec<f64>>>>);
+
+    struct Series(Arc<Mutex<BTreeMap<String, Vec<f64>>>>, String);
+
+    impl CounterFn for Series {
+        fn increment(&self, value: u64) {
+            let mut series = self.0.lock().unwrap();
+            let values = series.entry(self.1.clone()).or_insert_with(|| vec![0.0]);
+            values[0] += value as f64;
+        }
+
+        fn absolute(&self, value: u64) {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(self.1.clone(), vec![value as f64]);
+        }
+    }
+
+    impl HistogramFn for Series {
+        fn record(&self, value: f64) {
+            let mut series = self.0.lock().unwrap();
+            series.entry(self.1.clone()).or_default().push(value);
+        }
+    }
+
+    impl Capture {
+        fn series(&self, key: &Key) -> Arc<Series> {
+            let labels: Vec<_> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            let name = if labels.is_empty() {
+                key.name().to_owned()
+            } else {
+                format!("{}{{{}}}", key.name(), labels.join(","))
+            };
+            Arc::new(Series(self.0.clone(), name))
+        }
+
+        fn get(&self, name: &str) -> Vec<f64> {
+            self.0
+                .lock()
+                .unwrap()
+                .get(name)
+                .cloned()
+                .unwrap_or_default()
+        }
+    }
+
+    impl Recorder for Capture {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.series(key))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(self.series(key))
+        }
+    }
+
+    let extract = |body: &'static str| {
+        Xml::<Outer>::from_request(
+            Request::builder()
+                .method("POST")
+                .header("content-type", "application/xml")
+                .body(Body::from(body))
+                .unwrap(),
+            &(),
+        )
+    };
+
+    let capture = Capture::default();
+    metrics::with_local_recorder(&capture, || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let Xml(value) = extract(OUTER_XML).await.unwrap();
+            Xml(value).into_response();
+
+            let rejection = extract("<Outer>").await.unwrap_err();
+            rejection.into_response();
+        });
+    });
+
+    assert_eq!(capture.get("axum_xml.requests.total{outcome=ok}"), [1.0]);
+    assert_eq!(
+        capture.get("axum_xml.requests.total{outcome=rejected}"),
+        [1.0]
+    );
+    assert_eq!(
+        capture.get("axum_xml.request.body_size"),
+        [OUTER_XML.len() as f64, "<Outer>".len() as f64]
+    );
+    assert_eq!(
+        capture.get("axum_xml.rejections.total{rejection=InvalidXMLBody,code=xml.parse.invalid}"),
+        [1.0]
+    );
+    assert_eq!(capture.get("axum_xml.responses.total"), [1.0]);
+    assert_eq!(
+        capture.get("axum_xml.response.body_size"),
+        [OUTER_XML.len() as f64]
+    );
+    assert!(capture.get("axum_xml.serialize_errors.total").is_empty());
+}
+
+#[cfg(feature = "form")]
+#[tokio::test]
+async fn xml_or_form() {
+    use crate::XmlOrForm;
+
+    #[derive(Deserialize)]
+    struct Notification {
+        event: String,
+        id: u64,
+    }
+
+    let app = Router::new().route(
+        "/",
+        post(
+            |XmlOrForm(notification): XmlOrForm<Notification>| async move {
+                format!("{} {}", notification.event, notification.id)
+            },
+        ),
+    );
+
+    let client = TestClient::new(app);
+    let send = |body: &'static str, content_type: &'static str| {
+        client
+            .post("/")
+            .body(body)
+            .header("content-type", content_type)
+            .send()
+    };
+
+    #[cfg(feature = "backend-quick-xml")]
+    const XML: &str = r#"<Notification event="paid" id="7"/>"#;
+    #[cfg(feature = "backend-serde-xml-rs")]
+    const XML: &str = "<Notification><event>paid</event><id>7</id></Notification>";
+
+    let res = send(XML, "application/xml").await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "paid 7");
+
+    let res = send("event=paid&id=7", "application/x-www-form-urlencoded")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "paid 7");
+
+    let res = send("event=paid&id=seven", "application/x-www-form-urlencoded")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res = send("{}", "application/json").await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("`application/x-www-form-urlencoded`"));
+}
+
+#[tokio::test]
+async fn deny_unknown_elements() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    let app = Router::new()
+        .route("/", post(|Xml(input): Xml<Input>| async move { input.foo }))
+        .route(
+            "/strict",
+            post(|Xml(input): Xml<Input>| async move { input.foo })
+                .layer(Extension(XmlConfig::new().with_deny_unknown_elements(true))),
+        );
+
+    let client = TestClient::new(app);
+    let send = |path: &'static str, body: &'static str| {
+        client
+            .post(path)
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    const EXTRA: &str = "<Input><foo>bar</foo><added>new</added></Input>";
+
+    let res = send("/", EXTRA).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+
+    let res = send("/strict", EXTRA).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("Unknown element `added`"));
+
+    let res = send("/strict", "<Input>\n  <foo>bar</foo>\n</Input>")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "bar");
+
+    // Namespace declarations and `xml:` attributes aren't fields, though serde-xml-rs reads
+    // `xml:lang` as `lang`.
+    let res = send(
+        "/strict",
+        r#"<Input xmlns="urn:input" xmlns:x="urn:x" xml:lang="en"><foo>bar</foo></Input>"#,
+    )
+    .await
+    .unwrap();
+    #[cfg(feature = "backend-quick-xml")]
+    assert_eq!(res.status(), StatusCode::OK);
+    #[cfg(feature = "backend-serde-xml-rs")]
+    assert!(res.text().await.unwrap().contains("Unknown element `lang`"));
+
+    let res = send("/strict", "<Input><bar>baz</bar></Input>")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let config = XmlConfig::new().with_deny_unknown_elements(true);
+    let rejection = crate::de::deserialize::<Input>(EXTRA.as_bytes(), None, &config).unwrap_err();
+    assert_eq!(rejection.code(), "xml.element.unknown");
+    assert!(matches!(rejection, XmlRejection::UnknownElement { name } if name == "added"));
+}
+
+#[tokio::test]
+async fn respond_with_collection() {
+    #[derive(Serialize)]
+    struct User {
+        name: String,
+    }
+
+    let names = || ["ann", "bob", "cy"].map(str::to_owned).to_vec();
+
+    let app = Router::new()
+        .route(
+            "/names",
+            get(move || async move { XmlCollection::new("names", "name", names()) }),
+        )
+        .route(
+            "/users",
+            get(move || async move {
+                let users = names().into_iter().map(|name| User { name }).collect();
+                XmlCollection::new("users", "user", users)
+                    .builder()
+                    .with_declaration()
+            }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/names").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    assert_eq!(
+        res.text().await.unwrap(),
+        "<names><name>ann</name><name>bob</name><name>cy</name></names>"
+    );
+
+    let res = client.get("/users").send().await.unwrap();
+    #[cfg(feature = "backend-quick-xml")]
+    const USERS: &str = r#"<users><user name="ann"/><user name="bob"/><user name="cy"/></users>"#;
+    #[cfg(feature = "backend-serde-xml-rs")]
+    const USERS: &str = "<users><user><name>ann</name></user><user><name>bob</name></user>\
+                         <user><name>cy</name></user></users>";
+    assert_eq!(
+        res.text().await.unwrap(),
+        format!(r#"<?xml version="1.0" encoding="UTF-8"?>{USERS}"#)
+    );
+}
+
+#[tokio::test]
+async fn syntax_errors_are_bad_requests() {
+    let app = Router::new().route("/", post(|_: Xml<Outer>| async {}));
+
+    let client = TestClient::new(app);
+    let send = |body: &'static str| {
+        client
+            .post("/")
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    for body in ["<Outer><inner", "<Outer><inner name=a/></Outer>", "<Outer>"] {
+        let res = send(body).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{body}");
+        assert!(res.text().await.unwrap().contains("Failed to parse"));
+    }
+
+    // Well-formedness errors the crate finds itself are bad requests too.
+    for (body, message) in [
+        ("<Outer><inner></Outer>", "does not match"),
+        ("<Outer/><Outer/>", "more than one root element"),
+    ] {
+        let res = send(body).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{body}");
+        assert!(res.text().await.unwrap().contains(message), "{body}");
+    }
+
+    let app = Router::new()
+        .route("/", post(|_: Xml<Outer>| async {}))
+        .layer(Extension(XmlConfig::new().forbid_comments(true)));
+    let client = TestClient::new(app);
+    let res = client
+        .post("/")
+        .body("<Outer><!-- no --></Outer>")
+        .header("content-type", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // Well-formed, but without the required `other`.
+    #[cfg(feature = "backend-quick-xml")]
+    const MISSING_FIELD: &str = r#"<Outer><inner name="a"/></Outer>"#;
+    #[cfg(feature = "backend-serde-xml-rs")]
+    const MISSING_FIELD: &str = "<Outer><inner><name>a</name></inner></Outer>";
+
+    let res = send(MISSING_FIELD).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(res.text().await.unwrap().contains("Failed to parse"));
+
+    let rejection = Xml::<Outer>::from_bytes(MISSING_FIELD.as_bytes()).unwrap_err();
+    assert!(rejection
+        .as_parse_error()
+        .unwrap()
+        .to_string()
+        .contains("other"));
+}
+
+#[tokio::test]
+async fn map_xml_value() {
+    #[derive(Deserialize)]
+    struct Input {
+        foo: String,
+    }
+
+    let app = Router::new()
+        .route(
+            "/",
+            post(|input: Xml<Input>| async move {
+                input.map(|input| Inner {
+                    name: input.foo.to_uppercase(),
+                })
+            }),
+        )
+        .route(
+            "/configured",
+            post(|Xml(input): Xml<Input>| async move {
+                Xml::with_root_name(input, "reply")
+                    .with_declaration()
+                    .status(StatusCode::CREATED)
+                    .header("x-mapped", "yes")
+                    .map(|input| Inner { name: input.foo })
+            }),
+        );
+
+    let client = TestClient::new(app);
+    let send = |uri: &'static str| {
+        client
+            .post(uri)
+            .body("<Input><foo>bar</foo></Input>")
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    #[cfg(feature = "backend-quick-xml")]
+    const INNER: (&str, &str) = (r#"<Inner name="BAR"/>"#, r#"<reply name="bar"/>"#);
+    #[cfg(feature = "backend-serde-xml-rs")]
+    const INNER: (&str, &str) = (
+        "<Inner><name>BAR</name></Inner>",
+        "<reply><name>bar</name></reply>",
+    );
+
+    let res = send("/").await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), INNER.0);
+
+    let res = send("/configured").await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(res.headers()["x-mapped"], "yes");
+    assert_eq!(
+        res.text().await.unwrap(),
+        format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, INNER.1)
+    );
+}
+
+#[tokio::test]
+async fn reject_invalid_utf8() {
+    #[derive(Debug, Deserialize)]
+    struct Input {
+        #[allow(dead_code)]
+        foo: String,
+    }
+
+    let app = Router::new().route("/", post(|_: Xml<Input>| async {}));
+
+    let client = TestClient::new(app);
+    let send = |body: &'static [u8]| {
+        client
+            .post("/")
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    // `\xC3` starts a two byte sequence, but `(` is no continuation byte.
+    let res = send(b"<Input>\n<foo>caf\xC3(</foo></Input>").await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = res.text().await.unwrap();
+    assert!(
+        body.contains("not valid UTF-8 at line 2, column 9 (byte 16)"),
+        "{body}"
+    );
+
+    let res = send(b"\xEF\xBB\xBF<Input><foo>\xFF</foo></Input>")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(res.text().await.unwrap().contains("(byte 15)"));
+
+    let rejection = Xml::<Input>::from_bytes(b"<Input><foo>\xE2\x82</foo></Input>").unwrap_err();
+    assert!(matches!(rejection, XmlRejection::InvalidUtf8 { .. }));
+    assert_eq!(rejection.code(), "xml.encoding.invalid_utf8");
+    assert_eq!(rejection.position(), Some(12));
+
+    let res = send("<Input><foo>café</foo></Input>".as_bytes())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn respond_with_option() {
+    use crate::XmlEncoder;
+
+    let app = Router::new()
+        .route(
+            "/some",
+            get(|| async { Xml(Some(Inner { name: "a".into() })) }),
+        )
+        .route("/none", get(|| async { Xml(None::<Inner>) }))
+        .route(
+            "/none-declared",
+            get(|| async {
+                Xml::with_declaration(None::<Inner>)
+                    .pretty()
+                    .header("x-found", "no")
+            }),
+        )
+        .route(
+            "/none-accepted",
+            get(|| async { Xml::builder(None::<Inner>).status(StatusCode::ACCEPTED) }),
+        );
+
+    let client = TestClient::new(app);
+
+    let res = client.get("/some").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    #[cfg(feature = "backend-quick-xml")]
+    assert_eq!(res.text().await.unwrap(), r#"<Inner name="a"/>"#);
+    #[cfg(feature = "backend-serde-xml-rs")]
+    assert_eq!(res.text().await.unwrap(), "<Inner><name>a</name></Inner>");
+
+    for uri in ["/none", "/none-declared"] {
+        let res = client.get(uri).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT, "{uri}");
+        assert!(!res.headers().contains_key("content-type"));
+        assert_eq!(res.text().await.unwrap(), "");
+    }
+
+    let res = client.get("/none-declared").send().await.unwrap();
+    assert_eq!(res.headers()["x-found"], "no");
+
+    let res = client.get("/none-accepted").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    assert_eq!(res.text().await.unwrap(), "");
+
+    let encoder = XmlEncoder::new().with_declaration();
+    assert!(encoder.encode(&None::<Inner>).unwrap().is_empty());
+}
+
+#[cfg(feature = "test-util")]
+#[tokio::test]
+async fn assert_xml_equivalence() {
+    use crate::test_util::assert_xml_eq;
+
+    assert_xml_eq(
+        br#"<user id="1" name="ann"></user>"#,
+        r#"<user name="ann" id="1"/>"#,
+    );
+    assert_xml_eq(
+        b"<?xml version=\"1.0\"?>\n<list>\n  <item>a &amp; b</item>\n  <!-- b -->\n  <item/>\n</list>\n",
+        "<list><item><![CDATA[a & b]]></item><item></item></list>",
+    );
+    assert_xml_eq(
+        b"<p>one <b>two</b> three</p>",
+        "<p>one <b>two</b> three</p>",
+    );
+    assert_xml_eq(
+        b"<text>split<!-- by a comment -->text</text>",
+        "<text>splittext</text>",
+    );
+
+    let app = Router::new().route("/", get(|| async { Xml::pretty(outer()) }));
+    let client = TestClient::new(app);
+    let res = client.get("/").send().await.unwrap();
+    assert_xml_eq(&res.bytes().await.unwrap(), OUTER_XML);
+
+    let differs = |actual: &'static str, expected: &'static str| {
+        std::panic::catch_unwind(|| assert_xml_eq(actual.as_bytes(), expected)).is_err()
+    };
+    assert!(differs(r#"<user id="1"/>"#, r#"<user id="2"/>"#));
+    assert!(differs("<user/>", r#"<user id="1"/>"#));
+    assert!(differs("<a><b/><c/></a>", "<a><c/><b/></a>"));
+    assert!(differs("<a>text</a>", "<a> text </a>"));
+    assert!(differs("<ns:a/>", "<a/>"));
+    assert!(differs("<a>", "<a/>"));
+}
+
+#[test]
+fn empty_and_missing_strings() {
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Profile {
+        nickname: Option<String>,
+        bio: String,
+    }
+
+    let profile = |nickname: Option<&str>, bio: &str| Profile {
+        nickname: nickname.map(str::to_owned),
+        bio: bio.to_owned(),
+    };
+
+    #[cfg(feature = "backend-quick-xml")]
+    let cases = [
+        (profile(None, "hi"), r#"<Profile bio="hi"/>"#),
+        (
+            profile(Some(""), "hi"),
+            r#"<Profile bio="hi"><nickname/></Profile>"#,
+        ),
+        (
+            profile(Some("ann"), ""),
+            r#"<Profile nickname="ann"><bio/></Profile>"#,
+        ),
+    ];
+    #[cfg(feature = "backend-serde-xml-rs")]
+    let cases = [
+        (profile(None, "hi"), "<Profile><bio>hi</bio></Profile>"),
+        (
+            profile(Some(""), "hi"),
+            "<Profile><nickname></nickname><bio>hi</bio></Profile>",
+        ),
+        (
+            profile(Some("ann"), ""),
+            "<Profile><nickname>ann</nickname><bio></bio></Profile>",
+        ),
+    ];
+
+    for (value, xml) in cases {
+        let bytes = Xml::to_bytes(&value).unwrap();
+        assert_eq!(String::from_utf8(bytes.clone()).unwrap(), xml);
+        let Xml(read) = Xml::<Profile>::from_bytes(&bytes).unwrap();
+        assert_eq!(read, value);
+    }
+
+    // Both empty element forms are the empty string, a missing element is `None`.
+    for (xml, nickname) in [
+        ("<Profile><nickname></nickname><bio/></Profile>", Some("")),
+        ("<Profile><nickname/><bio/></Profile>", Some("")),
+        (
+            "<Profile><nickname>ann</nickname><bio/></Profile>",
+            Some("ann"),
+        ),
+        ("<Profile><bio/></Profile>", None),
+    ] {
+        let Xml(read) = Xml::<Profile>::from_bytes(xml.as_bytes()).unwrap();
+        assert_eq!(read, profile(nickname, ""), "{xml}");
+    }
+}
+
+#[tokio::test]
+async fn require_xml_responses() {
+    use crate::RequireXmlResponseLayer;
+
+    let routes = || {
+        Router::new()
+            .route("/xml", get(|| async { Xml(outer()) }))
+            .route(
+                "/soap",
+                get(|| async { ([("content-type", "application/soap+xml")], "<Envelope/>") }),
+            )
+            .route("/text", get(|| async { "<Outer/>" }))
+            .route("/none", get(|| async { StatusCode::NO_CONTENT }))
+    };
+
+    let client = TestClient::new(routes().layer(RequireXmlResponseLayer::new()));
+
+    let res = client.get("/xml").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), OUTER_XML);
+
+    let res = client.get("/soap").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "<Envelope/>");
+
+    let res = client.get("/text").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(res
+        .text()
+        .await
+        .unwrap()
+        .contains("the response is not XML"));
+
+    let res = client.get("/none").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    // The empty 404 of the fallback has no body to check.
+    let res = client.get("/missing").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let client = TestClient::new(routes().layer(RequireXmlResponseLayer::new().with_relabel(true)));
+
+    let res = client.get("/text").send().await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "application/xml");
+    assert_eq!(res.text().await.unwrap(), "<Outer/>");
+
+    let res = client.get("/soap").send().await.unwrap();
+    assert_eq!(res.headers()["content-type"], "application/soap+xml");
+}
+
+#[tokio::test]
+async fn custom_entities() {
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Note {
+        #[serde(rename = "$value")]
+        text: String,
+    }
+
+    let config = XmlConfig::new().with_entities([("copy", "©"), ("company", "R&D <Labs>")]);
+    let app = Router::new()
+        .route("/", post(|Xml(note): Xml<Note>| async { note.text }))
+        .layer(Extension(config.clone()));
+
+    let client = TestClient::new(app);
+    let send = |body: &'static str| {
+        client
+            .post("/")
+            .body(body)
+            .header("content-type", "application/xml")
+            .send()
+    };
+
+    let res = send("<Note>&copy; 2024 &company; &amp; co</Note>")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "© 2024 R&D <Labs> & co");
+
+    // References in CDATA sections are no references at all.
+    let res = send("<Note><![CDATA[&copy;]]></Note>").await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "&copy;");
+
+    // Entities that aren't registered are rejected as before.
+    let res = send("<Note>&nbsp;</Note>").await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    #[derive(Debug, Deserialize)]
+    struct Signed {
+        by: String,
+    }
+    #[cfg(feature = "backend-quick-xml")]
+    const SIGNED: &str = r#"<Signed by="&company;"/>"#;
+    #[cfg(feature = "backend-serde-xml-rs")]
+    const SIGNED: &str = "<Signed><by>&company;</by></Signed>";
+    let Xml(signed) = Xml::<Signed>::from_bytes_with_config(SIGNED.as_bytes(), &config).unwrap();
+    assert_eq!(signed.by, "R&D <Labs>");
+
+    let rejection = Xml::<Note>::from_bytes_with_config(
+        "<Note>&company;&company;&company;</Note>".as_bytes(),
+        &config.clone().with_entity_limit(16, 8),
+    )
+    .unwrap_err();
+    assert!(matches!(rejection, XmlRejection::EntityExpansionLimit));
+
+    let bytes = XmlEncoder::new()
+        .with_entities([("copy", "©"), ("company", "R&D <Labs>")])
+        .encode(&Note {
+            text: "© R&D <Labs>".to_owned(),
+        })
+        .unwrap();
+    assert_eq!(bytes, b"<Note>&copy; &company;</Note>");
+    let Xml(read) = Xml::<Note>::from_bytes_with_config(&bytes, &config).unwrap();
+    assert_eq!(read.text, "© R&D <Labs>");
+
+    // Replacement text isn't looked for inside other references.
+    let bytes = XmlEncoder::new()
+        .with_entities([("m", "m")])
+        .encode(&Note {
+            text: "me & you".to_owned(),
+        })
+        .unwrap();
+    assert_eq!(bytes, b"<Note>&m;e &amp; you</Note>");
+}
+
+#[test]
+#[should_panic(expected = "predefined")]
+fn predefined_entities_are_not_registered() {
+    let _ = XmlConfig::new().with_entities([("amp", "and")]);
 }
@@ -0,0 +1,238 @@
+use quick_xml::{
+    events::{attributes::Attribute, BytesCData, BytesEnd, BytesStart, BytesText, Event},
+    name::QName,
+    Reader, Writer,
+};
+use std::borrow::Cow;
+
+/// Remove the comments and processing instructions from a document.
+///
+/// The deserializer skips them, but stops reading a text node at a comment, so that in
+/// `<name>Ann<!-- - -->e</name>` the `e` would be lost. Text on both sides of a removed comment
+/// is joined. Documents that can't be read are returned untouched.
+pub(crate) fn strip_comments(xml: &[u8]) -> Cow<'_, [u8]> {
+    let mut reader = Reader::from_reader(xml);
+    let mut writer = Writer::new(Vec::with_capacity(xml.len()));
+
+    loop {
+        let written = match reader.read_event() {
+            Ok(Event::Eof) => return Cow::Owned(writer.into_inner()),
+            Ok(Event::Comment(_) | Event::PI(_)) => continue,
+            Ok(event) => writer.write_event(event),
+            Err(_) => return Cow::Borrowed(xml),
+        };
+        if written.is_err() {
+            return Cow::Borrowed(xml);
+        }
+    }
+}
+
+/// Prefix the name of every attribute with `@`, except for namespace declarations.
+///
+/// Documents that can't be read are returned untouched.
+pub(crate) fn prefix_attributes(xml: &[u8]) -> Cow<'_, [u8]> {
+    let mut reader = Reader::from_reader(xml);
+    let mut writer = Writer::new(Vec::with_capacity(xml.len()));
+    let prefix = |key: &[u8]| {
+        if key == b"xmlns" || key.starts_with(b"xmlns:") {
+            None
+        } else {
+            Some([b"@", key].concat())
+        }
+    };
+
+    loop {
+        let event = match reader.read_event() {
+            Ok(Event::Eof) => return Cow::Owned(writer.into_inner()),
+            Ok(Event::Start(start)) => rename_attributes(&start, prefix).map(Event::Start),
+            Ok(Event::Empty(start)) => rename_attributes(&start, prefix).map(Event::Empty),
+            Ok(event) => Some(event),
+            Err(_) => None,
+        };
+        match event.map(|event| writer.write_event(event)) {
+            Some(Ok(())) => {}
+            _ => return Cow::Borrowed(xml),
+        }
+    }
+}
+
+/// Drop the namespace prefix from the names of elements and attributes, except for namespace
+/// declarations and `xml:` attributes.
+///
+/// Documents that can't be read are returned untouched.
+pub(crate) fn strip_prefixes(xml: &[u8]) -> Cow<'_, [u8]> {
+    let mut reader = Reader::from_reader(xml);
+    let mut writer = Writer::new(Vec::with_capacity(xml.len()));
+    let strip = |key: &[u8]| {
+        if key.starts_with(b"xmlns:") || key.starts_with(b"xml:") {
+            None
+        } else {
+            local_name(key).map(<[u8]>::to_vec)
+        }
+    };
+    let unprefixed = |start: &BytesStart<'_>| {
+        let mut renamed = rename_attributes(start, strip)?;
+        if let Some(name) = local_name(start.name().into_inner()) {
+            renamed.set_name(name);
+        }
+        Some(renamed)
+    };
+
+    loop {
+        let event = match reader.read_event() {
+            Ok(Event::Eof) => return Cow::Owned(writer.into_inner()),
+            Ok(Event::Start(start)) => unprefixed(&start).map(Event::Start),
+            Ok(Event::Empty(start)) => unprefixed(&start).map(Event::Empty),
+            Ok(Event::End(end)) => match local_name(end.name().into_inner()) {
+                Some(name) => std::str::from_utf8(name)
+                    .ok()
+                    .map(|name| Event::End(BytesEnd::new(name.to_owned()))),
+                None => Some(Event::End(end)),
+            },
+            Ok(event) => Some(event),
+            Err(_) => None,
+        };
+        match event.map(|event| writer.write_event(event)) {
+            Some(Ok(())) => {}
+            _ => return Cow::Borrowed(xml),
+        }
+    }
+}
+
+/// The part of a prefixed `name` after the colon, or `None` if it has no prefix.
+fn local_name(name: &[u8]) -> Option<&[u8]> {
+    let colon = name.iter().position(|&byte| byte == b':')?;
+    Some(&name[colon + 1..])
+}
+
+/// Copy `start` with each attribute renamed to what `rename` returns for its name, if anything.
+///
+/// Returns `None` if the attributes can't be read.
+pub(crate) fn rename_attributes(
+    start: &BytesStart<'_>,
+    rename: impl Fn(&[u8]) -> Option<Vec<u8>>,
+) -> Option<BytesStart<'static>> {
+    let mut renamed = start.clone().into_owned();
+    renamed.clear_attributes();
+
+    for attribute in start.attributes() {
+        let attribute = attribute.ok()?;
+        let key = rename(attribute.key.as_ref());
+        renamed.push_attribute(Attribute {
+            key: key.as_deref().map_or(attribute.key, QName),
+            value: attribute.value,
+        });
+    }
+    Some(renamed)
+}
+
+/// Rewrite the text content of a document as CDATA sections.
+///
+/// The deserializer trims the whitespace around every text node, but leaves CDATA sections
+/// as they are, so this keeps the whitespace. Only text that is an element's content is
+/// rewritten, not the indentation between elements. Documents that can't be read are returned
+/// untouched, for the deserializer to report the error.
+pub(crate) fn preserve_whitespace(xml: &[u8]) -> Cow<'_, [u8]> {
+    match rewrite(xml, true) {
+        Some(rewritten) => Cow::Owned(rewritten),
+        None => Cow::Borrowed(xml),
+    }
+}
+
+/// Rewrite the text content of elements marked `xml:space="preserve"` as CDATA sections, as
+/// [`preserve_whitespace`] does for the whole document.
+///
+/// The marking is inherited by child elements, up to one marked `xml:space="default"`.
+pub(crate) fn preserve_marked_whitespace(xml: &[u8]) -> Cow<'_, [u8]> {
+    match rewrite(xml, false) {
+        Some(rewritten) => Cow::Owned(rewritten),
+        None => Cow::Borrowed(xml),
+    }
+}
+
+fn rewrite(xml: &[u8], preserve_all: bool) -> Option<Vec<u8>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut writer = Writer::new(Vec::with_capacity(xml.len()));
+    // Whitespace held back until it's known whether it's all there is to an element.
+    let mut pending: Option<BytesText<'_>> = None;
+    let mut after_start = false;
+    // Whether each open element keeps its whitespace, from its `xml:space` or its parent's.
+    let mut preserving: Vec<bool> = Vec::new();
+
+    loop {
+        let event = reader.read_event().ok()?;
+        if let Some(text) = pending.take() {
+            if matches!(event, Event::End(_)) {
+                write_cdata(&mut writer, &text)?;
+            } else {
+                writer.write_event(Event::Text(text)).ok()?;
+            }
+        }
+
+        let preserve = preserve_all || preserving.last().copied().unwrap_or(false);
+        match event {
+            Event::Eof => return Some(writer.into_inner()),
+            Event::Text(text) if !preserve => {
+                writer.write_event(Event::Text(text)).ok()?;
+                after_start = false;
+            }
+            Event::Text(text) if text.iter().all(u8::is_ascii_whitespace) => {
+                if after_start {
+                    pending = Some(text);
+                } else {
+                    writer.write_event(Event::Text(text)).ok()?;
+                }
+                after_start = false;
+            }
+            Event::Text(text) => {
+                write_cdata(&mut writer, &text)?;
+                after_start = false;
+            }
+            event => {
+                match &event {
+                    Event::Start(start) => {
+                        preserving.push(xml_space(start)?.unwrap_or(preserve));
+                    }
+                    Event::End(_) => {
+                        preserving.pop();
+                    }
+                    _ => {}
+                }
+                after_start = matches!(event, Event::Start(_));
+                writer.write_event(event).ok()?;
+            }
+        }
+    }
+}
+
+/// Whether the `xml:space` attribute of `start` asks to keep the whitespace, if it has one.
+///
+/// Returns `None` if the attributes can't be read.
+fn xml_space(start: &BytesStart<'_>) -> Option<Option<bool>> {
+    for attribute in start.attributes() {
+        let attribute = attribute.ok()?;
+        if attribute.key.as_ref() == b"xml:space" {
+            return Some(match attribute.value.as_ref() {
+                b"preserve" => Some(true),
+                b"default" => Some(false),
+                _ => None,
+            });
+        }
+    }
+    Some(None)
+}
+
+/// Write `text` unescaped inside CDATA.
+///
+/// Text containing `]]>` can't be put in a single CDATA section, and the deserializer only
+/// reads the first of several, so such text is left as it is and gets trimmed.
+fn write_cdata(writer: &mut Writer<Vec<u8>>, text: &BytesText<'_>) -> Option<()> {
+    let unescaped = text.unescape().ok()?;
+    let event = if unescaped.contains("]]>") {
+        Event::Text(text.clone())
+    } else {
+        Event::CData(BytesCData::new(unescaped))
+    };
+
+    writer.write_event(event).ok()
+}
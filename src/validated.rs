@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use axum_core::{extract::FromRequest, BoxError};
+use http::Request;
+use http_body::Body as HttpBody;
+use serde::de::DeserializeOwned;
+use std::ops::{Deref, DerefMut};
+use validator::Validate;
+
+use crate::{rejection::XmlRejection, Xml};
+
+/// XML Extractor which validates the deserialized value.
+///
+/// Extracts the body like [`Xml`] does, then runs [`Validate::validate`] on it. Values that
+/// fail validation are rejected with [`XmlRejection::Validation`], a `422 Unprocessable Entity`
+/// listing the failed constraints. Requires the `validator` feature.
+///
+/// ```rust,no_run
+/// use axum::{routing::post, Router};
+/// use serde::Deserialize;
+/// use validator::Validate;
+/// use rustcms_axum_xml::ValidatedXml;
+///
+/// #[derive(Deserialize, Validate)]
+/// struct CreateUser {
+///     #[validate(email)]
+///     email: String,
+/// }
+///
+/// async fn create_user(ValidatedXml(payload): ValidatedXml<CreateUser>) {
+///     // payload.email is a valid email address
+/// }
+///
+/// let app: Router = Router::new().route("/users", post(create_user));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedXml<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for ValidatedXml<T>
+where
+    T: DeserializeOwned + Validate,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = XmlRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let Xml(value) = Xml::<T>::from_request(req, state).await?;
+        value.validate()?;
+
+        Ok(Self(value))
+    }
+}
+
+impl<T> Deref for ValidatedXml<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for ValidatedXml<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
@@ -0,0 +1,269 @@
+use async_trait::async_trait;
+use axum_core::{
+    extract::FromRequest,
+    response::{IntoResponse, Response},
+    BoxError,
+};
+use http::{
+    header::{self, HeaderValue},
+    Request,
+};
+use http_body::Body as HttpBody;
+use quick_xml::{
+    events::{BytesStart, BytesText, Event},
+    Reader, Writer,
+};
+
+use crate::{backend, config::XmlConfig, de::Document, rejection::XmlRejection, xml_content_type};
+
+/// Node of an XML document of any shape.
+///
+/// The dynamic counterpart of a `#[derive(Deserialize)]` type, for documents whose structure
+/// isn't known up front. Comments and processing instructions are not kept, and CDATA
+/// sections are read as text. Extract one with [`DynXml`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum XmlValue {
+    /// An element with its attributes, in document order, and its content.
+    Element {
+        name: String,
+        attributes: Vec<(String, String)>,
+        children: Vec<XmlValue>,
+    },
+    /// Text content, unescaped.
+    Text(String),
+}
+
+impl XmlValue {
+    /// Create an element without attributes or content.
+    pub fn element(name: impl Into<String>) -> Self {
+        XmlValue::Element {
+            name: name.into(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Name of the element, or `None` for text.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            XmlValue::Element { name, .. } => Some(name),
+            XmlValue::Text(_) => None,
+        }
+    }
+
+    /// Value of the attribute called `name`, if this is an element that has it.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        match self {
+            XmlValue::Element { attributes, .. } => attributes
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str()),
+            XmlValue::Text(_) => None,
+        }
+    }
+
+    /// Content of the element, or nothing for text.
+    pub fn children(&self) -> &[XmlValue] {
+        match self {
+            XmlValue::Element { children, .. } => children,
+            XmlValue::Text(_) => &[],
+        }
+    }
+
+    /// The child elements called `name`.
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlValue> {
+        self.children()
+            .iter()
+            .filter(move |child| child.name() == Some(name))
+    }
+
+    /// All the text in this node and below it, joined together.
+    pub fn text(&self) -> String {
+        match self {
+            XmlValue::Element { children, .. } => children.iter().map(XmlValue::text).collect(),
+            XmlValue::Text(text) => text.clone(),
+        }
+    }
+
+    /// Parse a UTF-8 document, with the text of every node trimmed if `trim`.
+    pub(crate) fn parse(bytes: &[u8], trim: bool) -> Result<Self, XmlRejection> {
+        let mut reader = Reader::from_reader(bytes);
+        // Elements opened but not yet closed, innermost last.
+        let mut open: Vec<XmlValue> = Vec::new();
+        let mut root = None;
+
+        let error = |reader: &Reader<&[u8]>, err: &dyn std::fmt::Display| {
+            XmlRejection::invalid_body(backend::syntax_error(err), bytes, reader.buffer_position())
+        };
+
+        loop {
+            let node = match reader.read_event() {
+                Ok(Event::Start(start)) => {
+                    open.push(element(&start).map_err(|err| error(&reader, &err))?);
+                    continue;
+                }
+                Ok(Event::Empty(start)) => element(&start).map_err(|err| error(&reader, &err))?,
+                Ok(Event::End(_)) => match open.pop() {
+                    Some(element) => element,
+                    None => return Err(error(&reader, &"unexpected end tag")),
+                },
+                Ok(Event::Text(text)) => {
+                    let text = text.unescape().map_err(|err| error(&reader, &err))?;
+                    let text = if trim { text.trim() } else { &text };
+                    if text.is_empty() || open.is_empty() {
+                        continue;
+                    }
+                    XmlValue::Text(text.to_owned())
+                }
+                Ok(Event::CData(cdata)) => {
+                    let text = std::str::from_utf8(&cdata).map_err(|err| error(&reader, &err))?;
+                    XmlValue::Text(text.to_owned())
+                }
+                Ok(Event::Eof) => break,
+                Ok(_) => continue,
+                Err(err) => return Err(error(&reader, &err)),
+            };
+
+            match open.last_mut() {
+                Some(XmlValue::Element { children, .. }) => children.push(node),
+                _ if root.is_none() => root = Some(node),
+                _ => return Err(error(&reader, &"more than one root element")),
+            }
+        }
+
+        match root {
+            Some(root) if open.is_empty() => Ok(root),
+            _ => Err(error(&reader, &"unexpected end of document")),
+        }
+    }
+
+    /// Write this node and everything below it through `writer`.
+    fn write(&self, writer: &mut Writer<Vec<u8>>) -> quick_xml::Result<()> {
+        match self {
+            XmlValue::Element {
+                name,
+                attributes,
+                children,
+            } => {
+                let start = BytesStart::new(name.as_str()).with_attributes(
+                    attributes
+                        .iter()
+                        .map(|(key, value)| (key.as_str(), value.as_str())),
+                );
+                if children.is_empty() {
+                    return writer.write_event(Event::Empty(start));
+                }
+
+                let end = start.to_end().into_owned();
+                writer.write_event(Event::Start(start))?;
+                for child in children {
+                    child.write(writer)?;
+                }
+                writer.write_event(Event::End(end))
+            }
+            XmlValue::Text(text) => writer.write_event(Event::Text(BytesText::new(text))),
+        }
+    }
+
+    /// Serialize the node as a document.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::new(Vec::new());
+        self.write(&mut writer)
+            .expect("writing to a `Vec` never fails");
+        writer.into_inner()
+    }
+}
+
+fn element(start: &BytesStart<'_>) -> Result<XmlValue, quick_xml::Error> {
+    let name = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+    let attributes = start
+        .attributes()
+        .map(|attribute| {
+            let attribute = attribute?;
+            Ok((
+                String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+                attribute.unescape_value()?.into_owned(),
+            ))
+        })
+        .collect::<quick_xml::Result<_>>()?;
+
+    Ok(XmlValue::Element {
+        name,
+        attributes,
+        children: Vec::new(),
+    })
+}
+
+/// XML Extractor / Response for documents of any shape, as an [`XmlValue`] tree.
+///
+/// As an extractor it applies the same `Content-Type` check and limits as [`Xml`](crate::Xml)
+/// and reads any well-formed document. As a response it writes the tree back as compact XML
+/// with `Content-Type: application/xml`.
+///
+/// ```rust,no_run
+/// use axum::{routing::post, Router};
+/// use rustcms_axum_xml::{DynXml, XmlValue};
+///
+/// async fn tag(DynXml(mut document): DynXml) -> DynXml {
+///     if let XmlValue::Element { attributes, .. } = &mut document {
+///         attributes.push(("checked".to_owned(), "true".to_owned()));
+///     }
+///     DynXml(document)
+/// }
+///
+/// let app: Router = Router::new().route("/tag", post(tag));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DynXml(pub XmlValue);
+
+#[async_trait]
+impl<S, B> FromRequest<S, B> for DynXml
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = XmlRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let config = XmlConfig::from_extensions(req.extensions());
+        let request_id = config.request_id(req.headers());
+
+        let extracted: Result<Self, XmlRejection> = async move {
+            if xml_content_type(req.headers(), &config) {
+                let document = Document::from_request(req, state, &config).await?;
+                let checked = document.into_checked(&config)?;
+
+                match XmlValue::parse(&checked.bytes, config.trim_text()) {
+                    Ok(value) => Ok(Self(value)),
+                    Err(rejection) if checked.as_sent => Err(rejection),
+                    Err(rejection) => Err(rejection.without_position()),
+                }
+            } else {
+                Err(config.missing_content_type(&req))
+            }
+        }
+        .await;
+        extracted.map_err(|rejection| rejection.with_request_id(request_id))
+    }
+}
+
+impl IntoResponse for DynXml {
+    fn into_response(self) -> Response {
+        (
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/xml"),
+            )],
+            self.0.to_bytes(),
+        )
+            .into_response()
+    }
+}
+
+impl From<XmlValue> for DynXml {
+    fn from(value: XmlValue) -> Self {
+        Self(value)
+    }
+}
@@ -0,0 +1,170 @@
+//! `xsi:nil` support for `Option` fields.
+//!
+//! Many XML schemas represent a missing value as `<field xsi:nil="true"/>` rather than by
+//! leaving the element out. Mark an `Option` field with
+//! `#[serde(serialize_with = "rustcms_axum_xml::xsi_nil::serialize")]` to write `None` that
+//! way, and turn on [`XmlConfig::with_xsi_nil`](crate::XmlConfig::with_xsi_nil) to read such
+//! elements back as `None`.
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Reading {
+//!     #[serde(serialize_with = "rustcms_axum_xml::xsi_nil::serialize")]
+//!     celsius: Option<i32>,
+//! }
+//! ```
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    name::{Namespace, ResolveResult},
+    NsReader, Reader, Writer,
+};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::borrow::Cow;
+
+use crate::backend::{self, Error};
+
+/// Namespace of the `xsi` prefix.
+const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
+/// Name of the newtype struct [`serialize`] writes for `None`, which the response serializer
+/// turns into [`NilElement`]. No Rust type can have this name.
+pub(crate) const NIL: &str = "$xsi:nil";
+
+/// Serialize `None` as an element with `xsi:nil="true"` and `Some` as the value itself.
+///
+/// The `xsi` prefix is declared on the root element of documents that contain a nil element.
+pub fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    match value {
+        Some(value) => serializer.serialize_some(value),
+        None => serializer.serialize_newtype_struct(NIL, &()),
+    }
+}
+
+/// Element with `xsi:nil="true"` and no content, named after the field it is written for.
+pub(crate) struct NilElement;
+
+impl Serialize for NilElement {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // serde-xml-rs only writes fields as attributes when they are marked with an `@`.
+        let key = if cfg!(feature = "backend-quick-xml") {
+            "xsi:nil"
+        } else {
+            "@xsi:nil"
+        };
+        let mut element = serializer.serialize_struct("", 1)?;
+        element.serialize_field(key, "true")?;
+        element.end()
+    }
+}
+
+/// Declare the `xsi` prefix on the root of `xml`, serialized XML, if an element has an
+/// `xsi:nil` attribute and the root doesn't declare the prefix already.
+pub(crate) fn declare_xsi(xml: Vec<u8>) -> Result<Vec<u8>, Error> {
+    if !xml.windows(7).any(|window| window == b"xsi:nil") {
+        return Ok(xml);
+    }
+
+    let mut reader = Reader::from_reader(&xml[..]);
+    let mut root = None;
+    let mut has_nil = false;
+    while !has_nil {
+        let start = reader.buffer_position();
+        let event = reader.read_event().map_err(backend::writer_error)?;
+        if let Event::Start(element) | Event::Empty(element) = &event {
+            has_nil = element
+                .attributes()
+                .with_checks(false)
+                .flatten()
+                .any(|attribute| attribute.key.as_ref() == b"xsi:nil");
+            if root.is_none() {
+                root = Some((event.into_owned(), start, reader.buffer_position()));
+            }
+        } else if let Event::Eof = event {
+            break;
+        }
+    }
+
+    let (mut event, start, end) = match root {
+        Some(root) if has_nil => root,
+        _ => return Ok(xml),
+    };
+    if let Event::Start(element) | Event::Empty(element) = &mut event {
+        if declares_xsi(element) {
+            return Ok(xml);
+        }
+        element.push_attribute(("xmlns:xsi", XSI_NAMESPACE));
+    }
+
+    let mut writer = Writer::new(Vec::with_capacity(xml.len() + XSI_NAMESPACE.len() + 12));
+    writer.inner().extend_from_slice(&xml[..start]);
+    writer.write_event(event).map_err(backend::writer_error)?;
+    writer.inner().extend_from_slice(&xml[end..]);
+    Ok(writer.into_inner())
+}
+
+fn declares_xsi(root: &BytesStart<'_>) -> bool {
+    root.attributes()
+        .with_checks(false)
+        .flatten()
+        .any(|attribute| attribute.key.as_ref() == b"xmlns:xsi")
+}
+
+/// Remove the elements below the root that carry `xsi:nil="true"`, so that deserializing
+/// leaves their fields `None`.
+///
+/// Documents that can't be read are returned untouched.
+pub(crate) fn drop_nil_elements(xml: &[u8]) -> Cow<'_, [u8]> {
+    let mut reader = NsReader::from_reader(xml);
+    let mut writer = Writer::new(Vec::with_capacity(xml.len()));
+    let mut depth = 0usize;
+
+    loop {
+        let written = match reader.read_event() {
+            Ok(Event::Eof) => return Cow::Owned(writer.into_inner()),
+            Ok(Event::Start(start)) if depth > 0 && is_nil(&reader, &start) => {
+                match reader.read_to_end(start.name()) {
+                    Ok(_) => continue,
+                    Err(_) => return Cow::Borrowed(xml),
+                }
+            }
+            Ok(Event::Empty(start)) if depth > 0 && is_nil(&reader, &start) => continue,
+            Ok(event) => {
+                match &event {
+                    Event::Start(_) => depth += 1,
+                    Event::End(_) => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                writer.write_event(event)
+            }
+            Err(_) => return Cow::Borrowed(xml),
+        };
+        if written.is_err() {
+            return Cow::Borrowed(xml);
+        }
+    }
+}
+
+/// Whether `start` has an `xsi:nil` attribute set to `true`, with whichever prefix `reader`
+/// has bound to the XML Schema instance namespace.
+fn is_nil(reader: &NsReader<&[u8]>, start: &BytesStart<'_>) -> bool {
+    start
+        .attributes()
+        .with_checks(false)
+        .flatten()
+        .any(|attribute| {
+            let (namespace, name) = reader.resolve_attribute(attribute.key);
+            name.as_ref() == b"nil"
+                && namespace == ResolveResult::Bound(Namespace(XSI_NAMESPACE.as_bytes()))
+                && matches!(&*attribute.value, b"true" | b"1")
+        })
+}